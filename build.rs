@@ -5,6 +5,23 @@ const COMMANDS: &[&str] = &[
     "send_analytics_page",
     "send_analytics_screen",
     "send_analytics_track",
+    "send_analytics_batch",
+    "get_consent_state",
+    "should_prompt_consent",
+    "prompt_consent",
+    "answer_consent",
+    "set_consent_category",
+    "get_metrics",
+    "is_analytics_allowed",
+    "check_analytics_connectivity",
+    "get_audit_log",
+    "increment_trait_counter",
+    "export_analytics_data",
+    "wipe_analytics_data",
+    "get_analytics_status",
+    "track_page_view",
+    "report_activity",
+    "set_analytics_user_id",
 ];
 
 fn main() {
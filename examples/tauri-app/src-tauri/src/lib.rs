@@ -1,14 +1,70 @@
+use serde_json::json;
+use tauri_plugin_rudderstack::{
+    types::Track, AnalyticsExt, ConsentPolicy, DestinationConsent, EventDefinition, Metrics,
+    RudderStackBuilder, TrackEvent,
+};
+
+/// A strongly typed event, the fields are sent as the event properties.
+#[derive(serde::Serialize, specta::Type)]
+struct Greeted {
+    name: String,
+}
+
+impl TrackEvent for Greeted {
+    const NAME: &'static str = "Greeted";
+}
+
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
-fn greet(name: &str) -> String {
+fn greet(app: tauri::AppHandle, name: &str) -> String {
+    app.track(Greeted {
+        name: name.to_string(),
+    });
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// Used by the event inspector to show the delivery counters.
+#[tauri::command]
+fn inspector_metrics(app: tauri::AppHandle) -> Metrics {
+    app.metrics()
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let data_plane = option_env!("RUDDERSTACK_DATA_PLANE")
+        .unwrap_or("https://example.dataplane.rudderstack.com");
+    let key = option_env!("RUDDERSTACK_WRITE_KEY").unwrap_or("example-write-key");
+
+    let rudderstack = RudderStackBuilder::new(data_plane, key)
+        .first_run(true)
+        .with_context(|context| {
+            context.insert("example".to_string(), json!(true));
+        })
+        // only these events can be sent from the webview
+        .allowed_events(["Button Clicked", "Greeted", "First Run"])
+        // an event that was retired in 0.1.0
+        .register_event(EventDefinition::new("Legacy Clicked").max_version("0.0.9"))
+        // events are only sent once the user granted consent
+        .consent_policy(ConsentPolicy::new(1).essential_events(["First Run"]))
+        // disable marketing destinations unless the user granted the category
+        .transformer(DestinationConsent::new().category("marketing", ["Braze"]))
+        .sample_rate(1.0)
+        .capture_recent_events(50)
+        .event_log(1024 * 1024, 3)
+        // log the payloads instead of sending them when no write key is configured
+        .dry_run(option_env!("RUDDERSTACK_WRITE_KEY").is_none())
+        .build();
+
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![greet])
-        .plugin(tauri_plugin_analytics::init())
+        .plugin(rudderstack)
+        .invoke_handler(tauri::generate_handler![greet, inspector_metrics])
+        .setup(|app| {
+            app.send_analytic_track(Track {
+                event: "App Started".to_string(),
+                ..Track::default()
+            });
+            Ok(())
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -41,12 +41,152 @@ async sendAnalyticsScreen(event: Screen) : Promise<void> {
  */
 async sendAnalyticsTrack(event: Track) : Promise<void> {
     await TAURI_INVOKE("plugin:rudderstack|send_analytics_track", { event });
+},
+/**
+ * Send a [Batch] of track, page and screen events to the RudderStack data plane in one request. \
+ * Other events, and track events that are not in the allowed events, are removed from the batch.
+ */
+async sendAnalyticsBatch(event: Batch) : Promise<void> {
+    await TAURI_INVOKE("plugin:rudderstack|send_analytics_batch", { event });
+},
+/**
+ * Get the persisted consent state of the user.
+ */
+async getConsentState() : Promise<ConsentState> {
+    return await TAURI_INVOKE("plugin:rudderstack|get_consent_state");
+},
+/**
+ * Returns true if the user should be prompted for consent.
+ */
+async shouldPromptConsent() : Promise<boolean> {
+    return await TAURI_INVOKE("plugin:rudderstack|should_prompt_consent");
+},
+/**
+ * Mark that the user is being prompted for consent.
+ */
+async promptConsent() : Promise<Result<ConsentState, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("plugin:rudderstack|prompt_consent") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Record the answer of the user to the consent prompt.
+ */
+async answerConsent(granted: boolean) : Promise<Result<ConsentState, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("plugin:rudderstack|answer_consent", { granted }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Record the consent of the user for a category, e.g. `marketing`.
+ */
+async setConsentCategory(category: string, granted: boolean) : Promise<ConsentState> {
+    return await TAURI_INVOKE("plugin:rudderstack|set_consent_category", { category, granted });
+},
+/**
+ * Get a snapshot of the delivery counters: events sent, failed, dropped and queued.
+ */
+async getMetrics() : Promise<Metrics> {
+    return await TAURI_INVOKE("plugin:rudderstack|get_metrics");
+},
+/**
+ * Returns true if non-essential events are currently delivered to the data plane,
+ * taking consent, mock and dry run mode into account.
+ */
+async isAnalyticsAllowed() : Promise<boolean> {
+    return await TAURI_INVOKE("plugin:rudderstack|is_analytics_allowed");
+},
+/**
+ * Check that the data plane is reachable, without sending an event.
+ */
+async checkAnalyticsConnectivity() : Promise<Result<Connectivity, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("plugin:rudderstack|check_analytics_connectivity") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Get the log of identity and consent changes on this device, oldest first.
+ */
+async getAuditLog() : Promise<AuditEntry[]> {
+    return await TAURI_INVOKE("plugin:rudderstack|get_audit_log");
+},
+/**
+ * Increment a persisted lifetime counter, e.g. `total_exports`, and return its new value.
+ * The counters are included in the traits of every identify event.
+ * The returned value is clamped to `4294967295` (`u32::MAX`), which a JavaScript number
+ * represents exactly, the counter itself keeps counting.
+ */
+async incrementTraitCounter(name: string) : Promise<number> {
+    return await TAURI_INVOKE("plugin:rudderstack|increment_trait_counter", { name });
+},
+/**
+ * Get all analytics data stored on this device, for data subject access requests.
+ */
+async exportAnalyticsData() : Promise<AnalyticsData> {
+    return await TAURI_INVOKE("plugin:rudderstack|export_analytics_data");
+},
+/**
+ * Delete all analytics data stored on this device, for data subject deletion requests.
+ * Subsequent events use a new anonymous ID.
+ */
+async wipeAnalyticsData() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("plugin:rudderstack|wipe_analytics_data") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Get the status of the plugin, including an unexpected reset of the anonymous ID detected on startup.
+ */
+async getAnalyticsStatus() : Promise<AnalyticsStatus> {
+    return await TAURI_INVOKE("plugin:rudderstack|get_analytics_status");
+},
+/**
+ * Send a Page event for a navigation of the webview, called by the automatic page tracking.
+ */
+async trackPageView(url: string, title: string | null) : Promise<void> {
+    await TAURI_INVOKE("plugin:rudderstack|track_page_view", { url, title });
+},
+/**
+ * Record user input in the webview, called by the idle detection.
+ */
+async reportActivity() : Promise<void> {
+    await TAURI_INVOKE("plugin:rudderstack|report_activity");
+},
+/**
+ * Set the user ID sent with the following events, `None` to clear it, e.g. on sign out. \
+ * The `identify` event, e.g. with the traits of the user, is sent instead of the Identify event
+ * of a new user, and also if the user ID was set before.
+ */
+async setAnalyticsUserId(userId: string | null, identify: Identify | null) : Promise<void> {
+    await TAURI_INVOKE("plugin:rudderstack|set_analytics_user_id", { userId, identify });
 }
 }
 
 /** user-defined events **/
 
 
+export const events = __makeEvents__<{
+eventDropped: EventDropped,
+eventFailed: EventFailed,
+eventSent: EventSent
+}>({
+eventDropped: "plugin:rudderstack:event-dropped",
+eventFailed: "plugin:rudderstack:event-failed",
+eventSent: "plugin:rudderstack:event-sent"
+})
+
 
 /** user-defined constants **/
 
@@ -87,6 +227,202 @@ context?: JsonValue | null;
  * Integrations to route this message to.
  */
 integrations?: JsonValue | null }
+/**
+ * All analytics data stored on this device.
+ */
+export type AnalyticsData = { 
+/**
+ * The anonymous ID of the user.
+ */
+anonymousId: string; 
+/**
+ * The user ID of the user, if identified.
+ */
+userId: string | null; 
+/**
+ * The traits the plugin adds to identify events.
+ */
+traits: JsonValue; 
+/**
+ * The context added to every event.
+ */
+context: JsonValue; 
+/**
+ * The stored config, including the consent state and the audit log.
+ */
+config: JsonValue; 
+/**
+ * The events recorded in the event log and the local store, oldest first.
+ */
+events: JsonValue[] }
+/**
+ * The status of the analytics plugin.
+ */
+export type AnalyticsStatus = { 
+/**
+ * True if events are currently sent, see [AnalyticsExt::is_analytics_allowed](crate::AnalyticsExt::is_analytics_allowed).
+ */
+allowed: boolean; 
+/**
+ * The anonymous ID of the user.
+ */
+anonymousId: string; 
+/**
+ * Set if the anonymous ID changed unexpectedly since the last run.
+ */
+identityReset: IdentityReset | null }
+/**
+ * A change of the tracking state of the device.
+ */
+export type AuditChange = { type: "anonymousIdSet"; anonymousId: string } | { type: "userIdSet"; userId: string } | { type: "userIdCleared" } | { type: "consentChanged"; status: ConsentStatus; policyVersion: number | null } | { type: "consentCategoryChanged"; category: string; granted: boolean }
+/**
+ * An entry of the audit log.
+ */
+export type AuditEntry = { 
+/**
+ * When the change happened.
+ */
+at: string; 
+/**
+ * What changed.
+ */
+change: AuditChange }
+/**
+ * A batch of events.
+ * The [Batch] call lets you send multiple user events(of type [Identify], [Track], [Page], [Screen], [Group], and [Alias]) in one call.
+ */
+export type Batch = { 
+/**
+ * The batch of messages to send.
+ */
+batch: BatchMessage[]; 
+/**
+ * Context associated with this message.
+ */
+context?: JsonValue | null; 
+/**
+ * Integrations to route this message to.
+ */
+integrations?: JsonValue | null; 
+/**
+ * The timestamp associated with this message.
+ */
+originalTimestamp?: string | null }
+/**
+ * An enum containing all messages which may be placed inside a batch.
+ */
+export type BatchMessage = ({ type: "identify" } & Identify) | ({ type: "track" } & Track) | ({ type: "page" } & Page) | ({ type: "screen" } & Screen) | ({ type: "group" } & Group) | ({ type: "alias" } & Alias)
+/**
+ * `Cart Viewed`: the user viewed the cart.
+ */
+export type CartViewed = { cart_id?: string | null; products: Product[] }
+/**
+ * `Checkout Started`: the user started the checkout.
+ */
+export type CheckoutStarted = Order
+/**
+ * The result of a connectivity check of the data plane.
+ */
+export type Connectivity = { 
+/**
+ * True if the data plane responded successfully.
+ */
+reachable: boolean; 
+/**
+ * The time the check took in milliseconds.
+ */
+latencyMs: number; 
+/**
+ * Why the data plane is not reachable.
+ */
+error: string | null }
+/**
+ * The persisted consent state of the user.
+ */
+export type ConsentState = { 
+/**
+ * The current consent status.
+ */
+status: ConsentStatus; 
+/**
+ * The policy version the user answered.
+ */
+policyVersion: number | null; 
+/**
+ * When the status last changed.
+ */
+updatedAt: string | null; 
+/**
+ * The consent of the user per category, e.g. `marketing`.
+ */
+categories?: { [key in string]: boolean } }
+/**
+ * The consent status of the user.
+ */
+export type ConsentStatus = "notAsked" | "prompted" | "granted" | "denied"
+/**
+ * Why a message was not delivered.
+ */
+export type DropReason = "noConsent" | "doNotTrack" | "expired" | "filtered" | "sampled" | "throttled" | "backpressured" | "duplicate" | "dryRun" | "disabled"
+/**
+ * Emitted as `plugin:rudderstack:event-dropped` when an event expired or was dropped
+ * because too many deliveries are pending, see [RudderStackBuilder::max_event_age](crate::RudderStackBuilder::max_event_age).
+ */
+export type EventDropped = { 
+/**
+ * The ID of the dropped message.
+ */
+messageId: string; 
+/**
+ * The name of the event, or the message type for events without a name.
+ */
+event: string; 
+/**
+ * Why the event was dropped.
+ */
+reason: DropReason }
+/**
+ * Emitted as `plugin:rudderstack:event-failed` when an event failed to be delivered.
+ */
+export type EventFailed = { 
+/**
+ * The ID of the message that failed.
+ */
+messageId: string; 
+/**
+ * The name of the event, or the message type for events without a name.
+ */
+event: string; 
+/**
+ * The delivery error.
+ */
+error: string }
+/**
+ * Emitted as `plugin:rudderstack:event-sent` when an event was delivered to the data plane.
+ */
+export type EventSent = { 
+/**
+ * The ID of the delivered message.
+ */
+messageId: string; 
+/**
+ * The name of the event, or the message type for events without a name.
+ */
+event: string }
+/**
+ * A `Funnel Step Completed` event, see [Funnel].
+ */
+export type FunnelStep = { 
+funnel_name: string; 
+/**
+ * The number of the step, starting at 1.
+ */
+step_index: number; 
+step_name: string; 
+/**
+ * The number of declared steps in the funnel.
+ */
+total_steps?: number | null }
 /**
  * A group event.
  * The `group` call lets you associate an identified user to a group - either a company, project or a team and record any custom traits or properties associated with that group. \
@@ -101,6 +437,11 @@ groupId: string;
  * The traits to assign to the group.
  */
 traits?: JsonValue | null; 
+/**
+ * The anonymous ID to send this event with instead of the stored one,
+ * e.g. to attribute events to another local profile.
+ */
+anonymousId?: string | null; 
 /**
  * The timestamp associated with this message.
  */
@@ -122,6 +463,11 @@ export type Identify = {
  * The traits to assign to the user.
  */
 traits?: JsonValue | null; 
+/**
+ * The anonymous ID to send this event with instead of the stored one,
+ * e.g. to attribute events to another local profile.
+ */
+anonymousId?: string | null; 
 /**
  * The timestamp associated with this message.
  */
@@ -134,7 +480,97 @@ context?: JsonValue | null;
  * Integrations to route this message to.
  */
 integrations?: JsonValue | null }
+/**
+ * An unexpected change of the anonymous ID since the last run.
+ */
+export type IdentityReset = { 
+/**
+ * The anonymous ID used by the last run.
+ */
+previousAnonymousId: string; 
+/**
+ * The anonymous ID loaded for this run.
+ */
+anonymousId: string; reason: IdentityResetReason }
+/**
+ * Why the anonymous ID changed since the last run.
+ */
+export type IdentityResetReason = 
+/**
+ * The config file was missing or could not be read, so a new anonymous ID was generated.
+ */
+"configLost" | 
+/**
+ * The config file holds a different anonymous ID than the last run used.
+ */
+"mismatch"
 export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
+/**
+ * A snapshot of the delivery counters of the plugin.
+ */
+export type Metrics = { 
+/**
+ * Events delivered to the data plane.
+ */
+sent: number; 
+/**
+ * Events that failed to be delivered.
+ */
+failed: number; 
+/**
+ * Events dropped before sending, e.g. by sampling, consent or the event registry.
+ */
+dropped: number; 
+/**
+ * Events dropped because their deadline, TTL or maximum age passed before they were sent.
+ */
+expired: number; 
+/**
+ * Events dropped because too many deliveries were pending or the memory budget was exceeded.
+ */
+overflowed: number; 
+/**
+ * Events currently waiting to be delivered.
+ */
+queued: number }
+/**
+ * The properties shared by the checkout and order events.
+ */
+export type Order = { 
+order_id: string; 
+/**
+ * The store or affiliation the order is attributed to.
+ */
+affiliation?: string | null; 
+/**
+ * The total value of the order, after discounts, including shipping and taxes.
+ */
+total?: number | null; 
+/**
+ * The revenue of the order, after discounts, excluding shipping and taxes.
+ */
+revenue?: number | null; 
+shipping?: number | null; 
+tax?: number | null; 
+discount?: number | null; 
+coupon?: string | null; 
+/**
+ * The currency of the order in ISO 4217 format, e.g. `USD`.
+ */
+currency?: string | null; 
+products: Product[] }
+/**
+ * `Order Cancelled`: the order was cancelled.
+ */
+export type OrderCancelled = Order
+/**
+ * `Order Completed`: the user completed the order.
+ */
+export type OrderCompleted = Order
+/**
+ * `Order Refunded`: the order was refunded, fully or only the listed `products`.
+ */
+export type OrderRefunded = Order
 /**
  * A page event.
  * 
@@ -150,6 +586,11 @@ name: string;
  * The properties associated with the event.
  */
 properties?: JsonValue | null; 
+/**
+ * The anonymous ID to send this event with instead of the stored one,
+ * e.g. to attribute events to another local profile.
+ */
+anonymousId?: string | null; 
 /**
  * The timestamp associated with this message.
  */
@@ -162,6 +603,50 @@ context?: JsonValue | null;
  * Integrations to route this message to.
  */
 integrations?: JsonValue | null }
+/**
+ * A product, used by the product events and in the `products` of cart, checkout and order events.
+ */
+export type Product = { 
+/**
+ * The ID of the product in the database.
+ */
+product_id?: string | null; 
+/**
+ * The stock keeping unit of the product.
+ */
+sku?: string | null; 
+category?: string | null; 
+name?: string | null; 
+brand?: string | null; 
+variant?: string | null; 
+/**
+ * The price of a single unit.
+ */
+price?: number | null; 
+quantity?: number | null; 
+coupon?: string | null; 
+/**
+ * The position of the product in a list.
+ */
+position?: number | null; 
+url?: string | null; 
+image_url?: string | null }
+/**
+ * `Product Added`: the user added a product to the cart.
+ */
+export type ProductAdded = ({ cart_id?: string | null }) & Product
+/**
+ * `Product Removed`: the user removed a product from the cart.
+ */
+export type ProductRemoved = ({ cart_id?: string | null }) & Product
+/**
+ * `Product Viewed`: the user viewed the details of a product.
+ */
+export type ProductViewed = ({ currency?: string | null }) & Product
+/**
+ * `Products Searched`: the user searched for products.
+ */
+export type ProductsSearched = { query: string }
 /**
  * A screen event.
  * 
@@ -179,6 +664,11 @@ name: string;
  * The properties associated with the event.
  */
 properties?: JsonValue | null; 
+/**
+ * The anonymous ID to send this event with instead of the stored one,
+ * e.g. to attribute events to another local profile.
+ */
+anonymousId?: string | null; 
 /**
  * The timestamp associated with this message.
  */
@@ -204,6 +694,16 @@ event: string;
  * The properties associated with the event.
  */
 properties?: JsonValue | null; 
+/**
+ * Drop the event instead of sending it after this deadline, e.g. for impressions
+ * that are worthless once stale.
+ */
+sendBy?: string | null; 
+/**
+ * The anonymous ID to send this event with instead of the stored one,
+ * e.g. to attribute events to another local profile.
+ */
+anonymousId?: string | null; 
 /**
  * The timestamp associated with this message.
  */
@@ -1,4 +1,14 @@
-import { Alias, commands, Group, Identify, Page, Screen, Track } from "./bindings";
+import {
+    Alias,
+    BatchMessage,
+    commands,
+    Group,
+    Identify,
+    JsonValue,
+    Page,
+    Screen,
+    Track,
+} from "./bindings";
 export * from "./bindings";
 
 interface PageProperties {
@@ -112,3 +122,379 @@ export const sendGroupEvent = async (message: Group) => {
 export const sendAliasEvent = async (message: Alias) => {
     await commands.sendAnalyticsAlias(message);
 }
+
+/**
+ * A typed builder for the `integrations` field of a message.
+ *
+ * @example
+ * const integrations = Integrations.all(false)
+ *     .enable("Amplitude")
+ *     .enableWithOptions("Braze", { appId: "..." })
+ *     .build();
+ */
+export class Integrations {
+    private readonly destinations: { [key: string]: JsonValue } = {};
+
+    /**
+     * Route the message to all destinations by default, or to none if `enabled` is false.
+     */
+    static all(enabled: boolean): Integrations {
+        return new Integrations().set("All", enabled);
+    }
+
+    /**
+     * Route the message to this destination.
+     */
+    enable(destination: string): Integrations {
+        return this.set(destination, true);
+    }
+
+    /**
+     * Do not route the message to this destination.
+     */
+    disable(destination: string): Integrations {
+        return this.set(destination, false);
+    }
+
+    /**
+     * Route the message to this destination, with destination specific options.
+     */
+    enableWithOptions(destination: string, options: { [key: string]: JsonValue }): Integrations {
+        return this.set(destination, options);
+    }
+
+    /**
+     * The integrations object to pass as the `integrations` field of a message.
+     */
+    build(): JsonValue {
+        return { ...this.destinations };
+    }
+
+    private set(destination: string, value: JsonValue): Integrations {
+        this.destinations[destination] = value;
+        return this;
+    }
+}
+
+/**
+ * The properties or traits of an event.
+ */
+export type Properties = { [key: string]: JsonValue };
+
+/**
+ * Optional fields shared by all events.
+ */
+export interface EventOptions {
+    /** Context merged into the context of the message. */
+    context?: Properties;
+    /** Integrations to route this message to, see {@link Integrations}. */
+    integrations?: JsonValue;
+    /** The anonymous ID to send this event with instead of the stored one. */
+    anonymousId?: string;
+    /** When the event happened, defaults to now. */
+    timestamp?: Date | string;
+}
+
+/**
+ * Optional fields of a track event.
+ */
+export interface TrackOptions extends EventOptions {
+    /** Drop the event instead of sending it after this deadline. */
+    sendBy?: Date | string;
+}
+
+const toTimestamp = (timestamp?: Date | string) =>
+    timestamp instanceof Date ? timestamp.toISOString() : timestamp ?? null;
+
+const fields = (options: EventOptions = {}) => ({
+    context: options.context ?? null,
+    integrations: options.integrations ?? null,
+    originalTimestamp: toTimestamp(options.timestamp),
+});
+
+/**
+ * Send analytics events without spelling out every optional field.
+ *
+ * @example
+ * await analytics.identify("user-1", { plan: "pro" });
+ * await analytics.track("Signed In", { method: "oauth" });
+ * await analytics.page();
+ */
+export const analytics = {
+    /**
+     * Record a user action, the current page is added to the properties.
+     */
+    async track(event: string, properties?: Properties, options: TrackOptions = {}) {
+        await sendTrackEvent({
+            event,
+            properties: properties ?? null,
+            sendBy: toTimestamp(options.sendBy),
+            anonymousId: options.anonymousId ?? null,
+            ...fields(options),
+        });
+    },
+
+    /**
+     * Record a page view, defaults to the current path with its title, URL and referrer.
+     */
+    async page(name?: string, properties?: Properties, options: EventOptions = {}) {
+        await commands.sendAnalyticsPage({
+            name: name ?? window.location.pathname,
+            properties: {
+                title: document.title,
+                url: window.location.href,
+                path: window.location.pathname,
+                referrer: document.referrer,
+                ...properties,
+            },
+            anonymousId: options.anonymousId ?? null,
+            ...fields(options),
+        });
+    },
+
+    /**
+     * Record a screen view.
+     */
+    async screen(name: string, properties?: Properties, options: EventOptions = {}) {
+        await commands.sendAnalyticsScreen({
+            name,
+            properties: properties ?? null,
+            anonymousId: options.anonymousId ?? null,
+            ...fields(options),
+        });
+    },
+
+    /**
+     * Set the user ID sent with the following events and record the traits of the user,
+     * with a single Identify event.
+     */
+    async identify(userId: string, traits?: Properties, options: EventOptions = {}) {
+        await commands.setAnalyticsUserId(userId, {
+            traits: traits ?? null,
+            anonymousId: options.anonymousId ?? null,
+            ...fields(options),
+        });
+    },
+
+    /**
+     * Associate the user with a group, e.g. a company or team.
+     */
+    async group(groupId: string, traits?: Properties, options: EventOptions = {}) {
+        await commands.sendAnalyticsGroup({
+            groupId,
+            traits: traits ?? null,
+            anonymousId: options.anonymousId ?? null,
+            ...fields(options),
+        });
+    },
+
+    /**
+     * Merge the identity `previousId` into `userId`.
+     */
+    async alias(
+        userId: string,
+        previousId: string,
+        traits?: Properties,
+        options: EventOptions = {},
+    ) {
+        await commands.sendAnalyticsAlias({
+            userId,
+            previousId,
+            traits: traits ?? null,
+            ...fields(options),
+        });
+    },
+
+    /**
+     * Stop sending the user ID, e.g. on sign out.
+     */
+    async reset() {
+        await commands.setAnalyticsUserId(null, null);
+    },
+};
+
+/**
+ * Options of {@link autoTrackPages}.
+ */
+export interface AutoTrackPagesOptions {
+    /**
+     * Subscribe to the route changes of your router and return a function to unsubscribe,
+     * defaults to listening to the history API.
+     */
+    subscribe?: (onChange: () => void) => (() => void) | void;
+    /** The name of the page, defaults to the path. */
+    name?: (location: Location) => string;
+    /** Send a page event for the current route right away, defaults to true. */
+    trackInitial?: boolean;
+}
+
+const listenToHistory = (onChange: () => void) => {
+    const pushState = history.pushState;
+    const replaceState = history.replaceState;
+    history.pushState = function (...args) {
+        pushState.apply(history, args);
+        onChange();
+    };
+    history.replaceState = function (...args) {
+        replaceState.apply(history, args);
+        onChange();
+    };
+    window.addEventListener("popstate", onChange);
+    window.addEventListener("hashchange", onChange);
+
+    return () => {
+        history.pushState = pushState;
+        history.replaceState = replaceState;
+        window.removeEventListener("popstate", onChange);
+        window.removeEventListener("hashchange", onChange);
+    };
+};
+
+/**
+ * Send a page event with the path, title and referrer on every route change of a single page app.
+ * The previous URL is sent as the referrer, route changes that keep the URL are ignored.
+ *
+ * @example
+ * // history API, works with any router
+ * autoTrackPages();
+ * // Vue Router
+ * autoTrackPages({ subscribe: (onChange) => router.afterEach(onChange) });
+ * // React Router data routers
+ * autoTrackPages({ subscribe: (onChange) => router.subscribe(onChange) });
+ * // SvelteKit
+ * autoTrackPages({ subscribe: (onChange) => page.subscribe(onChange) });
+ *
+ * @returns A function to stop tracking.
+ */
+export const autoTrackPages = (options: AutoTrackPagesOptions = {}) => {
+    let lastUrl: string | null = null;
+    let referrer = document.referrer;
+
+    const track = () => {
+        const url = window.location.href;
+        if (url === lastUrl) return;
+        if (lastUrl !== null) referrer = lastUrl;
+        lastUrl = url;
+        analytics.page(options.name?.(window.location), { referrer }).catch((e) => {
+            console.error("failed to send the page event", e);
+        });
+    };
+    // wait for the router to update the title
+    const trackLater = () => {
+        setTimeout(track, 0);
+    };
+
+    const unsubscribe = (options.subscribe ?? listenToHistory)(trackLater);
+    if (options.trackInitial ?? true) {
+        track();
+    }
+
+    return () => {
+        unsubscribe?.();
+    };
+};
+
+/**
+ * Options of {@link autoCapture}.
+ */
+export interface AutoCaptureOptions {
+    /** The attribute naming the event of an element, defaults to `data-analytics-event`. */
+    attribute?: string;
+    /**
+     * The attribute with the JSON properties of an element, defaults to the event attribute
+     * with `-properties` instead of `-event`, e.g. `data-analytics-properties`.
+     */
+    propertiesAttribute?: string;
+    /** How long clicks are collected before they are sent as one batch, defaults to 1000 ms. */
+    flushInterval?: number;
+    /** Send the collected clicks right away once there are this many, defaults to 20. */
+    maxBatchSize?: number;
+}
+
+const parseProperties = (attribute: string, json: string | null): Properties => {
+    if (!json) return {};
+    try {
+        const properties = JSON.parse(json);
+        return properties && typeof properties === "object" ? properties : {};
+    } catch {
+        console.error(`invalid ${attribute}`, json);
+        return {};
+    }
+};
+
+/**
+ * Track clicks on elements annotated with `data-analytics-event`, opt-in.
+ * Properties are read from the JSON in `data-analytics-properties`,
+ * the clicks are sent in batches with the `send_analytics_batch` command.
+ *
+ * @example
+ * autoCapture();
+ * // <button data-analytics-event="Signed In" data-analytics-properties='{"method":"oauth"}'>
+ *
+ * @returns A function to stop capturing, pending clicks are sent.
+ */
+export const autoCapture = (options: AutoCaptureOptions = {}) => {
+    const attribute = options.attribute ?? "data-analytics-event";
+    const propertiesAttribute =
+        options.propertiesAttribute ?? `${attribute.replace(/-event$/, "")}-properties`;
+    const flushInterval = options.flushInterval ?? 1000;
+    const maxBatchSize = options.maxBatchSize ?? 20;
+
+    let pending: BatchMessage[] = [];
+    let timer: ReturnType<typeof setTimeout> | null = null;
+
+    const flush = () => {
+        if (timer !== null) {
+            clearTimeout(timer);
+            timer = null;
+        }
+        if (pending.length === 0) return;
+        const batch = pending;
+        pending = [];
+        commands.sendAnalyticsBatch({ batch }).catch((e) => {
+            console.error("failed to send the captured clicks", e);
+        });
+    };
+
+    const onClick = (e: MouseEvent) => {
+        if (!(e.target instanceof Element)) return;
+        const element = e.target.closest<HTMLElement>(`[${attribute}]`);
+        const event = element?.getAttribute(attribute);
+        if (!element || !event) return;
+
+        const track = addPageProperties({
+            event,
+            properties: {
+                ...parseProperties(
+                    propertiesAttribute,
+                    element.getAttribute(propertiesAttribute),
+                ),
+                element: element.tagName.toLowerCase(),
+                elementId: element.id || null,
+            },
+            originalTimestamp: new Date().toISOString(),
+        });
+        pending.push({ type: "track", ...track });
+
+        if (pending.length >= maxBatchSize) {
+            flush();
+        } else if (timer === null) {
+            timer = setTimeout(flush, flushInterval);
+        }
+    };
+    // send the pending clicks before the webview is hidden or unloaded
+    const onHide = () => {
+        if (document.visibilityState === "hidden") flush();
+    };
+
+    document.addEventListener("click", onClick, true);
+    document.addEventListener("visibilitychange", onHide);
+    window.addEventListener("pagehide", flush);
+
+    return () => {
+        document.removeEventListener("click", onClick, true);
+        document.removeEventListener("visibilitychange", onHide);
+        window.removeEventListener("pagehide", flush);
+        flush();
+    };
+};
@@ -0,0 +1,67 @@
+use std::{sync::Mutex, time::Instant};
+
+use serde_json::{json, Value};
+
+#[derive(Debug)]
+struct FocusedWindow {
+    label: String,
+    title_hash: String,
+    focused_at: Instant,
+}
+
+/// Tracks the focused window, added to the context of every event as `active_window`.
+#[derive(Debug, Default)]
+pub(crate) struct ActiveWindow {
+    focused: Mutex<Option<FocusedWindow>>,
+}
+
+impl ActiveWindow {
+    /// Record a focus change of a window. The title is hashed so it is not sent in plain text.
+    pub fn focus_changed(&self, label: &str, title: Option<String>, focused: bool) {
+        let mut current = self.focused.lock().unwrap();
+        if focused {
+            *current = Some(FocusedWindow {
+                label: label.to_string(),
+                title_hash: title.map(|title| hash(&title)).unwrap_or_default(),
+                focused_at: Instant::now(),
+            });
+        } else if current.as_ref().is_some_and(|window| window.label == label) {
+            *current = None;
+        }
+    }
+
+    /// The `active_window` context of the focused window, if any.
+    pub fn context(&self) -> Option<Value> {
+        let current = self.focused.lock().unwrap();
+        current.as_ref().map(|window| {
+            json!({
+                "label": window.label,
+                "title_hash": window.title_hash,
+                "focused_ms": window.focused_at.elapsed().as_millis() as u64,
+            })
+        })
+    }
+}
+
+/// The 64-bit FNV-1a hash of the title. Unlike the hasher of the standard library
+/// it is stable across Rust versions and platforms, so the hashes can be compared over time.
+fn hash(title: &str) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let hash = title.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    });
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_titles_stably() {
+        assert_eq!(hash(""), "cbf29ce484222325");
+        assert_eq!(hash("a"), "af63dc4c8601ec8c");
+        assert_eq!(hash("foobar"), "85944171f73967e8");
+    }
+}
@@ -1,69 +1,157 @@
+use std::{future::IntoFuture as _, path::Path, time::Duration};
+
+use chrono::{DateTime, Utc};
 use tauri::{Manager as _, Runtime};
 
 use crate::{
+    audit::AuditEntry,
     config,
+    consent::{ConsentError, ConsentState},
+    data::{AnalyticsData, WipeError},
+    instance::Instance,
+    integrity::AnalyticsStatus,
+    lifecycle::PushNotification,
+    metrics::Metrics,
+    replay::ReplayDiff,
     rudder_wrapper::RudderWrapper,
+    rudderanalytics,
+    send_result::{DropReason, SendError, SendResult},
+    transport::{Connectivity, DataPlaneError},
     types::{self, Alias, Group, Identify, Page, Screen, Track},
+    window_metadata, TrackEvent,
 };
 
 /// Extensions to [`tauri::App`], [`tauri::AppHandle`] and [`tauri::Window`] to access the analytics APIs.
+///
+/// Each [`tauri::Window`] has its own context, merged on top of the global context
+/// for the events sent through the window, including the events sent from its webview.
+/// The context methods of a window only change its own context.
+/// Screen events sent through a window get its label, size, scale factor and monitor as properties.
 pub trait AnalyticsExt<R: Runtime> {
-    /// Send an analytics event to the RudderStack data plane.
-    fn send_analytic(
+    /// The RudderStack instance registered with this name,
+    /// see [RudderStackBuilder::instance_name](crate::RudderStackBuilder::instance_name).
+    fn instance(&self, name: &str) -> Option<Instance<R>>;
+
+    /// Send an analytics event to the RudderStack data plane. \
+    /// Await the [SendResult] to wait for the delivery.
+    #[track_caller]
+    fn send_analytic(&self, event: types::Message) -> SendResult;
+
+    /// Send an analytics event and block the current thread until it is delivered,
+    /// e.g. before the process exits. Dropped events are returned as [SendError::Dropped]. \
+    /// Deliveries are held while offline, the circuit breaker is open or the connection is metered,
+    /// use [AnalyticsExt::send_analytic_blocking_timeout] to not wait indefinitely. \
+    /// Panics if called from an async task, await [AnalyticsExt::send_analytic] there instead.
+    #[track_caller]
+    fn send_analytic_blocking(&self, event: types::Message) -> Result<(), SendError> {
+        let result = self.send_analytic(event);
+        tauri::async_runtime::block_on(result.into_future()).into_result()
+    }
+
+    /// Like [AnalyticsExt::send_analytic_blocking], but waits at most `timeout`
+    /// and returns [SendError::TimedOut] if the event is not delivered by then.
+    /// The delivery continues in the background.
+    #[track_caller]
+    fn send_analytic_blocking_timeout(
         &self,
         event: types::Message,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>>;
+        timeout: Duration,
+    ) -> Result<(), SendError> {
+        let result = self.send_analytic(event);
+        tauri::async_runtime::block_on(async move {
+            tokio::time::timeout(timeout, result.into_future()).await
+        })
+        .map_err(|_| SendError::TimedOut(timeout))?
+        .into_result()
+    }
 
     /// Send an [Identify] event to the RudderStack data plane.
-    fn send_analytic_identify(
-        &self,
-        event: Identify,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>> {
+    #[track_caller]
+    fn send_analytic_identify(&self, event: Identify) -> SendResult {
         let event = types::Message::Identify(event);
         self.send_analytic(event)
     }
 
     /// Send a [Track] event to the RudderStack data plane.
-    fn send_analytic_track(
-        &self,
-        event: Track,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>> {
+    #[track_caller]
+    fn send_analytic_track(&self, event: Track) -> SendResult {
         let event = types::Message::Track(event);
         self.send_analytic(event)
     }
 
-    /// Send a [Page] event to the RudderStack data plane.
-    fn send_analytic_page(
+    /// Send a `Push Notification Received` or `Push Notification Tapped` event like the
+    /// RudderStack mobile SDKs, e.g. from the callbacks of your push notification plugin.
+    #[track_caller]
+    fn track_push_notification(
         &self,
-        event: Page,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>> {
+        event: PushNotification,
+        properties: Option<serde_json::Value>,
+    ) -> SendResult {
+        self.send_analytic_track(Track {
+            event: event.event_name().to_string(),
+            properties,
+            ..Track::default()
+        })
+    }
+
+    /// Send a strongly typed [TrackEvent] to the RudderStack data plane.
+    #[track_caller]
+    fn track<E: TrackEvent>(&self, event: E) -> SendResult {
+        self.send_analytic_track(event.into_track())
+    }
+
+    /// Send a `Revenue` [Track] event with the properties the revenue destinations expect,
+    /// e.g. Amplitude and Mixpanel: `revenue` as a number and `currency` as an ISO 4217 code.
+    /// The `properties`, e.g. `productId` or `quantity`, are merged into the event properties. \
+    /// Amounts that are not finite are dropped.
+    #[track_caller]
+    fn track_revenue(
+        &self,
+        amount: f64,
+        currency: &str,
+        properties: Option<serde_json::Value>,
+    ) -> SendResult {
+        if !amount.is_finite() {
+            tracing::error!(amount, "revenue amount is not a finite number, dropping it");
+            return RudderWrapper::skipped(DropReason::Filtered);
+        }
+        let mut properties = match properties {
+            Some(serde_json::Value::Object(properties)) => properties,
+            _ => serde_json::Map::new(),
+        };
+        properties.insert("revenue".to_string(), amount.into());
+        properties.insert("currency".to_string(), currency.to_uppercase().into());
+        self.send_analytic_track(Track {
+            event: "Revenue".to_string(),
+            properties: Some(serde_json::Value::Object(properties)),
+            ..Track::default()
+        })
+    }
+
+    /// Send a [Page] event to the RudderStack data plane.
+    #[track_caller]
+    fn send_analytic_page(&self, event: Page) -> SendResult {
         let event = types::Message::Page(event);
         self.send_analytic(event)
     }
 
     /// Send a [Screen] event to the RudderStack data plane.
-    fn send_analytic_screen(
-        &self,
-        event: Screen,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>> {
+    #[track_caller]
+    fn send_analytic_screen(&self, event: Screen) -> SendResult {
         let event = types::Message::Screen(event);
         self.send_analytic(event)
     }
 
     /// Send a [Group] event to the RudderStack data plane.
-    fn send_analytic_group(
-        &self,
-        event: Group,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>> {
+    #[track_caller]
+    fn send_analytic_group(&self, event: Group) -> SendResult {
         let event = types::Message::Group(event);
         self.send_analytic(event)
     }
 
     /// Send an [Alias] event to the RudderStack data plane.
-    fn send_analytic_alias(
-        &self,
-        event: Alias,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>> {
+    #[track_caller]
+    fn send_analytic_alias(&self, event: Alias) -> SendResult {
         let event = types::Message::Alias(event);
         self.send_analytic(event)
     }
@@ -76,6 +164,10 @@ pub trait AnalyticsExt<R: Runtime> {
     /// It will overwrite the previous user ID.
     fn set_user_id(&self, id: Option<String>);
 
+    /// Send all subsequent events to another data plane, e.g. a region-specific one
+    /// fetched after login. Fails if a custom [Transport](crate::Transport) is used.
+    fn set_data_plane(&self, url: String) -> Result<(), DataPlaneError>;
+
     /// Add to context hash map
     fn add_to_context(&self, key: String, value: serde_json::Value) -> Option<serde_json::Value>;
 
@@ -87,64 +179,446 @@ pub trait AnalyticsExt<R: Runtime> {
 
     /// Get the context hash map
     fn get_context(&self) -> crate::types::Context;
+
+    /// The number of events that were sent instead of dropped by sampling, throttling
+    /// or backpressure, either through `force` or the exemption list.
+    fn forced_sends(&self) -> u64;
+
+    /// A snapshot of the delivery counters: events sent, failed, dropped and queued.
+    fn metrics(&self) -> Metrics;
+
+    /// Check that the data plane is reachable through the network of the user,
+    /// without sending an event.
+    fn check_analytics_connectivity(&self) -> tauri::async_runtime::JoinHandle<Connectivity>;
+
+    /// The approximate bytes used by in-memory plugin structures, and the limit of the memory budget.
+    fn memory_usage(&self) -> (usize, usize);
+
+    /// Returns true if non-essential events are currently delivered to the data plane.
+    /// This is false while consent is not granted under the [ConsentPolicy](crate::ConsentPolicy),
+    /// or in mock or dry run mode.
+    fn is_analytics_allowed(&self) -> bool;
+
+    /// The bounded log of identity and consent changes on this device, oldest first.
+    fn audit_log(&self) -> Vec<AuditEntry>;
+
+    /// The status of the plugin, including an unexpected reset of the anonymous ID
+    /// detected on startup, e.g. after the config file was restored from a backup.
+    fn analytics_status(&self) -> AnalyticsStatus;
+
+    /// The last input or focus activity of the user, in this window for a [`tauri::Window`]. \
+    /// `None` without [idle detection](crate::RudderStackBuilder::idle_threshold).
+    fn last_active(&self) -> Option<DateTime<Utc>>;
+
+    /// Increment a persisted lifetime counter, e.g. `total_exports`, and return its new value. \
+    /// The counters are included in the traits of every identify event.
+    fn increment_trait_counter(&self, name: &str) -> u64;
+
+    /// Send an `Experiment Viewed` event when the user is exposed to a variant of an A/B test. \
+    /// With [RudderStackBuilder::experiments_in_context](crate::RudderStackBuilder::experiments_in_context)
+    /// the variant is also added to the `experiments` context of all subsequent events.
+    fn track_experiment(&self, experiment: &str, variant: &str) -> SendResult;
+
+    /// All analytics data stored on this device, for data subject access requests.
+    fn export_analytics_data(&self) -> AnalyticsData;
+
+    /// Delete all analytics data stored on this device, for data subject deletion requests. \
+    /// Subsequent events use a new anonymous ID.
+    fn wipe_analytics_data(&self) -> Result<(), WipeError>;
+
+    /// Get the persisted consent state of the user.
+    fn consent_state(&self) -> ConsentState;
+
+    /// Returns true if the user should be prompted for consent,
+    /// either because they were never asked, the policy version changed, or the re-prompt interval passed.
+    fn should_prompt_consent(&self) -> bool;
+
+    /// Mark that the user is being prompted for consent.
+    fn prompt_consent(&self) -> Result<ConsentState, ConsentError>;
+
+    /// Record the answer of the user to the consent prompt.
+    fn answer_consent(&self, granted: bool) -> Result<ConsentState, ConsentError>;
+
+    /// Record the consent of the user for a category, e.g. `marketing`.
+    /// See [DestinationConsent](crate::DestinationConsent).
+    fn set_consent_category(&self, category: String, granted: bool) -> ConsentState;
+
+    /// The messages recorded in mock mode, see [RudderStackBuilder::mock](crate::RudderStackBuilder::mock). \
+    /// Always empty if mock mode is disabled.
+    fn recorded_events(&self) -> Vec<rudderanalytics::message::Message>;
+
+    /// Clear the messages recorded in mock mode.
+    fn clear_recorded_events(&self);
+
+    /// Panics if no [Track] event with this name was recorded in mock mode.
+    fn assert_tracked(&self, event: &str);
+
+    /// Re-run the last `count` captured events through the current rules without sending them,
+    /// and return the events whose result changed. \
+    /// Requires [RudderStackBuilder::capture_recent_events](crate::RudderStackBuilder::capture_recent_events).
+    fn replay_recent_events(&self, count: usize) -> Vec<ReplayDiff>;
+
+    /// Resubmit the events recorded in an event log, see [RudderStackBuilder::event_log](crate::RudderStackBuilder::event_log). \
+    /// The events keep their `originalTimestamp`. Records that fail to parse are skipped,
+    /// like events without consent or dropped by the current rules, sampling and transformers.
+    ///
+    /// Use this to recover events captured while analytics was failing.
+    fn replay_from_file(&self, path: impl AsRef<Path>) -> std::io::Result<Vec<SendResult>>;
+
+    /// Write all events of the [local store](crate::RudderStackBuilder::local_store) to `path`
+    /// as a JSONL event log, e.g. for a manual export from an air-gapped machine.
+    /// Send it later with [AnalyticsExt::replay_from_file]. Returns the number of exported events.
+    #[cfg(feature = "local-store")]
+    fn export_local_store(&self, path: impl AsRef<Path>) -> Result<usize, crate::LocalStoreError>;
 }
 
-impl<R: Runtime> AnalyticsExt<R> for tauri::AppHandle<R> {
-    fn send_analytic(
-        &self,
-        event: types::Message,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>> {
+impl<R: Runtime> AnalyticsExt<R> for Instance<R> {
+    fn instance(&self, name: &str) -> Option<Instance<R>> {
+        Instance::named(&self.handle, name)
+    }
+
+    fn send_analytic(&self, event: types::Message) -> SendResult {
         tracing::trace!(event = ?event, "sending analytics event");
         tracing::debug!("sending analytics event");
-        let message = types::convert_message(event);
-        let rudder = self.state::<RudderWrapper>();
-        rudder.send(message)
+        let rudder = self.rudder();
+        rudder.send_message(event)
     }
 
     fn set_anonymous_id(&self, id: String) -> Result<(), config::ClientIdError> {
         tracing::debug!("setting anonymous id: {:?}", id);
-        let rudder = self.state::<RudderWrapper>();
+        let rudder = self.rudder();
         rudder.set_anonymous_id(id.clone());
-        rudder.save(self)
+        rudder.save(&self.handle)
     }
 
     fn set_user_id(&self, id: Option<String>) {
         tracing::debug!("setting user id: {:?}", id);
-        let rudder = self.state::<RudderWrapper>();
-        rudder.set_user_id(id.clone());
+        self.identify_user(id, None);
+    }
+
+    fn set_data_plane(&self, url: String) -> Result<(), DataPlaneError> {
+        tracing::debug!("setting data plane: {:?}", url);
+        let rudder = self.rudder();
+        rudder.set_data_plane(url)
     }
 
     fn add_to_context(&self, key: String, value: serde_json::Value) -> Option<serde_json::Value> {
         tracing::debug!("adding to context: {:?} -> {:?}", key, value);
-        let rudder = self.state::<RudderWrapper>();
+        let rudder = self.rudder();
         rudder.add_to_context(key, value)
     }
 
     fn remove_from_context(&self, key: &str) -> Option<serde_json::Value> {
         tracing::debug!("removing from context: {:?}", key);
-        let rudder = self.state::<RudderWrapper>();
+        let rudder = self.rudder();
         rudder.remove_from_context(key)
     }
 
     fn clear_context(&self) {
         tracing::debug!("clearing context");
-        let rudder = self.state::<RudderWrapper>();
+        let rudder = self.rudder();
         rudder.clear_context();
     }
 
     fn get_context(&self) -> crate::types::Context {
         tracing::debug!("getting context");
-        let rudder = self.state::<RudderWrapper>();
+        let rudder = self.rudder();
         rudder.get_context()
     }
+
+    fn forced_sends(&self) -> u64 {
+        let rudder = self.rudder();
+        rudder.forced_sends()
+    }
+
+    fn metrics(&self) -> Metrics {
+        let rudder = self.rudder();
+        rudder.metrics()
+    }
+
+    fn check_analytics_connectivity(&self) -> tauri::async_runtime::JoinHandle<Connectivity> {
+        tracing::debug!("checking data plane connectivity");
+        let rudder = self.rudder();
+        rudder.check_connectivity()
+    }
+
+    fn memory_usage(&self) -> (usize, usize) {
+        let rudder = self.rudder();
+        rudder.memory_usage()
+    }
+
+    fn consent_state(&self) -> ConsentState {
+        let rudder = self.rudder();
+        rudder.consent_state()
+    }
+
+    fn should_prompt_consent(&self) -> bool {
+        let rudder = self.rudder();
+        rudder.should_prompt_consent()
+    }
+
+    fn is_analytics_allowed(&self) -> bool {
+        let rudder = self.rudder();
+        rudder.is_analytics_allowed()
+    }
+
+    fn audit_log(&self) -> Vec<AuditEntry> {
+        let rudder = self.rudder();
+        rudder.audit_log()
+    }
+
+    fn analytics_status(&self) -> AnalyticsStatus {
+        let rudder = self.rudder();
+        rudder.status()
+    }
+
+    fn last_active(&self) -> Option<DateTime<Utc>> {
+        let rudder = self.rudder();
+        rudder.idle_tracker()?.last_active(None)
+    }
+
+    fn track_experiment(&self, experiment: &str, variant: &str) -> SendResult {
+        let rudder = self.rudder();
+        rudder.add_experiment(experiment, variant);
+        self.send_analytic_track(Track {
+            event: "Experiment Viewed".to_string(),
+            properties: Some(serde_json::json!({
+                "experiment_name": experiment,
+                "variation_name": variant,
+            })),
+            ..Track::default()
+        })
+    }
+
+    fn increment_trait_counter(&self, name: &str) -> u64 {
+        tracing::debug!("incrementing trait counter: {:?}", name);
+        let rudder = self.rudder();
+        let value = rudder.increment_trait_counter(name);
+        if let Err(err) = rudder.save(&self.handle) {
+            tracing::error!("Failed to save config: {:?}", err);
+        }
+        value
+    }
+
+    fn export_analytics_data(&self) -> AnalyticsData {
+        tracing::debug!("exporting analytics data");
+        let rudder = self.rudder();
+        rudder.export_data()
+    }
+
+    fn wipe_analytics_data(&self) -> Result<(), WipeError> {
+        tracing::debug!("wiping analytics data");
+        let rudder = self.rudder();
+        rudder.wipe_data(&self.handle)
+    }
+
+    fn prompt_consent(&self) -> Result<ConsentState, ConsentError> {
+        tracing::debug!("prompting for consent");
+        let rudder = self.rudder();
+        let state = rudder.prompt_consent()?;
+        if let Err(err) = rudder.save(&self.handle) {
+            tracing::error!("Failed to save config: {:?}", err);
+        }
+        Ok(state)
+    }
+
+    fn answer_consent(&self, granted: bool) -> Result<ConsentState, ConsentError> {
+        tracing::debug!("answering consent: {:?}", granted);
+        let rudder = self.rudder();
+        let state = rudder.answer_consent(granted)?;
+        if let Err(err) = rudder.save(&self.handle) {
+            tracing::error!("Failed to save config: {:?}", err);
+        }
+        Ok(state)
+    }
+
+    fn set_consent_category(&self, category: String, granted: bool) -> ConsentState {
+        tracing::debug!("setting consent category {:?}: {:?}", category, granted);
+        let rudder = self.rudder();
+        let state = rudder.set_consent_category(category, granted);
+        if let Err(err) = rudder.save(&self.handle) {
+            tracing::error!("Failed to save config: {:?}", err);
+        }
+        state
+    }
+
+    fn recorded_events(&self) -> Vec<rudderanalytics::message::Message> {
+        let rudder = self.rudder();
+        rudder.recorded_events()
+    }
+
+    fn clear_recorded_events(&self) {
+        let rudder = self.rudder();
+        rudder.clear_recorded_events()
+    }
+
+    fn assert_tracked(&self, event: &str) {
+        let rudder = self.rudder();
+        assert!(
+            rudder.was_tracked(event),
+            "expected track event {:?} to be recorded, recorded events: {:#?}",
+            event,
+            rudder.recorded_events()
+        );
+    }
+
+    fn replay_recent_events(&self, count: usize) -> Vec<ReplayDiff> {
+        tracing::debug!("replaying {} recent events", count);
+        let rudder = self.rudder();
+        rudder.replay_recent_events(count)
+    }
+
+    fn replay_from_file(&self, path: impl AsRef<Path>) -> std::io::Result<Vec<SendResult>> {
+        tracing::debug!("replaying events from {:?}", path.as_ref());
+        let rudder = self.rudder();
+        rudder.replay_from_file(path.as_ref())
+    }
+
+    #[cfg(feature = "local-store")]
+    fn export_local_store(&self, path: impl AsRef<Path>) -> Result<usize, crate::LocalStoreError> {
+        tracing::debug!("exporting the local store to {:?}", path.as_ref());
+        let rudder = self.rudder();
+        rudder.export_local_store(path.as_ref())
+    }
 }
 
+impl<R: Runtime> AnalyticsExt<R> for tauri::AppHandle<R> {
+    fn instance(&self, name: &str) -> Option<Instance<R>> {
+        Instance::named(self, name)
+    }
+
+    fn send_analytic(&self, event: types::Message) -> SendResult {
+        Instance::default_instance(self).send_analytic(event)
+    }
+
+    fn set_anonymous_id(&self, id: String) -> Result<(), config::ClientIdError> {
+        Instance::default_instance(self).set_anonymous_id(id)
+    }
+
+    fn set_user_id(&self, id: Option<String>) {
+        Instance::default_instance(self).set_user_id(id)
+    }
+
+    fn set_data_plane(&self, url: String) -> Result<(), DataPlaneError> {
+        Instance::default_instance(self).set_data_plane(url)
+    }
+
+    fn add_to_context(&self, key: String, value: serde_json::Value) -> Option<serde_json::Value> {
+        Instance::default_instance(self).add_to_context(key, value)
+    }
+
+    fn remove_from_context(&self, key: &str) -> Option<serde_json::Value> {
+        Instance::default_instance(self).remove_from_context(key)
+    }
+
+    fn clear_context(&self) {
+        Instance::default_instance(self).clear_context()
+    }
+
+    fn get_context(&self) -> crate::types::Context {
+        Instance::default_instance(self).get_context()
+    }
+
+    fn forced_sends(&self) -> u64 {
+        Instance::default_instance(self).forced_sends()
+    }
+
+    fn metrics(&self) -> Metrics {
+        Instance::default_instance(self).metrics()
+    }
+
+    fn check_analytics_connectivity(&self) -> tauri::async_runtime::JoinHandle<Connectivity> {
+        Instance::default_instance(self).check_analytics_connectivity()
+    }
+
+    fn memory_usage(&self) -> (usize, usize) {
+        Instance::default_instance(self).memory_usage()
+    }
+
+    fn consent_state(&self) -> ConsentState {
+        Instance::default_instance(self).consent_state()
+    }
+
+    fn should_prompt_consent(&self) -> bool {
+        Instance::default_instance(self).should_prompt_consent()
+    }
+
+    fn is_analytics_allowed(&self) -> bool {
+        Instance::default_instance(self).is_analytics_allowed()
+    }
+
+    fn audit_log(&self) -> Vec<AuditEntry> {
+        Instance::default_instance(self).audit_log()
+    }
+
+    fn analytics_status(&self) -> AnalyticsStatus {
+        Instance::default_instance(self).analytics_status()
+    }
+
+    fn last_active(&self) -> Option<DateTime<Utc>> {
+        Instance::default_instance(self).last_active()
+    }
+
+    fn increment_trait_counter(&self, name: &str) -> u64 {
+        Instance::default_instance(self).increment_trait_counter(name)
+    }
+
+    fn track_experiment(&self, experiment: &str, variant: &str) -> SendResult {
+        Instance::default_instance(self).track_experiment(experiment, variant)
+    }
+
+    fn export_analytics_data(&self) -> AnalyticsData {
+        Instance::default_instance(self).export_analytics_data()
+    }
+
+    fn wipe_analytics_data(&self) -> Result<(), WipeError> {
+        Instance::default_instance(self).wipe_analytics_data()
+    }
+
+    fn prompt_consent(&self) -> Result<ConsentState, ConsentError> {
+        Instance::default_instance(self).prompt_consent()
+    }
+
+    fn answer_consent(&self, granted: bool) -> Result<ConsentState, ConsentError> {
+        Instance::default_instance(self).answer_consent(granted)
+    }
+
+    fn set_consent_category(&self, category: String, granted: bool) -> ConsentState {
+        Instance::default_instance(self).set_consent_category(category, granted)
+    }
+
+    fn recorded_events(&self) -> Vec<rudderanalytics::message::Message> {
+        Instance::default_instance(self).recorded_events()
+    }
+
+    fn clear_recorded_events(&self) {
+        Instance::default_instance(self).clear_recorded_events()
+    }
+
+    fn assert_tracked(&self, event: &str) {
+        Instance::default_instance(self).assert_tracked(event)
+    }
+
+    fn replay_recent_events(&self, count: usize) -> Vec<ReplayDiff> {
+        Instance::default_instance(self).replay_recent_events(count)
+    }
+
+    fn replay_from_file(&self, path: impl AsRef<Path>) -> std::io::Result<Vec<SendResult>> {
+        Instance::default_instance(self).replay_from_file(path)
+    }
+
+    #[cfg(feature = "local-store")]
+    fn export_local_store(&self, path: impl AsRef<Path>) -> Result<usize, crate::LocalStoreError> {
+        Instance::default_instance(self).export_local_store(path)
+    }
+}
 
 impl<R: Runtime> AnalyticsExt<R> for tauri::App<R> {
-    fn send_analytic(
-        &self,
-        event: types::Message,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>> {
+    fn instance(&self, name: &str) -> Option<Instance<R>> {
+        self.handle().instance(name)
+    }
+
+    fn send_analytic(&self, event: types::Message) -> SendResult {
         self.handle().send_analytic(event)
     }
 
@@ -156,6 +630,10 @@ impl<R: Runtime> AnalyticsExt<R> for tauri::App<R> {
         self.handle().set_user_id(id)
     }
 
+    fn set_data_plane(&self, url: String) -> Result<(), DataPlaneError> {
+        self.handle().set_data_plane(url)
+    }
+
     fn add_to_context(&self, key: String, value: serde_json::Value) -> Option<serde_json::Value> {
         self.handle().add_to_context(key, value)
     }
@@ -171,4 +649,242 @@ impl<R: Runtime> AnalyticsExt<R> for tauri::App<R> {
     fn get_context(&self) -> crate::types::Context {
         self.handle().get_context()
     }
-}
\ No newline at end of file
+
+    fn forced_sends(&self) -> u64 {
+        self.handle().forced_sends()
+    }
+
+    fn metrics(&self) -> Metrics {
+        self.handle().metrics()
+    }
+
+    fn check_analytics_connectivity(&self) -> tauri::async_runtime::JoinHandle<Connectivity> {
+        self.handle().check_analytics_connectivity()
+    }
+
+    fn memory_usage(&self) -> (usize, usize) {
+        self.handle().memory_usage()
+    }
+
+    fn consent_state(&self) -> ConsentState {
+        self.handle().consent_state()
+    }
+
+    fn should_prompt_consent(&self) -> bool {
+        self.handle().should_prompt_consent()
+    }
+
+    fn is_analytics_allowed(&self) -> bool {
+        self.handle().is_analytics_allowed()
+    }
+
+    fn audit_log(&self) -> Vec<AuditEntry> {
+        self.handle().audit_log()
+    }
+
+    fn analytics_status(&self) -> AnalyticsStatus {
+        self.handle().analytics_status()
+    }
+
+    fn last_active(&self) -> Option<DateTime<Utc>> {
+        self.handle().last_active()
+    }
+
+    fn increment_trait_counter(&self, name: &str) -> u64 {
+        self.handle().increment_trait_counter(name)
+    }
+
+    fn track_experiment(&self, experiment: &str, variant: &str) -> SendResult {
+        self.handle().track_experiment(experiment, variant)
+    }
+
+    fn export_analytics_data(&self) -> AnalyticsData {
+        self.handle().export_analytics_data()
+    }
+
+    fn wipe_analytics_data(&self) -> Result<(), WipeError> {
+        self.handle().wipe_analytics_data()
+    }
+
+    fn prompt_consent(&self) -> Result<ConsentState, ConsentError> {
+        self.handle().prompt_consent()
+    }
+
+    fn answer_consent(&self, granted: bool) -> Result<ConsentState, ConsentError> {
+        self.handle().answer_consent(granted)
+    }
+
+    fn set_consent_category(&self, category: String, granted: bool) -> ConsentState {
+        self.handle().set_consent_category(category, granted)
+    }
+
+    fn recorded_events(&self) -> Vec<rudderanalytics::message::Message> {
+        self.handle().recorded_events()
+    }
+
+    fn clear_recorded_events(&self) {
+        self.handle().clear_recorded_events()
+    }
+
+    fn assert_tracked(&self, event: &str) {
+        self.handle().assert_tracked(event)
+    }
+
+    fn replay_recent_events(&self, count: usize) -> Vec<ReplayDiff> {
+        self.handle().replay_recent_events(count)
+    }
+
+    fn replay_from_file(&self, path: impl AsRef<Path>) -> std::io::Result<Vec<SendResult>> {
+        self.handle().replay_from_file(path)
+    }
+
+    #[cfg(feature = "local-store")]
+    fn export_local_store(&self, path: impl AsRef<Path>) -> Result<usize, crate::LocalStoreError> {
+        self.handle().export_local_store(path)
+    }
+}
+impl<R: Runtime> AnalyticsExt<R> for tauri::Window<R> {
+    fn instance(&self, name: &str) -> Option<Instance<R>> {
+        self.app_handle().instance(name)
+    }
+
+    fn send_analytic(&self, event: types::Message) -> SendResult {
+        Instance::default_instance(self.app_handle())
+            .rudder()
+            .apply_window_context(self.label(), &mut event);
+        window_metadata::enrich_screen(self, &mut event);
+        self.app_handle().send_analytic(event)
+    }
+
+    fn set_anonymous_id(&self, id: String) -> Result<(), config::ClientIdError> {
+        self.app_handle().set_anonymous_id(id)
+    }
+
+    fn set_user_id(&self, id: Option<String>) {
+        self.app_handle().set_user_id(id)
+    }
+
+    fn set_data_plane(&self, url: String) -> Result<(), DataPlaneError> {
+        self.app_handle().set_data_plane(url)
+    }
+
+    fn add_to_context(&self, key: String, value: serde_json::Value) -> Option<serde_json::Value> {
+        Instance::default_instance(self.app_handle())
+            .rudder()
+            .add_to_window_context(self.label(), key, value)
+    }
+
+    fn remove_from_context(&self, key: &str) -> Option<serde_json::Value> {
+        Instance::default_instance(self.app_handle())
+            .rudder()
+            .remove_from_window_context(self.label(), key)
+    }
+
+    fn clear_context(&self) {
+        Instance::default_instance(self.app_handle())
+            .rudder()
+            .clear_window_context(self.label())
+    }
+
+    fn get_context(&self) -> crate::types::Context {
+        Instance::default_instance(self.app_handle())
+            .rudder()
+            .window_context(self.label())
+    }
+
+    fn forced_sends(&self) -> u64 {
+        self.app_handle().forced_sends()
+    }
+
+    fn metrics(&self) -> Metrics {
+        self.app_handle().metrics()
+    }
+
+    fn check_analytics_connectivity(&self) -> tauri::async_runtime::JoinHandle<Connectivity> {
+        self.app_handle().check_analytics_connectivity()
+    }
+
+    fn memory_usage(&self) -> (usize, usize) {
+        self.app_handle().memory_usage()
+    }
+
+    fn consent_state(&self) -> ConsentState {
+        self.app_handle().consent_state()
+    }
+
+    fn should_prompt_consent(&self) -> bool {
+        self.app_handle().should_prompt_consent()
+    }
+
+    fn is_analytics_allowed(&self) -> bool {
+        self.app_handle().is_analytics_allowed()
+    }
+
+    fn audit_log(&self) -> Vec<AuditEntry> {
+        self.app_handle().audit_log()
+    }
+
+    fn analytics_status(&self) -> AnalyticsStatus {
+        self.app_handle().analytics_status()
+    }
+
+    fn last_active(&self) -> Option<DateTime<Utc>> {
+        Instance::default_instance(self.app_handle())
+            .rudder()
+            .idle_tracker()?
+            .last_active(Some(self.label()))
+    }
+
+    fn increment_trait_counter(&self, name: &str) -> u64 {
+        self.app_handle().increment_trait_counter(name)
+    }
+
+    fn track_experiment(&self, experiment: &str, variant: &str) -> SendResult {
+        self.app_handle().track_experiment(experiment, variant)
+    }
+
+    fn export_analytics_data(&self) -> AnalyticsData {
+        self.app_handle().export_analytics_data()
+    }
+
+    fn wipe_analytics_data(&self) -> Result<(), WipeError> {
+        self.app_handle().wipe_analytics_data()
+    }
+
+    fn prompt_consent(&self) -> Result<ConsentState, ConsentError> {
+        self.app_handle().prompt_consent()
+    }
+
+    fn answer_consent(&self, granted: bool) -> Result<ConsentState, ConsentError> {
+        self.app_handle().answer_consent(granted)
+    }
+
+    fn set_consent_category(&self, category: String, granted: bool) -> ConsentState {
+        self.app_handle().set_consent_category(category, granted)
+    }
+
+    fn recorded_events(&self) -> Vec<rudderanalytics::message::Message> {
+        self.app_handle().recorded_events()
+    }
+
+    fn clear_recorded_events(&self) {
+        self.app_handle().clear_recorded_events()
+    }
+
+    fn assert_tracked(&self, event: &str) {
+        self.app_handle().assert_tracked(event)
+    }
+
+    fn replay_recent_events(&self, count: usize) -> Vec<ReplayDiff> {
+        self.app_handle().replay_recent_events(count)
+    }
+
+    fn replay_from_file(&self, path: impl AsRef<Path>) -> std::io::Result<Vec<SendResult>> {
+        self.app_handle().replay_from_file(path)
+    }
+
+    #[cfg(feature = "local-store")]
+    fn export_local_store(&self, path: impl AsRef<Path>) -> Result<usize, crate::LocalStoreError> {
+        self.app_handle().export_local_store(path)
+    }
+}
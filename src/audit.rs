@@ -0,0 +1,62 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::consent::ConsentStatus;
+
+/// The number of entries kept in the audit log, older entries are dropped.
+const CAPACITY: usize = 200;
+
+/// A change of the tracking state of the device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AuditChange {
+    /// The anonymous ID was set or rotated.
+    #[serde(rename_all = "camelCase")]
+    AnonymousIdSet { anonymous_id: String },
+    /// The user ID was set.
+    #[serde(rename_all = "camelCase")]
+    UserIdSet { user_id: String },
+    /// The user ID was cleared.
+    UserIdCleared,
+    /// The consent status changed.
+    #[serde(rename_all = "camelCase")]
+    ConsentChanged {
+        status: ConsentStatus,
+        policy_version: Option<u32>,
+    },
+    /// The consent for a category changed.
+    ConsentCategoryChanged { category: String, granted: bool },
+}
+
+/// An entry of the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// When the change happened.
+    pub at: DateTime<Utc>,
+    /// What changed.
+    pub change: AuditChange,
+}
+
+/// A bounded, append-only log of identity and consent changes, persisted in the config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct AuditLog(VecDeque<AuditEntry>);
+
+impl AuditLog {
+    pub fn record(&mut self, change: AuditChange) {
+        self.0.push_back(AuditEntry {
+            at: Utc::now(),
+            change,
+        });
+        while self.0.len() > CAPACITY {
+            self.0.pop_front();
+        }
+    }
+
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.0.iter().cloned().collect()
+    }
+}
@@ -0,0 +1,81 @@
+//! Saves the config in the background when it changes between the explicit saves,
+//! e.g. when sequence numbers are reserved or a message is delivered.
+
+use std::{
+    sync::{Condvar, Mutex},
+    time::Duration,
+};
+
+use tauri::Runtime;
+
+use crate::instance::Instance;
+
+/// How long the saves are coalesced after a request.
+const DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Requests to save the config, coalesced while a save is pending.
+#[derive(Debug, Default)]
+pub(crate) struct Autosave {
+    pending: Mutex<bool>,
+    requested: Condvar,
+}
+
+impl Autosave {
+    /// Save the config soon.
+    pub fn request(&self) {
+        *self.pending.lock().unwrap() = true;
+        self.requested.notify_one();
+    }
+
+    /// Returns true if a save was requested and has not started yet.
+    pub fn is_pending(&self) -> bool {
+        *self.pending.lock().unwrap()
+    }
+
+    /// Block until a save is requested.
+    fn wait(&self) {
+        let pending = self.pending.lock().unwrap();
+        let mut pending = self
+            .requested
+            .wait_while(pending, |pending| !*pending)
+            .unwrap();
+        *pending = false;
+    }
+}
+
+/// Save the config of the instance whenever a save is requested.
+pub(crate) fn start<R: Runtime>(instance: Instance<R>) {
+    let autosave = instance.rudder().autosave();
+    std::thread::spawn(move || loop {
+        autosave.wait();
+        std::thread::sleep(DEBOUNCE);
+        if let Err(err) = instance.rudder().save(&instance.handle) {
+            tracing::error!("Failed to save config: {:?}", err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn coalesces_requests() {
+        let autosave = Arc::new(Autosave::default());
+        autosave.request();
+        autosave.request();
+        assert!(autosave.is_pending());
+        autosave.wait();
+        assert!(!autosave.is_pending());
+
+        let waiting = {
+            let autosave = autosave.clone();
+            std::thread::spawn(move || autosave.wait())
+        };
+        autosave.request();
+        waiting.join().unwrap();
+        assert!(!autosave.is_pending());
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::priority::Priority;
+
+/// What happens to a new message while the maximum number of pending deliveries is reached,
+/// see [RudderStackBuilder::max_pending_sends](crate::RudderStackBuilder::max_pending_sends).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Drop the message with [DropReason::Backpressured](crate::DropReason::Backpressured).
+    Reject,
+    /// Block the sending thread until a delivery finishes, at most for the duration,
+    /// then drop the message. \
+    /// On a thread of the async runtime, e.g. in the commands of the webview, the message is
+    /// dropped right away instead: the deliveries that free the slots run on that runtime.
+    Block(Duration),
+}
+
+/// Counts the deliveries that are queued or in flight.
+#[derive(Debug)]
+pub(crate) struct PendingSends {
+    max: usize,
+    backpressure: Backpressure,
+    pending: Mutex<usize>,
+    finished: Condvar,
+}
+
+impl PendingSends {
+    pub fn new(max: usize, backpressure: Backpressure) -> Self {
+        Self {
+            max: max.max(1),
+            backpressure,
+            pending: Mutex::new(0),
+            finished: Condvar::new(),
+        }
+    }
+
+    /// Reserve a slot for a delivery, released when the slot is dropped. \
+    /// Returns `None` if no slot became free under the [Backpressure] policy,
+    /// which never blocks a thread of the async runtime.
+    /// [Priority::Debug] messages may only take three quarters of the slots,
+    /// so they are shed before other messages.
+    pub fn reserve(self: &Arc<Self>, priority: Priority) -> Option<PendingSlot> {
+        let max = match priority {
+            Priority::Debug => (self.max * 3 / 4).max(1),
+            _ => self.max,
+        };
+        let mut pending = self.pending.lock().unwrap();
+        let on_runtime = tokio::runtime::Handle::try_current().is_ok();
+        if let (Backpressure::Block(timeout), false) = (self.backpressure, on_runtime) {
+            let deadline = Instant::now() + timeout;
+            while *pending >= max {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                pending = self.finished.wait_timeout(pending, remaining).unwrap().0;
+            }
+        }
+        if *pending >= max {
+            return None;
+        }
+        *pending += 1;
+        Some(PendingSlot(self.clone()))
+    }
+}
+
+/// A reserved slot of [PendingSends].
+#[derive(Debug)]
+pub(crate) struct PendingSlot(Arc<PendingSends>);
+
+impl Drop for PendingSlot {
+    fn drop(&mut self) {
+        *self.0.pending.lock().unwrap() -= 1;
+        // waiters have different limits, a single woken debug waiter may not be able to proceed
+        self.0.finished.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_pending_sends() {
+        let rejecting = Arc::new(PendingSends::new(1, Backpressure::Reject));
+        let slot = rejecting.reserve(Priority::Normal);
+        assert!(slot.is_some());
+        assert!(rejecting.reserve(Priority::Normal).is_none());
+        drop(slot);
+        assert!(rejecting.reserve(Priority::Normal).is_some());
+
+        let blocking = Arc::new(PendingSends::new(
+            1,
+            Backpressure::Block(Duration::from_secs(5)),
+        ));
+        let slot = blocking.reserve(Priority::Normal);
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            drop(slot);
+        });
+        assert!(blocking.reserve(Priority::Normal).is_some());
+        release.join().unwrap();
+
+        let timing_out = Arc::new(PendingSends::new(
+            1,
+            Backpressure::Block(Duration::from_millis(10)),
+        ));
+        let _slot = timing_out.reserve(Priority::Normal);
+        assert!(timing_out.reserve(Priority::Normal).is_none());
+    }
+
+    #[test]
+    fn does_not_block_the_async_runtime() {
+        let blocking = Arc::new(PendingSends::new(
+            1,
+            Backpressure::Block(Duration::from_secs(5)),
+        ));
+        let _slot = blocking.reserve(Priority::Normal);
+        let start = Instant::now();
+        tauri::async_runtime::block_on(async {
+            assert!(blocking.reserve(Priority::Normal).is_none());
+        });
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn sheds_debug_messages_first() {
+        let pending_sends = Arc::new(PendingSends::new(4, Backpressure::Reject));
+        let _slots: Vec<_> = (0..3)
+            .map(|_| pending_sends.reserve(Priority::Normal))
+            .collect();
+        assert!(pending_sends.reserve(Priority::Debug).is_none());
+        assert!(pending_sends.reserve(Priority::Identity).is_some());
+    }
+}
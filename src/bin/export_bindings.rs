@@ -0,0 +1,40 @@
+//! Regenerates `guest-js/bindings.ts`, run with
+//!
+//! `cargo run --bin export-bindings --features build-types`
+//!
+//! Pass `--check` to fail instead if the bindings are out of date, e.g. in CI.
+
+use std::process::ExitCode;
+
+use tauri_plugin_rudderstack::bindings;
+
+const EXPORT: &str = "cargo run --bin export-bindings --features build-types";
+
+fn main() -> ExitCode {
+    let check = std::env::args().skip(1).any(|arg| arg == "--check");
+
+    if check {
+        return match bindings::is_current(bindings::PATH) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => {
+                eprintln!("{} is out of date, run `{}`", bindings::PATH, EXPORT);
+                ExitCode::FAILURE
+            }
+            Err(err) => {
+                eprintln!("failed to export the bindings: {}", err);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    match bindings::export(bindings::PATH) {
+        Ok(()) => {
+            println!("exported the bindings to {}", bindings::PATH);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("failed to export the bindings: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Exports the TypeScript bindings in `guest-js/bindings.ts`,
+//! see the `export-bindings` binary.
+
+use std::path::Path;
+
+use specta_typescript::{BigIntExportBehavior, ExportError, Typescript};
+
+use crate::{init_commands, PLUGIN_NAME};
+
+/// The bindings checked into the repository.
+pub const PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/guest-js/bindings.ts");
+
+fn language() -> Typescript {
+    Typescript::default()
+        .formatter(specta_typescript::formatter::eslint)
+        .bigint(BigIntExportBehavior::BigInt)
+        .header("/* eslint-disable */ \n // @ts-nocheck")
+}
+
+/// Write the bindings for the current commands, events and types to `path`.
+pub fn export(path: impl AsRef<Path>) -> Result<(), ExportError> {
+    init_commands::<tauri::Wry>().export(language(), path)
+}
+
+/// Returns false if the bindings at `path` differ from a fresh export, or do not exist.
+pub fn is_current(path: impl AsRef<Path>) -> Result<bool, ExportError> {
+    let fresh = std::env::temp_dir().join(format!("{}-bindings.ts", PLUGIN_NAME));
+    export(&fresh)?;
+    let exported = std::fs::read_to_string(&fresh)?;
+    std::fs::remove_file(&fresh).ok();
+    Ok(std::fs::read_to_string(path).is_ok_and(|current| current == exported))
+}
@@ -0,0 +1,149 @@
+//! Stops deliveries after repeated failures, see [RudderStackBuilder::circuit_breaker](crate::RudderStackBuilder::circuit_breaker).
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::OwnedMutexGuard;
+
+use crate::{
+    delivery_gate::{DeliveryGate, PauseReason},
+    transport::DeliveryError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CircuitState {
+    /// Deliveries are attempted, counting the consecutive failures.
+    Closed(u32),
+    /// Deliveries wait for the cool-down.
+    Open,
+    /// A single delivery probes the data plane.
+    HalfOpen,
+}
+
+/// How a delivery may proceed, see [CircuitBreaker::admit].
+pub(crate) enum Admission {
+    Deliver,
+    /// The delivery probes the data plane, the other deliveries wait until it finished.
+    Probe(OwnedMutexGuard<()>),
+    /// The circuit opened again, wait for the [DeliveryGate].
+    Wait,
+}
+
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    threshold: u32,
+    cool_down: Duration,
+    state: Mutex<CircuitState>,
+    probe: Arc<tokio::sync::Mutex<()>>,
+    gate: Arc<DeliveryGate>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cool_down: Duration, gate: Arc<DeliveryGate>) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cool_down,
+            state: Mutex::new(CircuitState::Closed(0)),
+            probe: Arc::default(),
+            gate,
+        }
+    }
+
+    /// Call after passing the [DeliveryGate].
+    pub async fn admit(&self) -> Admission {
+        if *self.state.lock().unwrap() != CircuitState::HalfOpen {
+            return Admission::Deliver;
+        }
+        let probe = self.probe.clone().lock_owned().await;
+        match *self.state.lock().unwrap() {
+            CircuitState::Closed(_) => Admission::Deliver,
+            CircuitState::Open => Admission::Wait,
+            CircuitState::HalfOpen => Admission::Probe(probe),
+        }
+    }
+
+    /// Record the result of a delivery, opening the circuit after too many consecutive failures
+    /// or a failed probe. Only failures of the data plane count, see [DeliveryError::is_outage].
+    pub fn record(self: &Arc<Self>, result: &Result<(), DeliveryError>) {
+        if result.as_ref().is_err_and(|err| !err.is_outage()) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        match (*state, result) {
+            (CircuitState::Open, _) => {}
+            (_, Ok(_)) => {
+                if *state == CircuitState::HalfOpen {
+                    tracing::info!("data plane recovered, closing the circuit");
+                }
+                *state = CircuitState::Closed(0);
+                self.gate.resume(PauseReason::CircuitOpen);
+            }
+            (CircuitState::Closed(failures), Err(_)) if failures + 1 < self.threshold => {
+                *state = CircuitState::Closed(failures + 1);
+            }
+            (_, Err(_)) => {
+                tracing::warn!(cool_down = ?self.cool_down, "deliveries keep failing, opening the circuit");
+                *state = CircuitState::Open;
+                self.gate.pause(PauseReason::CircuitOpen);
+                let breaker = self.clone();
+                tauri::async_runtime::spawn(async move {
+                    tokio::time::sleep(breaker.cool_down).await;
+                    *breaker.state.lock().unwrap() = CircuitState::HalfOpen;
+                    breaker.gate.resume(PauseReason::CircuitOpen);
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rudderanalytics::errors::Error;
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = Arc::new(CircuitBreaker::new(
+            2,
+            Duration::from_secs(60),
+            Arc::default(),
+        ));
+        let failure = || Err(DeliveryError::with_status(500, None));
+        breaker.record(&failure());
+        breaker.record(&Ok(()));
+        breaker.record(&failure());
+        assert!(!breaker.gate.is_paused(PauseReason::CircuitOpen));
+        breaker.record(&failure());
+        assert!(breaker.gate.is_paused(PauseReason::CircuitOpen));
+
+        *breaker.state.lock().unwrap() = CircuitState::HalfOpen;
+        breaker.record(&Ok(()));
+        assert!(!breaker.gate.is_paused(PauseReason::CircuitOpen));
+        assert_eq!(*breaker.state.lock().unwrap(), CircuitState::Closed(0));
+    }
+
+    #[test]
+    fn counts_only_outages() {
+        let breaker = Arc::new(CircuitBreaker::new(
+            1,
+            Duration::from_secs(60),
+            Arc::default(),
+        ));
+        breaker.record(&Err(DeliveryError::with_status(400, None)));
+        breaker.record(&Err(DeliveryError::with_status(413, None)));
+        let rejected = |error: Error| Err(DeliveryError::from(error));
+        breaker.record(&rejected(Error::InvalidRequest("no user ID".to_string())));
+        breaker.record(&rejected(Error::MessageTooLarge("too large".to_string())));
+        assert!(!breaker.gate.is_paused(PauseReason::CircuitOpen));
+
+        breaker.record(&Err(DeliveryError::with_status(
+            429,
+            Some(Duration::from_secs(5)),
+        )));
+        assert!(breaker.gate.is_paused(PauseReason::CircuitOpen));
+
+        assert!(DeliveryError::with_status(503, None).is_outage());
+    }
+}
@@ -1,63 +1,298 @@
-use tauri::{AppHandle, Runtime};
-use tracing::error;
+use tauri::{AppHandle, Manager as _, Runtime, Window};
+use tracing::{error, warn};
 
 use crate::{
-    types::{Alias, Group, Identify, Page, Screen, Track},
+    audit::AuditEntry,
+    consent::ConsentState,
+    data::AnalyticsData,
+    idle,
+    instance::Instance,
+    integrity::AnalyticsStatus,
+    metrics::Metrics,
+    rudder_wrapper::RudderWrapper,
+    send_result::SendStatus,
+    transport::Connectivity,
+    types::{Alias, Batch, BatchMessage, Group, Identify, Message, Page, Screen, Track},
     AnalyticsExt as _,
 };
 
 macro_rules! handle_error {
     ($result:expr) => {
-        match $result {
-            Ok(Ok(())) => {}
-            Ok(Err(e)) => {
-                error!("Failed to send analytics event: {:?}", e);
-            }
-            Err(e) => {
-                error!("Failed to send analytics event: {:?}", e);
-            }
+        if let SendStatus::Failed(e) = $result.status() {
+            error!("Failed to send analytics event: {:?}", e);
         }
     };
 }
 
+/// Run an event sent from `window` through the command middleware and queue it. \
+/// The allowed events are checked after the middleware, which may normalize the event names. \
+/// Returns without waiting for the delivery, which is held e.g. while offline,
+/// its result is emitted as an [EventSent](crate::EventSent) or [EventFailed](crate::EventFailed) event.
+fn send_from_window<R: Runtime>(app: &AppHandle<R>, window: &Window<R>, mut event: Message) {
+    let rudder = app.state::<RudderWrapper>();
+    if !rudder.apply_command_middleware(window.label(), &mut event) {
+        return;
+    }
+    let allowed = |event: &str| {
+        let allowed = rudder.is_event_allowed(event);
+        if !allowed {
+            warn!(
+                event,
+                "rejected track event that is not in the allowed events"
+            );
+        }
+        allowed
+    };
+    match &mut event {
+        Message::Track(track) if !allowed(&track.event) => return,
+        Message::Batch(batch) => {
+            batch.batch.retain(|message| match message {
+                BatchMessage::Track(track) => allowed(&track.event),
+                _ => true,
+            });
+            if batch.batch.is_empty() {
+                return;
+            }
+        }
+        _ => {}
+    }
+    handle_error!(window.send_analytic(event));
+}
+
 #[tauri::command]
 #[specta::specta]
 /// Send an analytics event to the RudderStack data plane.
-pub async fn send_analytics_alias<R: Runtime>(app: AppHandle<R>, event: Alias) {
-    handle_error!(app.send_analytic_alias(event).await);
+pub async fn send_analytics_alias<R: Runtime>(app: AppHandle<R>, window: Window<R>, event: Alias) {
+    send_from_window(&app, &window, Message::Alias(event));
 }
 
 #[tauri::command]
 #[specta::specta]
 /// Send an analytics event to the RudderStack data plane.
-pub async fn send_analytics_group<R: Runtime>(app: AppHandle<R>, event: Group) {
-    handle_error!(app.send_analytic_group(event).await);
+pub async fn send_analytics_group<R: Runtime>(app: AppHandle<R>, window: Window<R>, event: Group) {
+    send_from_window(&app, &window, Message::Group(event));
 }
 
 #[tauri::command]
 #[specta::specta]
 /// Send an [Identify] event to the RudderStack data plane.
-pub async fn send_analytics_identify<R: Runtime>(app: AppHandle<R>, event: Identify) {
-    handle_error!(app.send_analytic_identify(event).await);
+pub async fn send_analytics_identify<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    event: Identify,
+) {
+    send_from_window(&app, &window, Message::Identify(event));
 }
 
 #[tauri::command]
 #[specta::specta]
 /// Send a [Page] event to the RudderStack data plane.
-pub async fn send_analytics_page<R: Runtime>(app: AppHandle<R>, event: Page) {
-    handle_error!(app.send_analytic_page(event).await);
+pub async fn send_analytics_page<R: Runtime>(app: AppHandle<R>, window: Window<R>, event: Page) {
+    send_from_window(&app, &window, Message::Page(event));
 }
 
 #[tauri::command]
 #[specta::specta]
 /// Send a [Screen] event to the RudderStack data plane.
-pub async fn send_analytics_screen<R: Runtime>(app: AppHandle<R>, event: Screen) {
-    handle_error!(app.send_analytic_screen(event).await);
+pub async fn send_analytics_screen<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    event: Screen,
+) {
+    send_from_window(&app, &window, Message::Screen(event));
 }
 
 #[tauri::command]
 #[specta::specta]
 /// Send a [Track] event to the RudderStack data plane.
-pub async fn send_analytics_track<R: Runtime>(app: AppHandle<R>, event: Track) {
-    handle_error!(app.send_analytic_track(event).await);
+pub async fn send_analytics_track<R: Runtime>(app: AppHandle<R>, window: Window<R>, event: Track) {
+    send_from_window(&app, &window, Message::Track(event));
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Send a [Batch] of track, page and screen events to the RudderStack data plane in one request. \
+/// Other events, and track events that are not in the allowed events, are removed from the batch.
+pub async fn send_analytics_batch<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    mut event: Batch,
+) {
+    event.batch.retain(|message| {
+        let batchable = is_batchable(message);
+        if !batchable {
+            warn!("rejected identity event in a batch, use its own command");
+        }
+        batchable
+    });
+    if event.batch.is_empty() {
+        return;
+    }
+    send_from_window(&app, &window, Message::Batch(event));
+}
+
+/// Only track, page and screen events may be batched,
+/// so the permissions of the identify, group and alias commands apply to the webview.
+fn is_batchable(message: &BatchMessage) -> bool {
+    matches!(
+        message,
+        BatchMessage::Track(_) | BatchMessage::Page(_) | BatchMessage::Screen(_)
+    )
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get the persisted consent state of the user.
+pub async fn get_consent_state<R: Runtime>(app: AppHandle<R>) -> ConsentState {
+    app.consent_state()
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Returns true if the user should be prompted for consent.
+pub async fn should_prompt_consent<R: Runtime>(app: AppHandle<R>) -> bool {
+    app.should_prompt_consent()
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Mark that the user is being prompted for consent.
+pub async fn prompt_consent<R: Runtime>(app: AppHandle<R>) -> Result<ConsentState, String> {
+    app.prompt_consent().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Record the answer of the user to the consent prompt.
+pub async fn answer_consent<R: Runtime>(
+    app: AppHandle<R>,
+    granted: bool,
+) -> Result<ConsentState, String> {
+    app.answer_consent(granted).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Record the consent of the user for a category, e.g. `marketing`.
+pub async fn set_consent_category<R: Runtime>(
+    app: AppHandle<R>,
+    category: String,
+    granted: bool,
+) -> ConsentState {
+    app.set_consent_category(category, granted)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get a snapshot of the delivery counters: events sent, failed, dropped and queued.
+pub async fn get_metrics<R: Runtime>(app: AppHandle<R>) -> Metrics {
+    app.metrics()
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Returns true if non-essential events are currently delivered to the data plane,
+/// taking consent, mock and dry run mode into account.
+pub async fn is_analytics_allowed<R: Runtime>(app: AppHandle<R>) -> bool {
+    app.is_analytics_allowed()
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Check that the data plane is reachable, without sending an event.
+pub async fn check_analytics_connectivity<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<Connectivity, String> {
+    app.check_analytics_connectivity()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get the log of identity and consent changes on this device, oldest first.
+pub async fn get_audit_log<R: Runtime>(app: AppHandle<R>) -> Vec<AuditEntry> {
+    app.audit_log()
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Increment a persisted lifetime counter, e.g. `total_exports`, and return its new value.
+/// The counters are included in the traits of every identify event.
+/// The returned value is clamped to `4294967295` (`u32::MAX`), which a JavaScript number
+/// represents exactly, the counter itself keeps counting.
+pub async fn increment_trait_counter<R: Runtime>(app: AppHandle<R>, name: String) -> u32 {
+    u32::try_from(app.increment_trait_counter(&name)).unwrap_or(u32::MAX)
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get all analytics data stored on this device, for data subject access requests.
+pub async fn export_analytics_data<R: Runtime>(app: AppHandle<R>) -> AnalyticsData {
+    app.export_analytics_data()
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Delete all analytics data stored on this device, for data subject deletion requests.
+/// Subsequent events use a new anonymous ID.
+pub async fn wipe_analytics_data<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+    app.wipe_analytics_data().map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Get the status of the plugin, including an unexpected reset of the anonymous ID detected on startup.
+pub async fn get_analytics_status<R: Runtime>(app: AppHandle<R>) -> AnalyticsStatus {
+    app.analytics_status()
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Send a Page event for a navigation of the webview, called by the automatic page tracking.
+pub async fn track_page_view<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    url: String,
+    title: Option<String>,
+) {
+    let Some(page) = app.state::<RudderWrapper>().page_view(&url, title) else {
+        return;
+    };
+    send_from_window(&app, &window, Message::Page(page));
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Record user input in the webview, called by the idle detection.
+pub async fn report_activity<R: Runtime>(app: AppHandle<R>, window: Window<R>) {
+    idle::activity(&Instance::default_instance(&app), window.label());
+}
+
+#[tauri::command]
+#[specta::specta]
+/// Set the user ID sent with the following events, `None` to clear it, e.g. on sign out. \
+/// The `identify` event, e.g. with the traits of the user, is sent instead of the Identify event
+/// of a new user, and also if the user ID was set before.
+pub async fn set_analytics_user_id<R: Runtime>(
+    app: AppHandle<R>,
+    user_id: Option<String>,
+    identify: Option<Identify>,
+) {
+    Instance::default_instance(&app).identify_user(user_id, identify);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_batches_track_page_and_screen_events() {
+        assert!(is_batchable(&BatchMessage::Track(Track::default())));
+        assert!(is_batchable(&BatchMessage::Page(Page::default())));
+        assert!(is_batchable(&BatchMessage::Screen(Screen::default())));
+        assert!(!is_batchable(&BatchMessage::Identify(Identify::default())));
+        assert!(!is_batchable(&BatchMessage::Group(Group::default())));
+        assert!(!is_batchable(&BatchMessage::Alias(Alias::default())));
+    }
 }
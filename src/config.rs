@@ -1,16 +1,57 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use tauri::{AppHandle, Manager, Runtime};
 use tracing::debug;
 
+use crate::{
+    audit::{AuditChange, AuditEntry, AuditLog},
+    connected_ids::ConnectedIds,
+    consent::ConsentState,
+    dedup::SentMessageIds,
+    memory::MemoryBudget,
+    storage::{ConfigStore, Storage, StoreError},
+};
+
+/// Sequence numbers are reserved on disk ahead of the events in blocks of this size,
+/// so they are not reused after a crash, at the cost of a gap.
+const SEQUENCE_BLOCK: u64 = 100;
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct Config {
     /// The anonymous ID of the user. this is normally generated and saved in the file.
     anonymous_id: String,
-    /// A map of user IDs to anonymous IDs. this is used to connect the user ID to the anonymous ID.
-    connected_ids: HashMap<String, String>,
+    /// The user IDs connected to anonymous IDs, bounded to the most recently used ones.
+    #[serde(default)]
+    connected_ids: ConnectedIds,
     /// The user ID of the user. this is used to identify the user.
     user_id: Option<String>,
+    /// The consent state of the user.
+    #[serde(default)]
+    consent: ConsentState,
+    /// The IDs of recently sent messages, used to prevent duplicate delivery.
+    #[serde(default)]
+    sent_message_ids: SentMessageIds,
+    /// The highest sequence number that may have been used, see [SEQUENCE_BLOCK].
+    /// Released to the sequence number of the last event on exit.
+    #[serde(default, rename = "sequence")]
+    reserved_sequence: u64,
+    /// The sequence number of the last event, used to detect gaps and duplicates in the warehouse.
+    /// Continues after the reserved sequence numbers when loaded.
+    #[serde(skip)]
+    sequence: Option<u64>,
+    /// The log of identity and consent changes.
+    #[serde(default)]
+    audit_log: AuditLog,
+    /// Lifetime counters included in the traits of identify events, e.g. `total_sessions`.
+    #[serde(default)]
+    trait_counters: BTreeMap<String, u64>,
+    /// The version of the app on the last launch, used to detect installed updates.
+    #[serde(default)]
+    app_version: Option<String>,
 }
 
 impl Default for Config {
@@ -23,8 +64,15 @@ impl Config {
     pub fn new(anonymous_id: String) -> Self {
         Self {
             anonymous_id,
-            connected_ids: HashMap::new(),
+            connected_ids: ConnectedIds::default(),
             user_id: None,
+            consent: ConsentState::default(),
+            sent_message_ids: SentMessageIds::default(),
+            reserved_sequence: 0,
+            sequence: None,
+            audit_log: AuditLog::default(),
+            trait_counters: BTreeMap::new(),
+            app_version: None,
         }
     }
 
@@ -35,6 +83,11 @@ impl Config {
 
     /// Set the anonymous ID of the user.
     pub fn set_anonymous_id(&mut self, anonymous_id: String) {
+        if self.anonymous_id != anonymous_id {
+            self.audit_log.record(AuditChange::AnonymousIdSet {
+                anonymous_id: anonymous_id.clone(),
+            });
+        }
         self.anonymous_id = anonymous_id;
     }
 
@@ -48,47 +101,371 @@ impl Config {
     /// if the user ID is passed in is Some, it will return Some(true) if the user ID is already connected to the anonymous ID. \
     /// if the user ID is passed in is Some, it will return Some(false) if the user ID is not connected to the anonymous ID.
     pub fn set_user_id(&mut self, user_id: Option<String>) -> Option<bool> {
+        if self.user_id != user_id {
+            self.audit_log.record(match &user_id {
+                Some(user_id) => AuditChange::UserIdSet {
+                    user_id: user_id.clone(),
+                },
+                None => AuditChange::UserIdCleared,
+            });
+        }
         self.user_id = user_id.clone();
-        if let Some(id) = user_id {
-            if let std::collections::hash_map::Entry::Vacant(e) = self.connected_ids.entry(id) {
-                e.insert(self.anonymous_id.clone());
-                Some(false)
-            } else {
-                Some(true)
-            }
-        } else {
-            None
+        user_id.map(|id| self.connected_ids.connect(id, &self.anonymous_id))
+    }
+
+    /// Limit the number of remembered user IDs, evicting the least recently used ones.
+    pub fn set_connected_ids_capacity(&mut self, capacity: usize) {
+        self.connected_ids.set_capacity(capacity);
+    }
+
+    /// Account the remembered user IDs in the memory budget.
+    pub fn set_connected_ids_budget(&mut self, budget: Arc<MemoryBudget>) {
+        self.connected_ids.set_budget(budget);
+    }
+
+    /// Get the consent state of the user.
+    pub fn consent(&self) -> &ConsentState {
+        &self.consent
+    }
+
+    /// Get a mutable reference to the consent state of the user.
+    pub fn consent_mut(&mut self) -> &mut ConsentState {
+        &mut self.consent
+    }
+
+    /// Increment and return the sequence number of this client,
+    /// and true if more sequence numbers were reserved and the config should be saved.
+    pub fn next_sequence(&mut self) -> (u64, bool) {
+        let sequence = self.sequence.unwrap_or(self.reserved_sequence) + 1;
+        self.sequence = Some(sequence);
+        let reserve = sequence + SEQUENCE_BLOCK / 2 > self.reserved_sequence;
+        if reserve {
+            self.reserved_sequence = sequence + SEQUENCE_BLOCK;
+        }
+        (sequence, reserve)
+    }
+
+    /// Reserve a block of sequence numbers before the first event, saved on startup.
+    pub fn reserve_sequence(&mut self) {
+        let sequence = *self.sequence.get_or_insert(self.reserved_sequence);
+        self.reserved_sequence = self.reserved_sequence.max(sequence + SEQUENCE_BLOCK);
+    }
+
+    /// Release the reserved sequence numbers that were not used, saved on exit.
+    pub fn release_sequence(&mut self) {
+        if let Some(sequence) = self.sequence {
+            self.reserved_sequence = sequence;
         }
     }
 
-    /// Save the config to a file.
-    pub fn save<R: Runtime>(&self, handle: &AppHandle<R>) -> Result<(), ClientIdError> {
+    /// Increment a trait counter and return its new value.
+    pub fn increment_trait_counter(&mut self, name: &str) -> u64 {
+        let counter = self.trait_counters.entry(name.to_string()).or_default();
+        *counter += 1;
+        *counter
+    }
+
+    /// The lifetime counters included in the traits of identify events.
+    pub fn trait_counters(&self) -> &BTreeMap<String, u64> {
+        &self.trait_counters
+    }
+
+    /// Set the version of the app and return the version of the last launch.
+    pub fn set_app_version(&mut self, version: String) -> Option<String> {
+        self.app_version.replace(version)
+    }
+
+    /// Record a change of the consent state in the audit log.
+    pub(crate) fn record_consent_change(&mut self) {
+        self.audit_log.record(AuditChange::ConsentChanged {
+            status: self.consent.status,
+            policy_version: self.consent.policy_version,
+        });
+    }
+
+    /// Record a change in the audit log.
+    pub(crate) fn record_audit(&mut self, change: AuditChange) {
+        self.audit_log.record(change);
+    }
+
+    /// The log of identity and consent changes, oldest first.
+    pub fn audit_log(&self) -> Vec<AuditEntry> {
+        self.audit_log.entries()
+    }
+
+    pub(crate) fn sent_message_ids(&self) -> &SentMessageIds {
+        &self.sent_message_ids
+    }
+
+    pub(crate) fn set_sent_message_ids(&mut self, ids: SentMessageIds) {
+        self.sent_message_ids = ids;
+    }
+
+    /// Save the config to the store of the instance, the config file by default,
+    /// and record the anonymous ID in the marker file used to detect unexpected resets.
+    pub fn save<R: Runtime>(
+        &self,
+        handle: &AppHandle<R>,
+        file: &ConfigFile,
+    ) -> Result<(), ClientIdError> {
+        if file.is_ephemeral() {
+            return Ok(());
+        }
         debug!("saving config");
-        let path = Self::get_path(handle)?;
-        let config = serde_json::to_vec(&self)?;
-        Ok(std::fs::write(&path, config)?)
+        let mut config = serde_json::to_value(self)?;
+        file.save_identity(handle, &mut config)?;
+        match &file.store {
+            Some(store) => store.save(&config).map_err(ClientIdError::Store)?,
+            None => file.write(&file.path(handle)?, serde_json::to_vec(&config)?)?,
+        }
+        if !file.uses_keyring() {
+            if let Err(err) = Self::write_marker(handle, file, &self.anonymous_id) {
+                tracing::error!("Failed to write the anonymous id marker: {:?}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// The anonymous ID recorded by the last save, if any. \
+    /// The marker is not used if the identity is stored in the keyring, apart from the config file.
+    pub(crate) fn read_marker<R: Runtime>(
+        handle: &AppHandle<R>,
+        file: &ConfigFile,
+    ) -> Option<String> {
+        if file.uses_keyring() || file.is_ephemeral() {
+            return None;
+        }
+        let path = file.marker_path(handle).ok()?;
+        let marker = String::from_utf8(file.read(&path).ok()?).ok()?;
+        Some(marker.trim().to_string()).filter(|id| !id.is_empty())
+    }
+
+    fn write_marker<R: Runtime>(
+        handle: &AppHandle<R>,
+        file: &ConfigFile,
+        anonymous_id: &str,
+    ) -> Result<(), ClientIdError> {
+        let path = file.marker_path(handle)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        file.write(&path, anonymous_id.as_bytes().to_vec())
+    }
+
+    /// Delete the stored config of the instance, if it exists.
+    pub fn delete<R: Runtime>(
+        handle: &AppHandle<R>,
+        file: &ConfigFile,
+    ) -> Result<(), ClientIdError> {
+        if file.is_ephemeral() {
+            return Ok(());
+        }
+        debug!("deleting config");
+        let mut paths = vec![file.marker_path(handle)?, file.crash_report_path(handle)?];
+        match &file.store {
+            Some(store) => store.delete().map_err(ClientIdError::Store)?,
+            None => paths.push(file.path(handle)?),
+        }
+        for path in paths {
+            match std::fs::remove_file(&path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(ClientIdError::Delete(err))
+                }
+                _ => {}
+            }
+        }
+        file.clear_identity(handle)
     }
 
-    /// Load the config from a file.
-    pub fn load<R: Runtime>(handle: &AppHandle<R>) -> Self {
+    /// Load the config from the store of the instance, the config file by default.
+    pub fn load<R: Runtime>(handle: &AppHandle<R>, file: &ConfigFile) -> Self {
         debug!("loading config");
 
-        Self::try_load(handle).unwrap_or_default()
+        Self::try_load(handle, file).unwrap_or_default()
     }
 
-    pub(crate) fn try_load<R: Runtime>(handle: &AppHandle<R>) -> Result<Self, ClientIdError> {
-        let path = Self::get_path(handle)?;
-        let config = std::fs::read(&path)?;
-        Ok(serde_json::from_slice(&config)?)
+    pub(crate) fn try_load<R: Runtime>(
+        handle: &AppHandle<R>,
+        file: &ConfigFile,
+    ) -> Result<Self, ClientIdError> {
+        if file.is_ephemeral() {
+            return Err(ClientIdError::NotStored);
+        }
+        let mut config = match &file.store {
+            Some(store) => store
+                .load()
+                .map_err(ClientIdError::Store)?
+                .ok_or(ClientIdError::NotStored)?,
+            None => serde_json::from_slice(&file.read(&file.path(handle)?)?)?,
+        };
+        file.load_identity(handle, &mut config)?;
+        Ok(serde_json::from_value(config)?)
+    }
+}
+
+/// Where the config of an instance is persisted, a [ConfigStore] or the config file.
+/// The files are encrypted if a [KeyProvider](crate::KeyProvider) is configured.
+#[derive(Clone, Default)]
+pub(crate) struct ConfigFile {
+    instance: Option<String>,
+    #[cfg(feature = "encryption")]
+    key_provider: Option<Arc<dyn crate::KeyProvider>>,
+    storage: Storage,
+    store: Option<Arc<dyn ConfigStore>>,
+}
+
+impl ConfigFile {
+    pub fn new(instance: Option<String>) -> Self {
+        Self {
+            instance,
+            ..Self::default()
+        }
+    }
+
+    /// Encrypt the files with the key of `key_provider`.
+    #[cfg(feature = "encryption")]
+    pub fn with_key_provider(mut self, key_provider: Arc<dyn crate::KeyProvider>) -> Self {
+        self.key_provider = Some(key_provider);
+        self
+    }
+
+    /// Persist the config in `store` instead of the config file.
+    pub fn with_store(mut self, store: Arc<dyn ConfigStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Store the identity in `storage`.
+    pub fn with_storage(mut self, storage: Storage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Returns true if nothing is persisted, see [Storage::Memory].
+    pub fn is_ephemeral(&self) -> bool {
+        self.storage == Storage::Memory
     }
 
-    fn get_path<R: Runtime>(handle: &AppHandle<R>) -> Result<PathBuf, ClientIdError> {
-        let path = handle
-            .path()
-            .app_config_dir()?
-            .join("tauri-rudderstack.json");
+    pub(crate) fn uses_keyring(&self) -> bool {
+        #[cfg(feature = "keyring")]
+        if self.storage == Storage::Keyring {
+            return true;
+        }
+        false
+    }
+
+    /// Move the identity out of the serialized config into the keyring, if it is used.
+    #[cfg_attr(not(feature = "keyring"), allow(unused_variables))]
+    fn save_identity<R: Runtime>(
+        &self,
+        handle: &AppHandle<R>,
+        config: &mut serde_json::Value,
+    ) -> Result<(), ClientIdError> {
+        #[cfg(feature = "keyring")]
+        if let (Storage::Keyring, serde_json::Value::Object(config)) = (self.storage, config) {
+            let anonymous_id = config.remove("anonymous_id");
+            let user_id = config.remove("user_id");
+            crate::storage::keyring::save(
+                &handle.config().identifier,
+                self.instance.as_deref(),
+                anonymous_id
+                    .as_ref()
+                    .and_then(|id| id.as_str())
+                    .unwrap_or_default(),
+                user_id.as_ref().and_then(|id| id.as_str()),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Add the identity from the keyring to the serialized config, if it is used.
+    #[cfg_attr(not(feature = "keyring"), allow(unused_variables))]
+    fn load_identity<R: Runtime>(
+        &self,
+        handle: &AppHandle<R>,
+        config: &mut serde_json::Value,
+    ) -> Result<(), ClientIdError> {
+        #[cfg(feature = "keyring")]
+        if let (Storage::Keyring, serde_json::Value::Object(config)) = (self.storage, config) {
+            let (anonymous_id, user_id) = crate::storage::keyring::load(
+                &handle.config().identifier,
+                self.instance.as_deref(),
+            )?;
+            // without an anonymous id the config is treated as missing and a new one is generated
+            let anonymous_id = anonymous_id.ok_or(keyring::Error::NoEntry)?;
+            config.insert("anonymous_id".to_string(), anonymous_id.into());
+            config.insert("user_id".to_string(), user_id.into());
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "keyring"), allow(unused_variables))]
+    fn clear_identity<R: Runtime>(&self, handle: &AppHandle<R>) -> Result<(), ClientIdError> {
+        #[cfg(feature = "keyring")]
+        if self.storage == Storage::Keyring {
+            crate::storage::keyring::clear(&handle.config().identifier, self.instance.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// `tauri-rudderstack.json` for the default instance, `tauri-rudderstack-<name>.json` for named ones.
+    fn path<R: Runtime>(&self, handle: &AppHandle<R>) -> Result<PathBuf, ClientIdError> {
+        let file = match &self.instance {
+            Some(name) => format!("tauri-rudderstack-{name}.json"),
+            None => "tauri-rudderstack.json".to_string(),
+        };
+        let path = handle.path().app_config_dir()?.join(file);
         Ok(path)
     }
+
+    /// The marker is kept in the app data dir, apart from the config,
+    /// so restoring or corrupting the config file does not affect it.
+    fn marker_path<R: Runtime>(&self, handle: &AppHandle<R>) -> Result<PathBuf, ClientIdError> {
+        let file = match &self.instance {
+            Some(name) => format!("tauri-rudderstack-{name}.id"),
+            None => "tauri-rudderstack.id".to_string(),
+        };
+        let path = handle.path().app_data_dir()?.join(file);
+        Ok(path)
+    }
+
+    /// The crash report that is not delivered yet, in the app data dir.
+    pub(crate) fn crash_report_path<R: Runtime>(
+        &self,
+        handle: &AppHandle<R>,
+    ) -> Result<PathBuf, ClientIdError> {
+        let file = match &self.instance {
+            Some(name) => format!("tauri-rudderstack-{name}.crash.json"),
+            None => "tauri-rudderstack.crash.json".to_string(),
+        };
+        let path = handle.path().app_data_dir()?.join(file);
+        Ok(path)
+    }
+
+    /// Read a file, decrypting it if it is encrypted.
+    /// Plain text files are still read, they are encrypted on the next save.
+    pub(crate) fn read(&self, path: &Path) -> Result<Vec<u8>, ClientIdError> {
+        let bytes = std::fs::read(path)?;
+        #[cfg(feature = "encryption")]
+        if crate::encryption::is_encrypted(&bytes) {
+            let key_provider = self
+                .key_provider
+                .as_deref()
+                .ok_or(crate::encryption::EncryptionError::MissingKey)?;
+            return Ok(crate::encryption::decrypt(key_provider, &bytes)?);
+        }
+        Ok(bytes)
+    }
+
+    /// Write a file, encrypting it if a key provider is configured.
+    pub(crate) fn write(&self, path: &Path, bytes: Vec<u8>) -> Result<(), ClientIdError> {
+        #[cfg(feature = "encryption")]
+        let bytes = match &self.key_provider {
+            Some(key_provider) => crate::encryption::encrypt(key_provider.as_ref(), &bytes)?,
+            None => bytes,
+        };
+        Ok(std::fs::write(path, bytes)?)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -99,4 +476,59 @@ pub enum ClientIdError {
     AppConfigDir(#[from] tauri::Error),
     #[error("failed to serialize config")]
     Serialize(#[from] serde_json::Error),
+    #[error("failed to delete config {0}")]
+    Delete(std::io::Error),
+    #[error("no config is stored")]
+    NotStored,
+    #[error("config store error: {0}")]
+    Store(StoreError),
+    #[cfg(feature = "keyring")]
+    #[error("failed to access the keyring: {0}")]
+    Keyring(#[from] keyring::Error),
+    #[cfg(feature = "encryption")]
+    #[error("failed to encrypt or decrypt config: {0}")]
+    Encryption(#[from] crate::encryption::EncryptionError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The config as it is stored and loaded again.
+    fn reload(config: &Config) -> Config {
+        serde_json::from_value(serde_json::to_value(config).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn does_not_reuse_sequence_numbers_after_a_crash() {
+        let mut config = Config::new("anonymous-id".to_string());
+        config.reserve_sequence();
+        let mut saved = reload(&config);
+        let mut last = 0;
+        for _ in 0..250 {
+            let (sequence, reserved) = config.next_sequence();
+            assert_eq!(sequence, last + 1);
+            last = sequence;
+            if reserved {
+                saved = reload(&config);
+            }
+        }
+
+        let mut restarted = saved;
+        restarted.reserve_sequence();
+        assert!(restarted.next_sequence().0 > last);
+    }
+
+    #[test]
+    fn continues_the_sequence_after_an_exit() {
+        let mut config = Config::new("anonymous-id".to_string());
+        config.reserve_sequence();
+        assert_eq!(config.next_sequence().0, 1);
+        assert_eq!(config.next_sequence().0, 2);
+        config.release_sequence();
+
+        let mut restarted = reload(&config);
+        restarted.reserve_sequence();
+        assert_eq!(restarted.next_sequence().0, 3);
+    }
 }
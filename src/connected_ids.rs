@@ -0,0 +1,188 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::memory::{BudgetShare, MemoryBudget};
+
+/// The default number of user IDs that are remembered.
+pub(crate) const DEFAULT_CAPACITY: usize = 100;
+
+/// The persisted form of [ConnectedIds]. \
+/// Older versions stored an unordered map, newer versions store the pairs oldest first,
+/// so trimming on load drops the least recently used IDs.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum Persisted {
+    Map(HashMap<String, String>),
+    List(Vec<(String, String)>),
+}
+
+/// An LRU of user IDs and the anonymous ID they were first connected to,
+/// used to send an identify event only the first time a user ID is seen.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(from = "Persisted", into = "Persisted")]
+pub(crate) struct ConnectedIds {
+    capacity: usize,
+    /// User IDs, least recently used first.
+    order: VecDeque<String>,
+    ids: HashMap<String, String>,
+    budget: BudgetShare,
+}
+
+impl Default for ConnectedIds {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            order: VecDeque::new(),
+            ids: HashMap::new(),
+            budget: BudgetShare::default(),
+        }
+    }
+}
+
+impl ConnectedIds {
+    /// Set the capacity, evicting the least recently used IDs if there are too many.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        self.trim();
+    }
+
+    /// Account the IDs in the memory budget, evicting the least recently used IDs that don't fit.
+    pub fn set_budget(&mut self, budget: Arc<MemoryBudget>) {
+        let order = std::mem::take(&mut self.order);
+        let mut ids = std::mem::take(&mut self.ids);
+        self.budget = BudgetShare::new(budget);
+        for user_id in order {
+            if let Some(anonymous_id) = ids.remove(&user_id) {
+                self.connect(user_id, &anonymous_id);
+            }
+        }
+    }
+
+    /// Connect the user ID to the anonymous ID, evicting the least recently used ID if full
+    /// or the memory budget is exceeded. \
+    /// Returns true if the user ID was already connected.
+    pub fn connect(&mut self, user_id: String, anonymous_id: &str) -> bool {
+        if self.ids.contains_key(&user_id) {
+            if let Some(index) = self.order.iter().position(|id| id == &user_id) {
+                self.order.remove(index);
+            }
+            self.order.push_back(user_id);
+            return true;
+        }
+        while !self.budget.try_reserve(pair_size(&user_id, anonymous_id)) {
+            if !self.evict() {
+                tracing::debug!("memory budget exceeded, not remembering the user ID");
+                return false;
+            }
+        }
+        self.ids.insert(user_id.clone(), anonymous_id.to_string());
+        self.order.push_back(user_id);
+        self.trim();
+        false
+    }
+
+    fn trim(&mut self) {
+        while self.order.len() > self.capacity {
+            self.evict();
+        }
+    }
+
+    /// Evict the least recently used ID, returns false if there was nothing to evict.
+    fn evict(&mut self) -> bool {
+        let Some(oldest) = self.order.pop_front() else {
+            return false;
+        };
+        if let Some(anonymous_id) = self.ids.remove(&oldest) {
+            self.budget.release(pair_size(&oldest, &anonymous_id));
+        }
+        true
+    }
+}
+
+/// The memory used by a connected ID, the user ID is kept in the order and the map.
+fn pair_size(user_id: &str, anonymous_id: &str) -> usize {
+    3 * std::mem::size_of::<String>() + 2 * user_id.len() + anonymous_id.len()
+}
+
+impl From<Persisted> for ConnectedIds {
+    fn from(persisted: Persisted) -> Self {
+        let pairs = match persisted {
+            Persisted::Map(ids) => ids.into_iter().collect(),
+            Persisted::List(pairs) => pairs,
+        };
+        // keep everything until the configured capacity is applied with `set_capacity`
+        let mut ids = Self {
+            capacity: usize::MAX,
+            ..Self::default()
+        };
+        for (user_id, anonymous_id) in pairs {
+            ids.connect(user_id, &anonymous_id);
+        }
+        ids
+    }
+}
+
+impl From<ConnectedIds> for Persisted {
+    fn from(mut ids: ConnectedIds) -> Self {
+        Persisted::List(
+            ids.order
+                .drain(..)
+                .filter_map(|user_id| {
+                    let anonymous_id = ids.ids.remove(&user_id)?;
+                    Some((user_id, anonymous_id))
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_ids(ids: &ConnectedIds) -> Vec<&str> {
+        ids.order.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_id() {
+        let mut ids = ConnectedIds::default();
+        ids.set_capacity(2);
+        assert!(!ids.connect("a".to_string(), "anon"));
+        assert!(!ids.connect("b".to_string(), "anon"));
+        assert!(ids.connect("a".to_string(), "anon"));
+        assert!(!ids.connect("c".to_string(), "anon"));
+        assert_eq!(user_ids(&ids), ["a", "c"]);
+        assert!(!ids.ids.contains_key("b"));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_id_over_the_memory_budget() {
+        let mut ids = ConnectedIds::default();
+        ids.set_budget(Arc::new(MemoryBudget::new(2 * pair_size("a", "anon"))));
+        assert!(!ids.connect("a".to_string(), "anon"));
+        assert!(!ids.connect("b".to_string(), "anon"));
+        assert!(!ids.connect("c".to_string(), "anon"));
+        assert_eq!(user_ids(&ids), ["b", "c"]);
+    }
+
+    #[test]
+    fn trims_the_oldest_ids_on_load() {
+        let json = r#"[["a", "anon"], ["b", "anon"], ["c", "anon"]]"#;
+        let mut ids: ConnectedIds = serde_json::from_str(json).unwrap();
+        assert_eq!(user_ids(&ids), ["a", "b", "c"]);
+        ids.set_capacity(2);
+        assert_eq!(user_ids(&ids), ["b", "c"]);
+        assert_eq!(
+            serde_json::to_value(&ids).unwrap(),
+            serde_json::json!([["b", "anon"], ["c", "anon"]])
+        );
+
+        let legacy: ConnectedIds = serde_json::from_str(r#"{ "a": "anon" }"#).unwrap();
+        assert_eq!(user_ids(&legacy), ["a"]);
+    }
+}
@@ -0,0 +1,273 @@
+//! The consent flow state machine.
+//!
+//! `NotAsked -> Prompted -> Granted / Denied`, and back to `Prompted` once the
+//! [ConsentPolicy] asks for a re-prompt, or to `NotAsked` when the policy version is bumped.
+
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The consent status of the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum ConsentStatus {
+    /// The user has never been asked for consent.
+    #[default]
+    NotAsked,
+    /// The user is being asked for consent.
+    Prompted,
+    /// The user granted consent.
+    Granted,
+    /// The user denied consent.
+    Denied,
+}
+
+/// The persisted consent state of the user.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsentState {
+    /// The current consent status.
+    pub status: ConsentStatus,
+    /// The policy version the user answered.
+    pub policy_version: Option<u32>,
+    /// When the status last changed.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// The consent of the user per category, e.g. `marketing`.
+    #[serde(default)]
+    pub categories: BTreeMap<String, bool>,
+}
+
+/// The consent policy of the app, used to decide when the user needs to be prompted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsentPolicy {
+    version: u32,
+    reprompt_after: Option<Duration>,
+    essential_events: HashSet<String>,
+}
+
+impl ConsentPolicy {
+    /// Create a new consent policy with the current policy version. \
+    /// Bumping the version will re-prompt users that answered an older version.
+    pub fn new(version: u32) -> Self {
+        Self {
+            version,
+            reprompt_after: None,
+            essential_events: HashSet::new(),
+        }
+    }
+
+    /// Re-prompt the user this many days after they answered.
+    pub fn reprompt_after_days(mut self, days: i64) -> Self {
+        self.reprompt_after = Some(Duration::days(days));
+        self
+    }
+
+    /// Track event names that are essential for the app to function.
+    /// These are sent even when the user has not granted consent.
+    pub fn essential_events<I, S>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.essential_events
+            .extend(events.into_iter().map(Into::into));
+        self
+    }
+
+    /// The current policy version.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns true if the track event is essential and does not require consent.
+    pub fn is_essential(&self, event: &str) -> bool {
+        self.essential_events.contains(event)
+    }
+}
+
+/// An invalid transition of the consent state machine.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid consent transition from {from:?} to {to:?}")]
+pub struct ConsentError {
+    pub from: ConsentStatus,
+    pub to: ConsentStatus,
+}
+
+impl ConsentState {
+    /// Returns true if the user should be prompted for consent.
+    pub fn should_prompt(&self, policy: &ConsentPolicy, now: DateTime<Utc>) -> bool {
+        match self.status {
+            ConsentStatus::NotAsked => true,
+            ConsentStatus::Prompted => false,
+            ConsentStatus::Granted | ConsentStatus::Denied => {
+                if self.policy_version != Some(policy.version) {
+                    return true;
+                }
+                match (policy.reprompt_after, self.updated_at) {
+                    (Some(after), Some(updated_at)) => now - updated_at >= after,
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    /// Returns true if events may be sent under this policy.
+    pub fn is_granted(&self, policy: &ConsentPolicy) -> bool {
+        self.status == ConsentStatus::Granted && self.policy_version == Some(policy.version)
+    }
+
+    /// Returns true if the user granted consent for this category.
+    pub fn is_category_granted(&self, category: &str) -> bool {
+        self.status == ConsentStatus::Granted && self.categories.get(category) == Some(&true)
+    }
+
+    /// Record the consent of the user for a category. \
+    /// This does not change [ConsentState::updated_at], so the re-prompt is not postponed.
+    pub fn set_category(&mut self, category: impl Into<String>, granted: bool) {
+        self.categories.insert(category.into(), granted);
+    }
+
+    /// If the user answered an older policy version, move back to [ConsentStatus::NotAsked]
+    /// so they are re-prompted. The answered version is kept in [ConsentState::policy_version].
+    ///
+    /// Returns true if the state was reset.
+    pub fn expire_outdated(&mut self, policy: &ConsentPolicy, now: DateTime<Utc>) -> bool {
+        let outdated = matches!(self.status, ConsentStatus::Granted | ConsentStatus::Denied)
+            && self
+                .policy_version
+                .is_none_or(|version| version < policy.version);
+        if outdated {
+            self.transition(ConsentStatus::NotAsked, None, now);
+        }
+        outdated
+    }
+
+    /// Move to [ConsentStatus::Prompted].
+    /// Only allowed from `NotAsked`, or when [ConsentState::should_prompt] is true.
+    pub fn prompt(
+        &mut self,
+        policy: &ConsentPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<(), ConsentError> {
+        if !self.should_prompt(policy, now) {
+            return Err(ConsentError {
+                from: self.status,
+                to: ConsentStatus::Prompted,
+            });
+        }
+        self.transition(ConsentStatus::Prompted, None, now);
+        Ok(())
+    }
+
+    /// Record the answer of the user. Only allowed while [ConsentStatus::Prompted].
+    pub fn answer(
+        &mut self,
+        granted: bool,
+        policy: &ConsentPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<(), ConsentError> {
+        let to = if granted {
+            ConsentStatus::Granted
+        } else {
+            ConsentStatus::Denied
+        };
+        if self.status != ConsentStatus::Prompted {
+            return Err(ConsentError {
+                from: self.status,
+                to,
+            });
+        }
+        self.transition(to, Some(policy.version), now);
+        Ok(())
+    }
+
+    fn transition(
+        &mut self,
+        status: ConsentStatus,
+        policy_version: Option<u32>,
+        now: DateTime<Utc>,
+    ) {
+        self.status = status;
+        if policy_version.is_some() {
+            self.policy_version = policy_version;
+        }
+        self.updated_at = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn consent_flow() {
+        let policy = ConsentPolicy::new(1);
+        let now = Utc::now();
+        let mut state = ConsentState::default();
+
+        assert!(state.should_prompt(&policy, now));
+        assert!(state.answer(true, &policy, now).is_err());
+
+        state.prompt(&policy, now).unwrap();
+        assert!(!state.should_prompt(&policy, now));
+        assert!(!state.is_granted(&policy));
+
+        state.answer(true, &policy, now).unwrap();
+        assert!(state.is_granted(&policy));
+        assert!(!state.should_prompt(&policy, now));
+        assert!(state.prompt(&policy, now).is_err());
+    }
+
+    #[test]
+    fn reprompt_on_policy_bump() {
+        let now = Utc::now();
+        let mut state = ConsentState::default();
+        state.prompt(&ConsentPolicy::new(1), now).unwrap();
+        state.answer(false, &ConsentPolicy::new(1), now).unwrap();
+
+        let policy = ConsentPolicy::new(2);
+        assert!(state.should_prompt(&policy, now));
+        assert!(!state.is_granted(&policy));
+        state.prompt(&policy, now).unwrap();
+        assert_eq!(state.status, ConsentStatus::Prompted);
+    }
+
+    #[test]
+    fn expire_outdated_policy() {
+        let now = Utc::now();
+        let mut state = ConsentState::default();
+        state.prompt(&ConsentPolicy::new(1), now).unwrap();
+        state.answer(true, &ConsentPolicy::new(1), now).unwrap();
+
+        assert!(!state.expire_outdated(&ConsentPolicy::new(1), now));
+        assert!(state.expire_outdated(&ConsentPolicy::new(2), now));
+        assert_eq!(state.status, ConsentStatus::NotAsked);
+        assert_eq!(state.policy_version, Some(1));
+    }
+
+    #[test]
+    fn reprompt_after_days() {
+        let policy = ConsentPolicy::new(1).reprompt_after_days(30);
+        let now = Utc::now();
+        let mut state = ConsentState::default();
+        state.prompt(&policy, now).unwrap();
+        state.answer(true, &policy, now).unwrap();
+
+        assert!(!state.should_prompt(&policy, now + Duration::days(29)));
+        assert!(state.should_prompt(&policy, now + Duration::days(30)));
+    }
+
+    #[test]
+    fn categories_do_not_postpone_the_reprompt() {
+        let policy = ConsentPolicy::new(1).reprompt_after_days(30);
+        let now = Utc::now();
+        let mut state = ConsentState::default();
+        state.prompt(&policy, now).unwrap();
+        state.answer(true, &policy, now).unwrap();
+
+        state.set_category("marketing", false);
+        assert!(state.should_prompt(&policy, now + Duration::days(30)));
+        assert!(!state.is_category_granted("marketing"));
+    }
+}
@@ -0,0 +1,165 @@
+//! How the context of an event is merged into the global context.
+
+use serde_json::{Map, Value};
+
+/// How deep the context of an event is merged into the global context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeDepth {
+    /// Nested objects are merged key by key, the default.
+    #[default]
+    Deep,
+    /// Only the top level keys are merged, a nested object replaces the whole object.
+    Shallow,
+}
+
+/// Which value is kept when the context of an event and the global context have the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePrecedence {
+    /// The value of the event replaces the global value, the default.
+    #[default]
+    EventWins,
+    /// The global value is kept, events can only add keys.
+    GlobalWins,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ContextMerge {
+    pub depth: MergeDepth,
+    pub precedence: MergePrecedence,
+}
+
+impl ContextMerge {
+    /// Merge the context of the event into the global context.
+    pub fn apply(&self, global: &mut Value, event: &Value) {
+        let event_wins = self.precedence == MergePrecedence::EventWins;
+        match self.depth {
+            MergeDepth::Deep => merge_deep(global, event, event_wins),
+            MergeDepth::Shallow => merge_shallow(global, event, event_wins),
+        }
+    }
+
+    /// The context of a message: the per-message `overlay` on top of the `shared` global context,
+    /// with the context of the event merged in. \
+    /// Only the keys the event merges into are taken from the shared context before merging,
+    /// the shared context itself is left as is.
+    pub fn layer(
+        &self,
+        shared: &Value,
+        overlay: Map<String, Value>,
+        event: Option<&Value>,
+    ) -> Value {
+        let empty = Map::new();
+        let shared = shared.as_object().unwrap_or(&empty);
+        let mut context = overlay;
+        if let Some(Value::Object(event)) = event {
+            for key in event.keys() {
+                if let (false, Some(value)) = (context.contains_key(key), shared.get(key)) {
+                    context.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        let mut context = Value::Object(context);
+        if let Some(event) = event {
+            self.apply(&mut context, event);
+        }
+        if let Value::Object(context) = &mut context {
+            for (key, value) in shared {
+                if !context.contains_key(key) {
+                    context.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        context
+    }
+}
+
+fn merge_deep(a: &mut Value, b: &Value, b_wins: bool) {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (k, v) in b {
+                match a.get_mut(k) {
+                    Some(a) => merge_deep(a, v, b_wins),
+                    None => {
+                        a.insert(k.clone(), v.clone());
+                    }
+                }
+            }
+        }
+        (a, b) => {
+            if b_wins {
+                *a = b.clone()
+            }
+        }
+    }
+}
+
+fn merge_shallow(a: &mut Value, b: &Value, b_wins: bool) {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (k, v) in b {
+                if b_wins || !a.contains_key(k) {
+                    a.insert(k.clone(), v.clone());
+                }
+            }
+        }
+        (a, b) => {
+            if b_wins {
+                *a = b.clone()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merge_strategies() {
+        let global = json!({ "app": { "name": "app", "version": "1.0" }, "locale": "en" });
+        let event = json!({ "app": { "version": "2.0" }, "page": "home" });
+
+        let merged = |depth, precedence| {
+            let mut global = global.clone();
+            ContextMerge { depth, precedence }.apply(&mut global, &event);
+            global
+        };
+
+        assert_eq!(
+            merged(MergeDepth::Deep, MergePrecedence::EventWins),
+            json!({ "app": { "name": "app", "version": "2.0" }, "locale": "en", "page": "home" })
+        );
+        assert_eq!(
+            merged(MergeDepth::Deep, MergePrecedence::GlobalWins),
+            json!({ "app": { "name": "app", "version": "1.0" }, "locale": "en", "page": "home" })
+        );
+        assert_eq!(
+            merged(MergeDepth::Shallow, MergePrecedence::EventWins),
+            json!({ "app": { "version": "2.0" }, "locale": "en", "page": "home" })
+        );
+        assert_eq!(
+            merged(MergeDepth::Shallow, MergePrecedence::GlobalWins),
+            json!({ "app": { "name": "app", "version": "1.0" }, "locale": "en", "page": "home" })
+        );
+    }
+
+    #[test]
+    fn layers_the_message_context() {
+        let shared = json!({ "app": { "name": "app", "version": "1.0" }, "sequence": 0 });
+        let mut overlay = Map::new();
+        overlay.insert("sequence".to_string(), json!(7));
+        let event = json!({ "app": { "version": "2.0" } });
+
+        let context = ContextMerge::default().layer(&shared, overlay, Some(&event));
+
+        assert_eq!(
+            context,
+            json!({ "app": { "name": "app", "version": "2.0" }, "sequence": 7 })
+        );
+        assert_eq!(
+            shared,
+            json!({ "app": { "name": "app", "version": "1.0" }, "sequence": 0 })
+        );
+    }
+}
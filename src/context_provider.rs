@@ -0,0 +1,25 @@
+/// Provides context values evaluated for every event, e.g. the focused window, memory usage or feature flags.
+///
+/// Providers run in the order they are added and get the per-event keys, e.g. `sequence`,
+/// not the global context, which is shared by the events. Their values replace the global
+/// values of the same key, the context of the event is merged in after them.
+/// Any `Fn(&mut serde_json::Map<String, Value>)` can be used as a provider.
+///
+/// ```ignore
+/// let provider = |context: &mut serde_json::Map<String, Value>| {
+///     context.insert("feature_flags".to_string(), json!(flags::active()));
+/// };
+/// ```
+pub trait ContextProvider: Send + Sync + 'static {
+    /// Add values to the context of the event.
+    fn provide(&self, context: &mut serde_json::Map<String, serde_json::Value>);
+}
+
+impl<F> ContextProvider for F
+where
+    F: Fn(&mut serde_json::Map<String, serde_json::Value>) + Send + Sync + 'static,
+{
+    fn provide(&self, context: &mut serde_json::Map<String, serde_json::Value>) {
+        self(context)
+    }
+}
@@ -0,0 +1,150 @@
+//! Crash reporting, see [RudderStackBuilder::report_crashes](crate::RudderStackBuilder::report_crashes).
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
+
+use chrono::Utc;
+use serde_json::json;
+use tauri::Runtime;
+use tracing::warn;
+
+use crate::{
+    config::ClientIdError,
+    event_log::LoggedEvent,
+    instance::Instance,
+    priority::Priority,
+    transport::DeliveryError,
+    types::{self, Message, Track},
+};
+
+/// How long the panic hook waits for the crash report to be delivered.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Set while a crash report is built and delivered, so a panic while doing so,
+/// e.g. on a lock poisoned by the panicking thread, is not reported again.
+static REPORTING: AtomicBool = AtomicBool::new(false);
+
+/// Clears [REPORTING] when the report is done, also if building it panicked.
+struct Reporting;
+
+impl Drop for Reporting {
+    fn drop(&mut self) {
+        REPORTING.store(false, Ordering::Release);
+    }
+}
+
+/// Send an `Application Crashed` event when the app panics, before the previous panic hook runs.
+pub(crate) fn install<R: Runtime>(instance: Instance<R>) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned());
+        let location = info.location().map(|location| location.to_string());
+        report(&instance, message, location);
+        previous(info);
+    }));
+}
+
+/// The report is built and delivered on a new thread, the panicking thread may hold any lock
+/// the report takes, e.g. of the config or the context, and may be a worker of the async runtime.
+/// The panicking thread waits at most [DELIVERY_TIMEOUT] for it.
+fn report<R: Runtime>(instance: &Instance<R>, message: Option<String>, location: Option<String>) {
+    if REPORTING.swap(true, Ordering::AcqRel) {
+        warn!("A crash report is already being sent, skipping this one");
+        return;
+    }
+    let (sender, receiver) = mpsc::channel();
+    let reporting = instance.clone();
+    std::thread::spawn(move || {
+        let _reporting = Reporting;
+        let _ = sender.send(send_report(&reporting, message, location));
+    });
+    match receiver.recv_timeout(DELIVERY_TIMEOUT) {
+        Ok(Some(Err(err))) => warn!("Failed to send the crash report: {:?}", err),
+        Ok(_) => {}
+        Err(_) => warn!("Timed out sending the crash report"),
+    }
+}
+
+/// The report goes through the same rules as other events and is written to disk first,
+/// then deleted once it is delivered, so a crash while offline is reported on the next launch
+/// by [send_pending]. \
+/// Returns the result of the delivery, `None` if the report was dropped.
+fn send_report<R: Runtime>(
+    instance: &Instance<R>,
+    message: Option<String>,
+    location: Option<String>,
+) -> Option<Result<(), DeliveryError>> {
+    let rudder = instance.rudder();
+    let mut track = Message::Track(Track {
+        event: "Application Crashed".to_string(),
+        properties: Some(json!({
+            "message": message,
+            "location": location,
+            "app_version": instance.handle.package_info().version.to_string(),
+        })),
+        ..Track::default()
+    });
+    rudder.should_send(&mut track).ok()?;
+    let mut msg = rudder.assemble(types::convert_message(track))?;
+    rudder.number(&mut msg);
+    let message_id = uuid::Uuid::new_v4().to_string();
+
+    let path = rudder.crash_report_path(&instance.handle);
+    if let Some(path) = &path {
+        let event = LoggedEvent {
+            logged_at: Utc::now(),
+            message_id: Some(message_id.clone()),
+            message: msg.clone().into(),
+        };
+        let written = serde_json::to_vec(&event)
+            .map_err(ClientIdError::from)
+            .and_then(|bytes| rudder.write_crash_report(path, bytes));
+        if let Err(err) = written {
+            warn!("Failed to write the crash report: {:?}", err);
+        }
+    }
+
+    let result = rudder.deliver_blocking(&msg, &message_id);
+    if let (Ok(()), Some(path)) = (&result, path) {
+        let _ = fs::remove_file(path);
+    }
+    Some(result)
+}
+
+/// Send the crash report that could not be delivered in the last run,
+/// unless the user revoked consent since.
+pub(crate) fn send_pending<R: Runtime>(instance: &Instance<R>) {
+    let rudder = instance.rudder();
+    let Some(path) = rudder.crash_report_path(&instance.handle) else {
+        return;
+    };
+    let event = match rudder.read_crash_report(&path) {
+        // there is no crash report
+        Err(ClientIdError::Save(_)) => return,
+        bytes => bytes.and_then(|bytes| {
+            serde_json::from_slice::<LoggedEvent>(&bytes).map_err(ClientIdError::from)
+        }),
+    };
+    if let Err(err) = fs::remove_file(&path) {
+        warn!("Failed to delete the crash report: {:?}", err);
+    }
+    match event {
+        Ok(event) => {
+            let message_id = event
+                .message_id
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            rudder.resend(event.message.into(), message_id, Priority::Critical);
+        }
+        Err(err) => warn!("Failed to read the crash report: {:?}", err),
+    }
+}
@@ -0,0 +1,36 @@
+//! Access to and deletion of the analytics data stored on this device,
+//! to support data subject access and deletion requests.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::ClientIdError;
+
+/// All analytics data stored on this device.
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsData {
+    /// The anonymous ID of the user.
+    pub anonymous_id: String,
+    /// The user ID of the user, if identified.
+    pub user_id: Option<String>,
+    /// The traits the plugin adds to identify events.
+    pub traits: Value,
+    /// The context added to every event.
+    pub context: Value,
+    /// The stored config, including the consent state and the audit log.
+    pub config: Value,
+    /// The events recorded in the event log and the local store, oldest first.
+    pub events: Vec<Value>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WipeError {
+    #[error("failed to delete the config: {0}")]
+    Config(#[from] ClientIdError),
+    #[error("failed to delete the event log: {0}")]
+    EventLog(#[from] std::io::Error),
+    #[cfg(feature = "local-store")]
+    #[error("failed to clear the local store: {0}")]
+    LocalStore(#[from] crate::local_store::LocalStoreError),
+}
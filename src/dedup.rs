@@ -0,0 +1,85 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::memory::{BudgetShare, MemoryBudget};
+
+/// The number of sent message IDs that are remembered.
+const CAPACITY: usize = 1000;
+
+/// A small LRU of the IDs of recently sent messages, used to prevent duplicate delivery.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "VecDeque<String>", into = "VecDeque<String>")]
+pub(crate) struct SentMessageIds {
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+    budget: BudgetShare,
+}
+
+impl SentMessageIds {
+    /// Returns true if a message with this ID was sent recently.
+    pub fn contains(&self, message_id: &str) -> bool {
+        self.ids.contains(message_id)
+    }
+
+    /// Remember that a message with this ID was sent,
+    /// evicting the oldest IDs if full or the memory budget is exceeded.
+    pub fn insert(&mut self, message_id: String) {
+        if self.ids.contains(&message_id) {
+            return;
+        }
+        while !self.budget.try_reserve(id_size(&message_id)) {
+            if !self.evict() {
+                tracing::debug!("memory budget exceeded, not remembering the sent message ID");
+                return;
+            }
+        }
+        self.ids.insert(message_id.clone());
+        self.order.push_back(message_id);
+        while self.order.len() > CAPACITY {
+            self.evict();
+        }
+    }
+
+    /// Account the IDs in the memory budget, evicting the oldest IDs that don't fit.
+    pub fn set_budget(&mut self, budget: Arc<MemoryBudget>) {
+        let order = std::mem::take(&mut self.order);
+        self.ids.clear();
+        self.budget = BudgetShare::new(budget);
+        order.into_iter().for_each(|id| self.insert(id));
+    }
+
+    /// Evict the oldest ID, returns false if there was nothing to evict.
+    fn evict(&mut self) -> bool {
+        match self.order.pop_front() {
+            Some(oldest) => {
+                self.ids.remove(&oldest);
+                self.budget.release(id_size(&oldest));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The memory used by an ID, which is kept in the order and the set.
+fn id_size(message_id: &str) -> usize {
+    2 * (std::mem::size_of::<String>() + message_id.len())
+}
+
+impl From<VecDeque<String>> for SentMessageIds {
+    fn from(order: VecDeque<String>) -> Self {
+        let mut ids = Self::default();
+        order.into_iter().for_each(|id| ids.insert(id));
+        ids
+    }
+}
+
+impl From<SentMessageIds> for VecDeque<String> {
+    fn from(ids: SentMessageIds) -> Self {
+        ids.order
+    }
+}
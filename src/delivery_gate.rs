@@ -0,0 +1,77 @@
+//! Holds deliveries while they are paused, e.g. on a metered connection or while offline.
+
+use tokio::sync::watch;
+
+/// Why deliveries are paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PauseReason {
+    /// The connection is metered, see [MeteredPolicy](crate::MeteredPolicy).
+    Metered,
+    /// The data plane can't be connected to, see [offline](crate::offline).
+    Offline,
+    /// Deliveries kept failing, see [CircuitBreaker](crate::circuit_breaker::CircuitBreaker).
+    CircuitOpen,
+}
+
+impl PauseReason {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct GateState {
+    /// The active pause reasons as bits.
+    paused: u8,
+    /// Incremented to let the waiting deliveries through while paused.
+    releases: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct DeliveryGate {
+    state: watch::Sender<GateState>,
+}
+
+impl Default for DeliveryGate {
+    fn default() -> Self {
+        Self {
+            state: watch::Sender::new(GateState::default()),
+        }
+    }
+}
+
+impl DeliveryGate {
+    /// Returns false if the deliveries were already paused for this reason.
+    pub fn pause(&self, reason: PauseReason) -> bool {
+        self.state.send_if_modified(|state| {
+            let paused = state.paused | reason.bit();
+            std::mem::replace(&mut state.paused, paused) != paused
+        })
+    }
+
+    pub fn resume(&self, reason: PauseReason) {
+        self.state.send_if_modified(|state| {
+            let paused = state.paused & !reason.bit();
+            std::mem::replace(&mut state.paused, paused) != paused
+        });
+    }
+
+    pub fn is_paused(&self, reason: PauseReason) -> bool {
+        self.state.borrow().paused & reason.bit() != 0
+    }
+
+    /// Let the deliveries that are currently waiting through, even if paused.
+    pub fn release(&self) {
+        self.state.send_modify(|state| state.releases += 1);
+    }
+
+    /// Wait until deliveries are not paused, or the waiting deliveries are released.
+    pub async fn wait(&self) {
+        let mut state = self.state.subscribe();
+        let releases = state.borrow().releases;
+        // the sender lives as long as the gate
+        let _ = state
+            .wait_for(|state| state.paused == 0 || state.releases != releases)
+            .await;
+    }
+}
@@ -0,0 +1,58 @@
+//! Detection of "do not track" signals set by the user or the OS.
+
+/// The environment variable checked by default, see <https://consoledonottrack.com>.
+pub(crate) const DEFAULT_ENV_VAR: &str = "DO_NOT_TRACK";
+
+/// Returns true if any of the environment variables is set to a truthy value,
+/// or the OS telemetry is turned off.
+pub(crate) fn is_requested(env_vars: &[String]) -> bool {
+    if let Some(var) = env_vars
+        .iter()
+        .find(|var| is_truthy(std::env::var(var).ok()))
+    {
+        tracing::info!(%var, "do not track is set, analytics are disabled");
+        return true;
+    }
+    if os_telemetry_disabled() {
+        tracing::info!("OS telemetry is turned off, analytics are disabled");
+        return true;
+    }
+    false
+}
+
+pub(crate) fn is_truthy(value: Option<String>) -> bool {
+    value.is_some_and(|value| {
+        let value = value.trim();
+        !value.is_empty() && value != "0" && !value.eq_ignore_ascii_case("false")
+    })
+}
+
+/// The "Allow Diagnostic Data" group policy, set to `0` to turn off telemetry.
+#[cfg(windows)]
+fn os_telemetry_disabled() -> bool {
+    use std::os::windows::process::CommandExt as _;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Policies\Microsoft\Windows\DataCollection",
+            "/v",
+            "AllowTelemetry",
+        ])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .is_some_and(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|line| line.contains("AllowTelemetry") && line.trim_end().ends_with("0x0"))
+        })
+}
+
+/// There is no OS-wide telemetry setting on macOS and Linux, only the environment variables are checked.
+#[cfg(not(windows))]
+fn os_telemetry_disabled() -> bool {
+    false
+}
@@ -0,0 +1,155 @@
+//! Encryption of the files persisted by the plugin with a key supplied by the app.
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+
+/// The prefix of encrypted files, followed by the nonce and the ciphertext.
+const MAGIC: &[u8] = b"RSENC1";
+const NONCE_LEN: usize = 12;
+
+/// Supplies the 256-bit key used to encrypt the config of the plugin,
+/// e.g. a key stored in the OS keychain by the app. \
+/// The key must stay the same across runs, otherwise the config can't be read
+/// and a new anonymous ID is generated.
+///
+/// Any `Fn() -> [u8; 32]` can be used as a key provider.
+pub trait KeyProvider: Send + Sync + 'static {
+    fn key(&self) -> [u8; 32];
+}
+
+impl<F> KeyProvider for F
+where
+    F: Fn() -> [u8; 32] + Send + Sync + 'static,
+{
+    fn key(&self) -> [u8; 32] {
+        self()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    #[error("the file is encrypted but no key provider is configured")]
+    MissingKey,
+    #[error("failed to encrypt the file")]
+    Encrypt,
+    #[error("failed to decrypt the file, the key may have changed")]
+    Decrypt,
+}
+
+/// Returns true if the bytes were written by [encrypt].
+pub(crate) fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// `MAGIC | nonce | ciphertext` with a random nonce.
+pub(crate) fn encrypt(
+    key_provider: &dyn KeyProvider,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, EncryptionError> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_provider.key()));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| EncryptionError::Encrypt)?;
+    let mut bytes = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&nonce);
+    bytes.extend_from_slice(&ciphertext);
+    Ok(bytes)
+}
+
+pub(crate) fn decrypt(
+    key_provider: &dyn KeyProvider,
+    bytes: &[u8],
+) -> Result<Vec<u8>, EncryptionError> {
+    let bytes = bytes
+        .strip_prefix(MAGIC)
+        .filter(|bytes| bytes.len() >= NONCE_LEN)
+        .ok_or(EncryptionError::Decrypt)?;
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_provider.key()));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| EncryptionError::Decrypt)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::{ClientIdError, ConfigFile};
+
+    const CONFIG: &[u8] = br#"{"anonymous_id":"anonymous-id"}"#;
+
+    fn key() -> [u8; 32] {
+        [7; 32]
+    }
+
+    fn other_key() -> [u8; 32] {
+        [8; 32]
+    }
+
+    #[test]
+    fn round_trips() {
+        let encrypted = encrypt(&key, CONFIG).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted
+            .windows(b"anonymous-id".len())
+            .any(|window| window == b"anonymous-id"));
+        assert_eq!(decrypt(&key, &encrypted).unwrap(), CONFIG);
+        // a random nonce is used for every write
+        assert_ne!(encrypt(&key, CONFIG).unwrap(), encrypted);
+    }
+
+    #[test]
+    fn rejects_the_wrong_key() {
+        let encrypted = encrypt(&key, CONFIG).unwrap();
+        assert!(matches!(
+            decrypt(&other_key, &encrypted),
+            Err(EncryptionError::Decrypt)
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_ciphertext() {
+        let mut encrypted = encrypt(&key, CONFIG).unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(matches!(
+            decrypt(&key, &encrypted),
+            Err(EncryptionError::Decrypt)
+        ));
+        let truncated = &encrypted[..MAGIC.len() + NONCE_LEN - 1];
+        assert!(matches!(
+            decrypt(&key, truncated),
+            Err(EncryptionError::Decrypt)
+        ));
+    }
+
+    #[test]
+    fn encrypts_plain_text_files_on_the_next_save() {
+        let path = std::env::temp_dir().join(format!(
+            "rudderstack-encryption-migration-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, CONFIG).unwrap();
+
+        let file = ConfigFile::new(None).with_key_provider(Arc::new(key));
+        assert_eq!(file.read(&path).unwrap(), CONFIG);
+        file.write(&path, CONFIG.to_vec()).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        assert!(is_encrypted(&written));
+        assert_eq!(file.read(&path).unwrap(), CONFIG);
+
+        // an encrypted file can't be read without the key
+        let result = ConfigFile::new(None).read(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(ClientIdError::Encryption(EncryptionError::MissingKey))
+        ));
+    }
+}
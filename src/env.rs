@@ -0,0 +1,32 @@
+//! Configuration from environment variables,
+//! see [RudderStackBuilder::from_env](crate::RudderStackBuilder::from_env).
+
+use crate::do_not_track;
+
+/// The URL of the data plane,
+/// read by [RudderStackBuilder::from_env](crate::RudderStackBuilder::from_env).
+pub const DATA_PLANE_ENV_VAR: &str = "RUDDERSTACK_DATA_PLANE";
+/// The write key, read by [RudderStackBuilder::from_env](crate::RudderStackBuilder::from_env).
+pub const WRITE_KEY_ENV_VAR: &str = "RUDDERSTACK_WRITE_KEY";
+/// Set to `1` or `true` to send events from debug builds,
+/// see [RudderStackBuilder::disable_in_debug](crate::RudderStackBuilder::disable_in_debug).
+pub const ENABLED_ENV_VAR: &str = "RUDDERSTACK_ENABLED";
+
+#[derive(Debug, thiserror::Error)]
+pub enum EnvError {
+    #[error("environment variable {0} is not set")]
+    Missing(&'static str),
+}
+
+/// The value of a required environment variable, empty values count as missing.
+pub(crate) fn var(name: &'static str) -> Result<String, EnvError> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .ok_or(EnvError::Missing(name))
+}
+
+/// Returns true if this is a debug build and sending was not enabled with [ENABLED_ENV_VAR].
+pub(crate) fn is_disabled_debug_build() -> bool {
+    cfg!(debug_assertions) && !do_not_track::is_truthy(std::env::var(ENABLED_ENV_VAR).ok())
+}
@@ -0,0 +1,98 @@
+//! Typed events defined once in Rust and exported with TypeScript helpers for the webview.
+
+use std::path::Path;
+
+use specta::{Language, NamedType, TypeMap};
+use specta_typescript::{ExportError, Typescript};
+
+use crate::{EventNameCase, EventNameNormalizer, TrackEvent};
+
+/// The catalog of your [TrackEvent]s, exported as TypeScript types with a `track` helper per event.
+///
+/// ```ignore
+/// #[derive(serde::Serialize, specta::Type)]
+/// struct SignIn {
+///     method: String,
+/// }
+///
+/// impl TrackEvent for SignIn {
+///     const NAME: &'static str = "Sign In";
+/// }
+///
+/// EventCatalog::new()
+///     .event::<SignIn>()
+///     .export("../src/analytics.ts")?;
+/// ```
+///
+/// Exports `trackSignIn({ method })` which sends the event with the `analytics` API
+/// of the guest bindings.
+/// Requires the `build-types` feature.
+#[derive(Default)]
+pub struct EventCatalog {
+    types: TypeMap,
+    /// The event names and the names of their property types.
+    events: Vec<(String, String)>,
+}
+
+impl EventCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an event, `E` is the type of its properties and [TrackEvent::NAME] its name.
+    pub fn event<E: TrackEvent + NamedType>(mut self) -> Self {
+        let definition = E::definition_named_data_type(&mut self.types);
+        self.events
+            .push((E::NAME.to_string(), definition.name().to_string()));
+        self.types.insert(E::sid(), definition);
+        self
+    }
+
+    /// Render the types of the events and their `track` helpers.
+    pub fn render(&self) -> Result<String, ExportError> {
+        let mut out = Typescript::default()
+            .header("import { analytics, TrackOptions } from \"tauri-plugin-rudderstack-api\";\n")
+            .export(self.types.clone())?;
+        for (event, properties) in &self.events {
+            out.push_str(&format!(
+                "\n/**\n * Track `{event}`.\n */\nexport const {helper} = \
+                 (properties: {properties}, options?: TrackOptions) =>\n    \
+                 analytics.track({event:?}, properties, options);\n",
+                helper = helper_name(event),
+            ));
+        }
+        Ok(out)
+    }
+
+    /// Write the rendered catalog to `path`.
+    pub fn export(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.render()?)?;
+        Ok(())
+    }
+}
+
+/// The name of the `track` helper of an event, e.g. `trackSignIn` for `Sign In`.
+fn helper_name(event: &str) -> String {
+    let name: String = EventNameNormalizer::new(EventNameCase::TitleCase)
+        .normalize(event)
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect();
+    format!("track{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_helpers_after_the_event() {
+        assert_eq!(helper_name("Sign In"), "trackSignIn");
+        assert_eq!(helper_name("app_opened"), "trackAppOpened");
+        assert_eq!(helper_name("checkout-started"), "trackCheckoutStarted");
+    }
+}
@@ -0,0 +1,439 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::rudderanalytics;
+
+/// The name of the event log file in the app data dir, without the extension.
+pub(crate) const EVENT_LOG_FILE: &str = "rudderstack-events";
+
+/// The on-disk encoding of the event log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventLogFormat {
+    /// One JSON object per line. Corrupted lines are skipped on read.
+    #[default]
+    Jsonl,
+    /// Length-prefixed MessagePack records with a CRC32 checksum per record,
+    /// for apps with very high event volumes. Corrupted records are skipped on read.
+    #[cfg(feature = "msgpack-log")]
+    MessagePack,
+}
+
+impl EventLogFormat {
+    /// The file extension of the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            #[cfg(feature = "msgpack-log")]
+            Self::MessagePack => "msgpack",
+        }
+    }
+
+    /// Guess the format of an event log from its file extension, defaulting to JSONL.
+    pub(crate) fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "msgpack-log")]
+            Some("msgpack") => Self::MessagePack,
+            _ => Self::Jsonl,
+        }
+    }
+
+    fn codec(self) -> &'static dyn LogCodec {
+        match self {
+            Self::Jsonl => &Jsonl,
+            #[cfg(feature = "msgpack-log")]
+            Self::MessagePack => &MessagePack,
+        }
+    }
+
+    /// Encode a single record of an event log.
+    pub(crate) fn encode(self, event: &LoggedEvent) -> io::Result<Vec<u8>> {
+        self.codec().encode(event)
+    }
+
+    /// Decode all readable records of an event log, skipping corrupted ones.
+    pub(crate) fn decode(self, bytes: &[u8]) -> Vec<LoggedEvent> {
+        self.codec().decode(bytes)
+    }
+}
+
+/// Encodes records of the event log.
+trait LogCodec: Send + Sync {
+    /// Encode a single record, including any framing.
+    fn encode(&self, event: &LoggedEvent) -> io::Result<Vec<u8>>;
+
+    /// Decode all records, skipping and logging records that can't be read.
+    fn decode(&self, bytes: &[u8]) -> Vec<LoggedEvent>;
+}
+
+struct Jsonl;
+
+impl LogCodec for Jsonl {
+    fn encode(&self, event: &LoggedEvent) -> io::Result<Vec<u8>> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        Ok(line)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Vec<LoggedEvent> {
+        String::from_utf8_lossy(bytes)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(index, line)| match serde_json::from_str(line) {
+                Ok(event) => Some(event),
+                Err(err) => {
+                    tracing::error!("Failed to parse event on line {}: {:?}", index + 1, err);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// `[length: u32 LE][crc32: u32 LE][MessagePack payload]` records.
+#[cfg(feature = "msgpack-log")]
+struct MessagePack;
+
+#[cfg(feature = "msgpack-log")]
+impl LogCodec for MessagePack {
+    fn encode(&self, event: &LoggedEvent) -> io::Result<Vec<u8>> {
+        let payload = rmp_serde::to_vec_named(event).map_err(io::Error::other)?;
+        let length = u32::try_from(payload.len()).map_err(io::Error::other)?;
+        let mut record = Vec::with_capacity(payload.len() + 8);
+        record.extend_from_slice(&length.to_le_bytes());
+        record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+        Ok(record)
+    }
+
+    fn decode(&self, mut bytes: &[u8]) -> Vec<LoggedEvent> {
+        let mut events = Vec::new();
+        let mut index = 0;
+        while !bytes.is_empty() {
+            index += 1;
+            let Some((header, rest)) = bytes.split_first_chunk::<8>() else {
+                tracing::error!("Event log is truncated at record {}", index);
+                break;
+            };
+            let length = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+            let checksum = u32::from_le_bytes(header[4..].try_into().unwrap());
+            if rest.len() < length {
+                tracing::error!("Event log is truncated at record {}", index);
+                break;
+            }
+            let (payload, rest) = rest.split_at(length);
+            bytes = rest;
+            if crc32fast::hash(payload) != checksum {
+                tracing::error!("Checksum mismatch in record {}, skipping it", index);
+                continue;
+            }
+            match rmp_serde::from_slice(payload) {
+                Ok(event) => events.push(event),
+                Err(err) => tracing::error!("Failed to parse record {}: {:?}", index, err),
+            }
+        }
+        events
+    }
+}
+
+/// A single record of the event log.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LoggedEvent {
+    /// When the event was written to the log.
+    pub logged_at: DateTime<Utc>,
+    /// The ID of the message, used to skip messages that were already delivered on replay.
+    #[serde(default)]
+    pub message_id: Option<String>,
+    /// The message as it was sent to the data plane.
+    pub message: LoggedMessage,
+}
+
+/// [rudderanalytics::message::Message] is untagged, which can't be deserialized
+/// without losing the message type, so the log stores the type next to the message.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum LoggedMessage {
+    Identify(rudderanalytics::message::Identify),
+    Track(rudderanalytics::message::Track),
+    Page(rudderanalytics::message::Page),
+    Screen(rudderanalytics::message::Screen),
+    Group(rudderanalytics::message::Group),
+    Alias(rudderanalytics::message::Alias),
+    Batch(rudderanalytics::message::Batch),
+}
+
+impl From<rudderanalytics::message::Message> for LoggedMessage {
+    fn from(message: rudderanalytics::message::Message) -> Self {
+        use rudderanalytics::message::Message;
+        match message {
+            Message::Identify(identify) => Self::Identify(identify),
+            Message::Track(track) => Self::Track(track),
+            Message::Page(page) => Self::Page(page),
+            Message::Screen(screen) => Self::Screen(screen),
+            Message::Group(group) => Self::Group(group),
+            Message::Alias(alias) => Self::Alias(alias),
+            Message::Batch(batch) => Self::Batch(batch),
+        }
+    }
+}
+
+impl From<LoggedMessage> for rudderanalytics::message::Message {
+    fn from(message: LoggedMessage) -> Self {
+        match message {
+            LoggedMessage::Identify(identify) => Self::Identify(identify),
+            LoggedMessage::Track(track) => Self::Track(track),
+            LoggedMessage::Page(page) => Self::Page(page),
+            LoggedMessage::Screen(screen) => Self::Screen(screen),
+            LoggedMessage::Group(group) => Self::Group(group),
+            LoggedMessage::Alias(alias) => Self::Alias(alias),
+            LoggedMessage::Batch(batch) => Self::Batch(batch),
+        }
+    }
+}
+
+/// Appends every outgoing message to a rotating log file.
+pub(crate) struct EventLog {
+    path: PathBuf,
+    format: EventLogFormat,
+    max_file_bytes: u64,
+    max_files: usize,
+    lock: Mutex<()>,
+}
+
+impl EventLog {
+    pub fn new(dir: &Path, format: EventLogFormat, max_file_bytes: u64, max_files: usize) -> Self {
+        Self {
+            path: dir.join(EVENT_LOG_FILE).with_extension(format.extension()),
+            format,
+            max_file_bytes,
+            max_files: max_files.max(1),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Append the message to the log, rotating the file if it is full.
+    pub fn append(
+        &self,
+        message: &rudderanalytics::message::Message,
+        message_id: &str,
+    ) -> io::Result<()> {
+        let line = self.format.codec().encode(&LoggedEvent {
+            logged_at: Utc::now(),
+            message_id: Some(message_id.to_string()),
+            message: message.clone().into(),
+        })?;
+
+        let _lock = self.lock.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.max_file_bytes {
+            self.rotate()?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)
+    }
+
+    /// All records of the log, oldest first.
+    pub fn read_all(&self) -> Vec<LoggedEvent> {
+        let _lock = self.lock.lock().unwrap();
+        self.files()
+            .iter()
+            .rev()
+            .filter_map(|path| fs::read(path).ok())
+            .flat_map(|bytes| self.format.decode(&bytes))
+            .collect()
+    }
+
+    /// Delete all log files.
+    pub fn clear(&self) -> io::Result<()> {
+        let _lock = self.lock.lock().unwrap();
+        self.files().iter().try_for_each(fs::remove_file)
+    }
+
+    /// The existing log files, newest first.
+    fn files(&self) -> Vec<PathBuf> {
+        std::iter::once(self.path.clone())
+            .chain((1..self.max_files).map(|index| self.rotated(index)))
+            .filter(|path| path.exists())
+            .collect()
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let extension = self.format.extension();
+        self.path.with_extension(format!("{index}.{extension}"))
+    }
+
+    /// `events.jsonl` -> `events.1.jsonl` -> `events.2.jsonl` ..., dropping the oldest file.
+    fn rotate(&self) -> io::Result<()> {
+        if self.max_files == 1 {
+            return fs::remove_file(&self.path);
+        }
+        let _ = fs::remove_file(self.rotated(self.max_files - 1));
+        for index in (1..self.max_files - 1).rev() {
+            let from = self.rotated(index);
+            if from.exists() {
+                fs::rename(from, self.rotated(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(event: &str) -> rudderanalytics::message::Message {
+        rudderanalytics::message::Message::Track(rudderanalytics::message::Track {
+            user_id: Some("user".to_string()),
+            event: event.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn logged(event: &str) -> LoggedEvent {
+        LoggedEvent {
+            logged_at: Utc::now(),
+            message_id: Some(format!("{event}-id")),
+            message: track(event).into(),
+        }
+    }
+
+    fn event_names(events: &[LoggedEvent]) -> Vec<&str> {
+        events
+            .iter()
+            .map(|logged| match &logged.message {
+                LoggedMessage::Track(track) => track.event.as_str(),
+                other => panic!("unexpected message {other:?}"),
+            })
+            .collect()
+    }
+
+    fn encode_all(format: EventLogFormat, events: &[&str]) -> Vec<Vec<u8>> {
+        events
+            .iter()
+            .map(|event| format.encode(&logged(event)).unwrap())
+            .collect()
+    }
+
+    /// A fresh directory for a log, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "rudderstack-event-log-{name}-{}",
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn round_trip(format: EventLogFormat) {
+        let bytes = encode_all(format, &["first", "second"]).concat();
+        let events = format.decode(&bytes);
+        assert_eq!(event_names(&events), ["first", "second"]);
+        assert_eq!(events[1].message_id.as_deref(), Some("second-id"));
+    }
+
+    #[test]
+    fn round_trips_jsonl() {
+        round_trip(EventLogFormat::Jsonl);
+    }
+
+    #[test]
+    fn skips_corrupted_jsonl_lines() {
+        let records = encode_all(EventLogFormat::Jsonl, &["first", "second", "third"]);
+        let bytes = [
+            &records[0][..],
+            b"{\"loggedAt\": \"garbage\n",
+            &records[2][..],
+        ]
+        .concat();
+        let events = EventLogFormat::Jsonl.decode(&bytes);
+        assert_eq!(event_names(&events), ["first", "third"]);
+
+        let truncated = &records.concat()[..records[0].len() + records[1].len() / 2];
+        assert_eq!(
+            event_names(&EventLogFormat::Jsonl.decode(truncated)),
+            ["first"]
+        );
+    }
+
+    #[cfg(feature = "msgpack-log")]
+    #[test]
+    fn round_trips_message_pack() {
+        round_trip(EventLogFormat::MessagePack);
+    }
+
+    #[cfg(feature = "msgpack-log")]
+    #[test]
+    fn skips_message_pack_records_with_a_checksum_mismatch() {
+        let mut records = encode_all(EventLogFormat::MessagePack, &["first", "second", "third"]);
+        let last = records[1].len() - 1;
+        records[1][last] ^= 0xff;
+        let events = EventLogFormat::MessagePack.decode(&records.concat());
+        assert_eq!(event_names(&events), ["first", "third"]);
+    }
+
+    #[cfg(feature = "msgpack-log")]
+    #[test]
+    fn stops_at_a_truncated_message_pack_record() {
+        let records = encode_all(EventLogFormat::MessagePack, &["first", "second"]);
+        let bytes = records.concat();
+        for cut in [records[0].len() + 4, bytes.len() - 1] {
+            let events = EventLogFormat::MessagePack.decode(&bytes[..cut]);
+            assert_eq!(event_names(&events), ["first"]);
+        }
+    }
+
+    #[test]
+    fn rotates_and_drops_the_oldest_file() {
+        let dir = TempDir::new("rotate");
+        // Room for one record per file.
+        let record = EventLogFormat::Jsonl.encode(&logged("event-0")).unwrap();
+        let log = EventLog::new(
+            &dir.0,
+            EventLogFormat::Jsonl,
+            record.len() as u64 * 3 / 2,
+            3,
+        );
+        for index in 0..5 {
+            log.append(&track(&format!("event-{index}")), &index.to_string())
+                .unwrap();
+        }
+
+        assert_eq!(
+            log.files(),
+            [log.path.clone(), log.rotated(1), log.rotated(2)]
+        );
+        assert!(!log.rotated(3).exists());
+        assert_eq!(
+            event_names(&log.read_all()),
+            ["event-2", "event-3", "event-4"]
+        );
+
+        log.clear().unwrap();
+        assert!(log.read_all().is_empty());
+    }
+}
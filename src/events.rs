@@ -0,0 +1,74 @@
+//! Tauri events emitted by the plugin.
+//!
+//! The events are registered with [tauri_specta], so the frontend gets typed listeners
+//! in the generated bindings, e.g. `events.eventSent.listen(...)`.
+//! Since this is a plugin the events are emitted as `plugin:rudderstack:<event-name>`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{rudderanalytics, DropReason};
+
+/// Emitted as `plugin:rudderstack:event-sent` when an event was delivered to the data plane.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSent {
+    /// The ID of the delivered message.
+    pub message_id: String,
+    /// The name of the event, or the message type for events without a name.
+    pub event: String,
+}
+
+/// Emitted as `plugin:rudderstack:event-failed` when an event failed to be delivered.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct EventFailed {
+    /// The ID of the message that failed.
+    pub message_id: String,
+    /// The name of the event, or the message type for events without a name.
+    pub event: String,
+    /// The delivery error.
+    pub error: String,
+}
+
+/// Emitted as `plugin:rudderstack:event-dropped` when an event expired or was dropped
+/// because too many deliveries are pending, see [RudderStackBuilder::max_event_age](crate::RudderStackBuilder::max_event_age).
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct EventDropped {
+    /// The ID of the dropped message.
+    pub message_id: String,
+    /// The name of the event, or the message type for events without a name.
+    pub event: String,
+    /// Why the event was dropped.
+    pub reason: DropReason,
+}
+
+/// The name of the event for the delivery status, or the message type for events without a name.
+pub(crate) fn event_name(message: &rudderanalytics::message::Message) -> String {
+    use rudderanalytics::message::Message;
+    match message {
+        Message::Track(track) => track.event.clone(),
+        Message::Page(page) => page.name.clone(),
+        Message::Screen(screen) => screen.name.clone(),
+        Message::Identify(_) => "identify".to_string(),
+        Message::Group(_) => "group".to_string(),
+        Message::Alias(_) => "alias".to_string(),
+        Message::Batch(_) => "batch".to_string(),
+    }
+}
+
+/// When the message was created, if the timestamp is set.
+pub(crate) fn message_timestamp(
+    message: &rudderanalytics::message::Message,
+) -> Option<chrono::DateTime<chrono::Utc>> {
+    use rudderanalytics::message::Message;
+    match message {
+        Message::Track(track) => track.original_timestamp,
+        Message::Page(page) => page.original_timestamp,
+        Message::Screen(screen) => screen.original_timestamp,
+        Message::Identify(identify) => identify.original_timestamp,
+        Message::Group(group) => group.original_timestamp,
+        Message::Alias(alias) => alias.original_timestamp,
+        Message::Batch(batch) => batch.original_timestamp,
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use crate::TrackEvent;
+
+/// Builds uniform `Funnel Step Completed` events for the steps of a funnel.
+///
+/// ```ignore
+/// let onboarding = Funnel::new("Onboarding").steps(["Signed Up", "Connected Account"]);
+///
+/// app.track(onboarding.step(2, "Connected Account"));
+/// // or numbered by the declared steps
+/// if let Some(step) = onboarding.step_named("Connected Account") {
+///     app.track(step);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Funnel {
+    name: String,
+    steps: Vec<String>,
+}
+
+impl Funnel {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Declare the steps in order, so they can be sent with [Self::step_named]
+    /// and the events include the `total_steps`.
+    pub fn steps(mut self, steps: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.steps = steps.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The event of a step, numbered from 1.
+    pub fn step(&self, index: u32, name: impl Into<String>) -> FunnelStep {
+        FunnelStep {
+            funnel_name: self.name.clone(),
+            step_index: index,
+            step_name: name.into(),
+            total_steps: self.total_steps(),
+        }
+    }
+
+    /// The event of a declared step, `None` if the step was not declared.
+    pub fn step_named(&self, name: &str) -> Option<FunnelStep> {
+        let position = self.steps.iter().position(|step| step == name)?;
+        Some(self.step(position as u32 + 1, name))
+    }
+
+    fn total_steps(&self) -> Option<u32> {
+        (!self.steps.is_empty()).then(|| self.steps.len() as u32)
+    }
+}
+
+/// A `Funnel Step Completed` event, see [Funnel].
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct FunnelStep {
+    pub funnel_name: String,
+    /// The number of the step, starting at 1.
+    pub step_index: u32,
+    pub step_name: String,
+    /// The number of declared steps in the funnel.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_steps: Option<u32>,
+}
+
+impl TrackEvent for FunnelStep {
+    const NAME: &'static str = "Funnel Step Completed";
+}
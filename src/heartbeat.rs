@@ -0,0 +1,69 @@
+//! Periodic `App Active` events, see [RudderStackBuilder::heartbeat](crate::RudderStackBuilder::heartbeat).
+
+use std::{collections::HashSet, sync::Mutex, time::Duration};
+
+use serde_json::json;
+use tauri::{Manager as _, Runtime};
+
+use crate::{instance::Instance, types::Track, AnalyticsExt as _};
+
+/// Tracks the focused windows, the heartbeat pauses while none is focused.
+#[derive(Debug)]
+pub(crate) struct Heartbeat {
+    interval: Duration,
+    focused: Mutex<HashSet<String>>,
+}
+
+impl Heartbeat {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            focused: Mutex::default(),
+        }
+    }
+
+    pub fn focus_changed(&self, label: &str, focused: bool) {
+        let mut windows = self.focused.lock().unwrap();
+        if focused {
+            windows.insert(label.to_string());
+        } else {
+            windows.remove(label);
+        }
+    }
+
+    /// The focused windows.
+    fn focused(&self) -> Vec<String> {
+        self.focused.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Send an `App Active` event every interval while a window is focused and not minimized,
+/// and the user is not idle.
+pub(crate) fn start<R: Runtime>(instance: Instance<R>) {
+    let Some(interval) = instance
+        .rudder()
+        .heartbeat()
+        .map(|heartbeat| heartbeat.interval)
+    else {
+        return;
+    };
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        let Some(heartbeat) = instance.rudder().heartbeat() else {
+            return;
+        };
+        let active = heartbeat.focused().iter().any(|label| {
+            instance
+                .handle
+                .get_window(label)
+                .is_some_and(|window| !window.is_minimized().unwrap_or(false))
+        });
+        if active && !instance.rudder().is_idle() {
+            instance.send_analytic_track(Track {
+                event: "App Active".to_string(),
+                properties: Some(json!({ "interval_seconds": interval.as_secs() })),
+                ..Track::default()
+            });
+        }
+    });
+}
@@ -0,0 +1,334 @@
+//! Async delivery to the RudderStack data plane.
+//!
+//! The rudderanalytics client only has a blocking send, which holds a thread for every request.
+//! This transport builds the same payloads and posts them with an async client instead.
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use rudderanalytics::{client::RudderAnalytics, errors::Error, message::Message};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    rudder_wrapper::merge,
+    transport::{DeliveryError, DeliveryFuture, Transport},
+};
+
+/// The library reported in the context, the payloads are the ones of the rudderanalytics client.
+const LIBRARY_NAME: &str = "RudderStack Rust SDK";
+const LIBRARY_VERSION: &str = "1.1.4";
+const CHANNEL: &str = "server";
+/// Context keys set by the library that events may not set.
+const RESERVED_KEYS: [&str; 1] = ["library"];
+
+/// The default transport, sending messages to the data plane.
+pub(crate) struct HttpTransport {
+    client: reqwest::Client,
+    /// Used for blocking deliveries, e.g. crash reports, and health checks.
+    blocking: RudderAnalytics,
+    #[cfg(feature = "gzip")]
+    gzip_batches: bool,
+}
+
+impl HttpTransport {
+    pub fn new(client: reqwest::Client, blocking: RudderAnalytics) -> Self {
+        Self {
+            client,
+            blocking,
+            #[cfg(feature = "gzip")]
+            gzip_batches: false,
+        }
+    }
+
+    /// Compress the bodies of batch requests, the data plane accepts gzip encoded requests.
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip_batches(mut self, gzip_batches: bool) -> Self {
+        self.gzip_batches = gzip_batches;
+        self
+    }
+
+    /// Post the message to the data plane, with its `messageId` if known.
+    async fn post(
+        self: Arc<Self>,
+        message: Message,
+        message_id: Option<String>,
+    ) -> Result<(), DeliveryError> {
+        let (path, payload) = payload(&message, Utc::now(), message_id.as_deref())?;
+        let request = self
+            .client
+            .post(format!("{}{}", self.blocking.data_plane_url, path))
+            .basic_auth(&self.blocking.write_key, Some(""));
+        #[cfg(feature = "gzip")]
+        let request = if self.gzip_batches && matches!(message, Message::Batch(_)) {
+            request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(reqwest::header::CONTENT_ENCODING, "gzip")
+                .body(gzip(&payload)?)
+        } else {
+            request.json(&payload)
+        };
+        #[cfg(not(feature = "gzip"))]
+        let request = request.json(&payload);
+        let res = request.send().await.map_err(Error::from)?;
+        if res.status() == 200 {
+            return Ok(());
+        }
+        let retry_after = res
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+        Err(DeliveryError::with_status(
+            res.status().as_u16(),
+            retry_after,
+        ))
+    }
+}
+
+impl Transport for HttpTransport {
+    fn deliver(&self, message: &Message) -> Result<(), DeliveryError> {
+        self.blocking.deliver(message)
+    }
+
+    fn health_check(&self) -> Result<(), Error> {
+        self.blocking.health_check()
+    }
+
+    fn deliver_async(self: Arc<Self>, message: Message) -> DeliveryFuture {
+        Box::pin(self.post(message, None))
+    }
+
+    fn deliver_with_id(self: Arc<Self>, message: Message, message_id: String) -> DeliveryFuture {
+        Box::pin(self.post(message, Some(message_id)))
+    }
+}
+
+/// Parse a `Retry-After` header, either delta-seconds or an HTTP-date.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let seconds = (date.with_timezone(&Utc) - now).num_seconds().max(0);
+    Some(Duration::from_secs(seconds as u64))
+}
+
+/// The size of the JSON payload sent for the message, with the metadata added to it,
+/// `None` if the message is invalid.
+pub(crate) fn payload_size(message: &Message) -> Option<usize> {
+    // the IDs are generated UUIDs, a placeholder of the same length gives the same size
+    let message_id = uuid::Uuid::nil().to_string();
+    let (_, payload) = payload(message, Utc::now(), Some(&message_id)).ok()?;
+    serde_json::to_vec(&payload).ok().map(|json| json.len())
+}
+
+/// The API path and the payload of a message, as the rudderanalytics client sends it,
+/// with the `messageId` the data plane deduplicates on.
+/// The events of a batch get the ID of the batch with their index, e.g. `<id>-0`.
+fn payload(
+    message: &Message,
+    sent_at: DateTime<Utc>,
+    message_id: Option<&str>,
+) -> Result<(String, Value), Error> {
+    validate(message)?;
+    let kind = message_type(message);
+    let mut payload =
+        serde_json::to_value(message).map_err(|err| Error::InvalidRequest(err.to_string()))?;
+    let Value::Object(fields) = &mut payload else {
+        return Err(Error::InvalidRequest(
+            "message is not an object".to_string(),
+        ));
+    };
+    let original_timestamp = fields
+        .get("originalTimestamp")
+        .cloned()
+        .unwrap_or_else(|| json!(sent_at));
+    if let Message::Batch(_) = message {
+        let batch_context = fields.get("context").cloned().unwrap_or_else(|| json!({}));
+        if let Some(Value::Array(batch)) = fields.get_mut("batch") {
+            for (index, event) in batch
+                .iter_mut()
+                .filter_map(Value::as_object_mut)
+                .enumerate()
+            {
+                let mut context = batch_context.clone();
+                if let Some(event_context) = event.get("context") {
+                    merge(&mut context, event_context);
+                }
+                merge(&mut context, &library_context());
+                event.insert("context".to_string(), context);
+                add_metadata(event, &original_timestamp, sent_at);
+                event.insert("channel".to_string(), CHANNEL.into());
+                if let Some(message_id) = message_id {
+                    event.insert(
+                        "messageId".to_string(),
+                        format!("{message_id}-{index}").into(),
+                    );
+                }
+            }
+        }
+        fields.insert("context".to_string(), batch_context);
+    } else {
+        // `library` is reserved, so it can be added without merging and copying the context
+        let mut context = match fields.remove("context") {
+            Some(Value::Object(context)) => context,
+            _ => Map::new(),
+        };
+        if let Value::Object(library) = library_context() {
+            context.extend(library);
+        }
+        fields.insert("context".to_string(), Value::Object(context));
+        fields.insert("channel".to_string(), CHANNEL.into());
+    }
+    add_metadata(fields, &original_timestamp, sent_at);
+    fields.insert("type".to_string(), kind.into());
+    if let Some(message_id) = message_id {
+        fields.insert("messageId".to_string(), message_id.into());
+    }
+    Ok((format!("/v1/{kind}"), payload))
+}
+
+/// The JSON encoded payload, compressed with gzip.
+#[cfg(feature = "gzip")]
+fn gzip(payload: &Value) -> Result<Vec<u8>, Error> {
+    use std::io::Write as _;
+
+    let json = serde_json::to_vec(payload).map_err(|err| Error::InvalidRequest(err.to_string()))?;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(&json)
+        .and_then(|()| encoder.finish())
+        .map_err(|err| Error::InvalidRequest(err.to_string()))
+}
+
+/// Reject the messages the rudderanalytics client rejects.
+fn validate(message: &Message) -> Result<(), Error> {
+    let (has_id, context) = match message {
+        Message::Identify(m) => (m.user_id.is_some() || m.anonymous_id.is_some(), &m.context),
+        Message::Track(m) => (m.user_id.is_some() || m.anonymous_id.is_some(), &m.context),
+        Message::Page(m) => (m.user_id.is_some() || m.anonymous_id.is_some(), &m.context),
+        Message::Screen(m) => (m.user_id.is_some() || m.anonymous_id.is_some(), &m.context),
+        Message::Group(m) => (m.user_id.is_some() || m.anonymous_id.is_some(), &m.context),
+        Message::Alias(m) => (true, &m.context),
+        Message::Batch(m) => (true, &m.context),
+    };
+    if !has_id {
+        return Err(Error::InvalidRequest(
+            "Either of user_id or anonymous_id is required".to_string(),
+        ));
+    }
+    let reserved = context
+        .as_ref()
+        .and_then(Value::as_object)
+        .is_some_and(|context| RESERVED_KEYS.iter().any(|key| context.contains_key(*key)));
+    if reserved {
+        return Err(Error::InvalidRequest(
+            "Reserve keyword present in context".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn message_type(message: &Message) -> &'static str {
+    match message {
+        Message::Identify(_) => "identify",
+        Message::Track(_) => "track",
+        Message::Page(_) => "page",
+        Message::Screen(_) => "screen",
+        Message::Group(_) => "group",
+        Message::Alias(_) => "alias",
+        Message::Batch(_) => "batch",
+    }
+}
+
+fn library_context() -> Value {
+    json!({
+        "library": {
+            "name": LIBRARY_NAME,
+            "version": LIBRARY_VERSION,
+        }
+    })
+}
+
+fn add_metadata(
+    fields: &mut Map<String, Value>,
+    original_timestamp: &Value,
+    sent_at: DateTime<Utc>,
+) {
+    fields.insert("originalTimestamp".to_string(), original_timestamp.clone());
+    fields.insert("sentAt".to_string(), json!(sent_at));
+}
+
+#[cfg(test)]
+mod tests {
+    use rudderanalytics::message::{Batch, BatchMessage, Track};
+
+    use super::*;
+
+    #[test]
+    fn builds_the_payload_of_the_rudderanalytics_client() {
+        let sent_at = Utc::now();
+        let track = Track {
+            anonymous_id: Some("anonymous".to_string()),
+            event: "Signed Up".to_string(),
+            context: Some(json!({ "app": "test" })),
+            ..Track::default()
+        };
+        let (path, body) = payload(&Message::Track(track.clone()), sent_at, Some("id")).unwrap();
+        assert_eq!(path, "/v1/track");
+        assert_eq!(body["type"], "track");
+        assert_eq!(body["channel"], "server");
+        assert_eq!(body["context"]["app"], "test");
+        assert_eq!(body["context"]["library"]["name"], LIBRARY_NAME);
+        assert_eq!(body["originalTimestamp"], json!(sent_at));
+        assert_eq!(body["sentAt"], json!(sent_at));
+        assert_eq!(body["messageId"], "id");
+
+        let batch = Batch {
+            batch: vec![BatchMessage::Track(track.clone())],
+            context: Some(json!({ "batch": true })),
+            ..Batch::default()
+        };
+        let (path, body) = payload(&Message::Batch(batch), sent_at, Some("batch-id")).unwrap();
+        assert_eq!(path, "/v1/batch");
+        assert_eq!(body["context"], json!({ "batch": true }));
+        assert_eq!(body["batch"][0]["type"], "track");
+        assert_eq!(body["batch"][0]["context"]["batch"], true);
+        assert_eq!(body["batch"][0]["context"]["app"], "test");
+        assert_eq!(body["messageId"], "batch-id");
+        assert_eq!(body["batch"][0]["messageId"], "batch-id-0");
+
+        let (_, body) = payload(&Message::Track(track), sent_at, None).unwrap();
+        assert!(body.get("messageId").is_none());
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn rejects_messages_without_an_id() {
+        let track = Track {
+            event: "Signed Up".to_string(),
+            ..Track::default()
+        };
+        assert!(payload(&Message::Track(track), Utc::now(), None).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+//! Generation of the anonymous ID.
+
+/// Generates new anonymous IDs, on the first run and after the analytics data is wiped.
+pub trait IdGenerator: Send + Sync + 'static {
+    /// Generate a new unique ID.
+    fn generate(&self) -> String;
+}
+
+/// Random UUIDs (v4), the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidV4;
+
+impl IdGenerator for UuidV4 {
+    fn generate(&self) -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+}
+
+/// Time-ordered UUIDs (v7), sortable by creation time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidV7;
+
+impl IdGenerator for UuidV7 {
+    fn generate(&self) -> String {
+        uuid::Uuid::now_v7().to_string()
+    }
+}
+
+/// Time-ordered [ULIDs](https://github.com/ulid/spec), sortable by creation time.
+#[cfg(feature = "ulid")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ulid;
+
+#[cfg(feature = "ulid")]
+impl IdGenerator for Ulid {
+    fn generate(&self) -> String {
+        ulid::Ulid::new().to_string()
+    }
+}
+
+impl<F> IdGenerator for F
+where
+    F: Fn() -> String + Send + Sync + 'static,
+{
+    fn generate(&self) -> String {
+        self()
+    }
+}
@@ -0,0 +1,19 @@
+// Reports user input in the webview to the rudderstack plugin, at most every few seconds.
+(function () {
+  if (window.__RUDDERSTACK_ACTIVITY__ || !window.__TAURI_INTERNALS__) return;
+  window.__RUDDERSTACK_ACTIVITY__ = true;
+
+  var lastReport = 0;
+  function report() {
+    var now = Date.now();
+    if (now - lastReport < 5000) return;
+    lastReport = now;
+    window.__TAURI_INTERNALS__
+      .invoke("plugin:rudderstack|report_activity")
+      .catch(function () {});
+  }
+
+  ["mousemove", "mousedown", "keydown", "wheel", "touchstart", "scroll"].forEach(function (type) {
+    window.addEventListener(type, report, { passive: true, capture: true });
+  });
+})();
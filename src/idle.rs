@@ -0,0 +1,119 @@
+//! Idle detection, see [RudderStackBuilder::idle_threshold](crate::RudderStackBuilder::idle_threshold).
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use tauri::Runtime;
+
+use crate::{instance::Instance, types::Track, AnalyticsExt as _};
+
+/// Reports the user input in the webviews to the `report_activity` command.
+pub(crate) const INIT_SCRIPT: &str = include_str!("idle.js");
+
+#[derive(Debug)]
+struct State {
+    /// The last activity in each window.
+    windows: HashMap<String, DateTime<Utc>>,
+    last_active: DateTime<Utc>,
+    last_instant: Instant,
+    idle: bool,
+}
+
+/// Tracks the input and focus activity of the user in the windows.
+#[derive(Debug)]
+pub(crate) struct IdleTracker {
+    threshold: Duration,
+    state: Mutex<State>,
+}
+
+impl IdleTracker {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            state: Mutex::new(State {
+                windows: HashMap::new(),
+                last_active: Utc::now(),
+                last_instant: Instant::now(),
+                idle: false,
+            }),
+        }
+    }
+
+    /// Record activity in the window. Returns how long the user was idle if they were.
+    pub fn activity(&self, label: &str) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        state.windows.insert(label.to_string(), now);
+        state.last_active = now;
+        let idle_for = state.last_instant.elapsed();
+        state.last_instant = Instant::now();
+        std::mem::replace(&mut state.idle, false).then_some(idle_for)
+    }
+
+    /// The last activity in the window, or in any window if `label` is `None`.
+    pub fn last_active(&self, label: Option<&str>) -> Option<DateTime<Utc>> {
+        let state = self.state.lock().unwrap();
+        match label {
+            Some(label) => state.windows.get(label).copied(),
+            None => Some(state.last_active),
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.state.lock().unwrap().idle
+    }
+
+    /// Mark the user idle if there was no activity for the threshold.
+    /// Returns true if the user became idle, and how long to wait before the next check.
+    fn check(&self) -> (bool, Duration) {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = state.last_instant.elapsed();
+        if state.idle {
+            (false, self.threshold)
+        } else if elapsed >= self.threshold {
+            state.idle = true;
+            (true, self.threshold)
+        } else {
+            (false, self.threshold - elapsed)
+        }
+    }
+}
+
+/// Record activity in the window, sending `User Became Active` if the user was idle.
+pub(crate) fn activity<R: Runtime>(instance: &Instance<R>, label: &str) {
+    let Some(idle_for) = instance
+        .rudder()
+        .idle_tracker()
+        .and_then(|tracker| tracker.activity(label))
+    else {
+        return;
+    };
+    instance.send_analytic_track(Track {
+        event: "User Became Active".to_string(),
+        properties: Some(json!({ "idle_seconds": idle_for.as_secs() })),
+        ..Track::default()
+    });
+}
+
+/// Send `User Became Idle` when there was no activity for the threshold.
+pub(crate) fn start<R: Runtime>(instance: Instance<R>) {
+    std::thread::spawn(move || loop {
+        let Some(tracker) = instance.rudder().idle_tracker() else {
+            return;
+        };
+        let (became_idle, wait) = tracker.check();
+        if became_idle {
+            instance.send_analytic_track(Track {
+                event: "User Became Idle".to_string(),
+                properties: Some(json!({ "idle_threshold_seconds": tracker.threshold.as_secs() })),
+                ..Track::default()
+            });
+        }
+        std::thread::sleep(wait);
+    });
+}
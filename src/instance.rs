@@ -0,0 +1,84 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use tauri::{AppHandle, Manager as _, Runtime};
+
+use crate::{rudder_wrapper::RudderWrapper, types::Identify};
+
+/// The named RudderStack instances, see [RudderStackBuilder::instance_name](crate::RudderStackBuilder::instance_name).
+#[derive(Default)]
+pub(crate) struct Instances(RwLock<HashMap<String, Arc<RudderWrapper>>>);
+
+impl Instances {
+    pub fn insert(&self, name: String, rudder: Arc<RudderWrapper>) {
+        self.0.write().unwrap().insert(name, rudder);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<RudderWrapper>> {
+        self.0.read().unwrap().get(name).cloned()
+    }
+}
+
+/// A RudderStack instance, e.g. a second project for error telemetry.
+/// Get it with [AnalyticsExt::instance](crate::AnalyticsExt::instance) and use it like the app handle.
+pub struct Instance<R: Runtime> {
+    pub(crate) handle: AppHandle<R>,
+    /// The named instance, or `None` for the default instance.
+    rudder: Option<Arc<RudderWrapper>>,
+}
+
+impl<R: Runtime> Clone for Instance<R> {
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            rudder: self.rudder.clone(),
+        }
+    }
+}
+
+impl<R: Runtime> Instance<R> {
+    /// The default instance, registered without an instance name.
+    pub(crate) fn default_instance(handle: &AppHandle<R>) -> Self {
+        Self {
+            handle: handle.clone(),
+            rudder: None,
+        }
+    }
+
+    /// The instance registered with this name, if any.
+    pub(crate) fn named(handle: &AppHandle<R>, name: &str) -> Option<Self> {
+        let rudder = handle.try_state::<Instances>()?.get(name)?;
+        Some(Self {
+            handle: handle.clone(),
+            rudder: Some(rudder),
+        })
+    }
+
+    /// The named instance, or the default instance if `name` is `None`.
+    pub(crate) fn get(handle: &AppHandle<R>, name: Option<&str>) -> Option<Self> {
+        match name {
+            Some(name) => Self::named(handle, name),
+            None => Some(Self::default_instance(handle)),
+        }
+    }
+
+    pub(crate) fn rudder(&self) -> &RudderWrapper {
+        match &self.rudder {
+            Some(rudder) => rudder,
+            None => self.handle.state::<RudderWrapper>().inner(),
+        }
+    }
+
+    /// Set the user ID and save it, sending `identify` as the Identify event if it is set,
+    /// see [RudderWrapper::identify_user].
+    pub(crate) fn identify_user(&self, user_id: Option<String>, identify: Option<Identify>) {
+        let rudder = self.rudder();
+        rudder.identify_user(user_id, identify);
+        // the change is recorded in the audit log
+        if let Err(err) = rudder.save(&self.handle) {
+            tracing::error!("Failed to save config: {:?}", err);
+        }
+    }
+}
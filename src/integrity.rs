@@ -0,0 +1,101 @@
+//! Detects unexpected resets of the anonymous ID, e.g. after the config file was corrupted
+//! or restored from a backup, which would otherwise look like a new user.
+
+use serde::{Deserialize, Serialize};
+
+/// Why the anonymous ID changed since the last run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum IdentityResetReason {
+    /// The config file was missing or could not be read, so a new anonymous ID was generated.
+    ConfigLost,
+    /// The config file holds a different anonymous ID than the last run used.
+    Mismatch,
+}
+
+/// An unexpected change of the anonymous ID since the last run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityReset {
+    /// The anonymous ID used by the last run.
+    pub previous_anonymous_id: String,
+    /// The anonymous ID loaded for this run.
+    pub anonymous_id: String,
+    pub reason: IdentityResetReason,
+}
+
+/// The status of the analytics plugin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsStatus {
+    /// True if events are currently sent, see [AnalyticsExt::is_analytics_allowed](crate::AnalyticsExt::is_analytics_allowed).
+    pub allowed: bool,
+    /// The anonymous ID of the user.
+    pub anonymous_id: String,
+    /// Set if the anonymous ID changed unexpectedly since the last run.
+    pub identity_reset: Option<IdentityReset>,
+}
+
+/// Compare the anonymous ID of the last run, recorded in the marker file,
+/// with the one loaded from the config file, `None` if the config could not be loaded.
+pub(crate) fn check(
+    marker: Option<String>,
+    loaded: Option<&str>,
+    anonymous_id: &str,
+) -> Option<IdentityReset> {
+    let previous_anonymous_id = marker?;
+    let reason = match loaded {
+        None => IdentityResetReason::ConfigLost,
+        Some(loaded) if loaded != previous_anonymous_id => IdentityResetReason::Mismatch,
+        Some(_) => return None,
+    };
+    tracing::warn!(%previous_anonymous_id, %anonymous_id, ?reason, "anonymous id was reset unexpectedly");
+    Some(IdentityReset {
+        previous_anonymous_id,
+        anonymous_id: anonymous_id.to_string(),
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_a_corrupted_config() {
+        // the config could not be loaded, so a new anonymous id was generated
+        let reset = check(Some("previous-id".to_string()), None, "new-id");
+        assert_eq!(
+            reset,
+            Some(IdentityReset {
+                previous_anonymous_id: "previous-id".to_string(),
+                anonymous_id: "new-id".to_string(),
+                reason: IdentityResetReason::ConfigLost,
+            })
+        );
+    }
+
+    #[test]
+    fn detects_a_changed_anonymous_id() {
+        // e.g. the config was restored from a backup of another installation
+        let reset = check(
+            Some("previous-id".to_string()),
+            Some("restored-id"),
+            "restored-id",
+        );
+        assert_eq!(
+            reset,
+            Some(IdentityReset {
+                previous_anonymous_id: "previous-id".to_string(),
+                anonymous_id: "restored-id".to_string(),
+                reason: IdentityResetReason::Mismatch,
+            })
+        );
+    }
+
+    #[test]
+    fn accepts_an_unchanged_anonymous_id_and_the_first_run() {
+        assert_eq!(check(Some("id".to_string()), Some("id"), "id"), None);
+        assert_eq!(check(None, None, "new-id"), None);
+    }
+}
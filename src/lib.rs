@@ -1,19 +1,163 @@
 #![doc = include_str!("../README.md")]
 
+#[cfg(not(any(feature = "client", feature = "noop")))]
+compile_error!("enable the `native-tls`, `rustls-tls` or `noop` feature");
+#[cfg(all(feature = "client", feature = "noop"))]
+compile_error!("the `noop` feature removes the client, disable the default features to use it");
+
+/// The message and error types of [Transport].
+/// With the `noop` feature a stand-in of the crate, so the same code compiles without it.
+#[cfg(not(feature = "noop"))]
+pub use ::rudderanalytics;
+#[cfg(feature = "noop")]
+#[path = "noop.rs"]
+pub mod rudderanalytics;
+
 pub use analytics_ext::AnalyticsExt;
+pub use audit::{AuditChange, AuditEntry};
+pub use backpressure::Backpressure;
+pub use consent::{ConsentError, ConsentPolicy, ConsentState, ConsentStatus};
+pub use context_merge::{MergeDepth, MergePrecedence};
+pub use context_provider::ContextProvider;
+pub use data::{AnalyticsData, WipeError};
+#[cfg(feature = "encryption")]
+pub use encryption::{EncryptionError, KeyProvider};
+pub use env::{EnvError, DATA_PLANE_ENV_VAR, ENABLED_ENV_VAR, WRITE_KEY_ENV_VAR};
+#[cfg(feature = "build-types")]
+pub use event_catalog::EventCatalog;
+pub use event_log::EventLogFormat;
+pub use events::{EventDropped, EventFailed, EventSent};
+pub use funnel::{Funnel, FunnelStep};
+#[cfg(feature = "ulid")]
+pub use id_generator::Ulid;
+pub use id_generator::{IdGenerator, UuidV4, UuidV7};
+pub use instance::Instance;
+pub use integrity::{AnalyticsStatus, IdentityReset, IdentityResetReason};
+pub use lifecycle::PushNotification;
+#[cfg(feature = "local-store")]
+pub use local_store::LocalStoreError;
+pub use metered::MeteredPolicy;
+pub use metrics::Metrics;
+pub use middleware::{CommandMiddleware, EventNameCase, EventNameNormalizer};
+pub use migration::IdentitySource;
+pub use priority::Priority;
+pub use registry::{EventDefinition, EventRenames, EventRenamesError};
+pub use replay::ReplayDiff;
+pub use sanitizer::PropertySanitizer;
+pub use send_result::{DropReason, SendError, SendResult, SendStatus};
+pub use storage::{ConfigStore, Storage, StoreError};
+#[cfg(feature = "tracing-layer")]
+pub use tracing_layer::AnalyticsLayer;
+pub use track_event::TrackEvent;
+#[cfg(feature = "tracking-plan")]
+pub use tracking_plan::{TrackingPlan, TrackingPlanError, ViolationBehavior};
+pub use transform::{
+    DestinationConsent, PageMode, PageToScreen, SendDecision, TransformContext, Transformer,
+};
+pub use transport::{Connectivity, DataPlaneError, DeliveryError, DeliveryFuture, Transport};
+
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use event_log::EventLog;
+use instance::Instances;
+use payload_size::PayloadLimit;
+use property_filter::PropertyFilter;
+use registry::EventRegistry;
 use rudder_wrapper::RudderWrapper;
+use sampling::Sampler;
 use tauri::{
     plugin::{Builder, TauriPlugin},
-    Manager, RunEvent, Runtime,
+    Manager, RunEvent, Runtime, WindowEvent,
 };
-use tracing::{error, info};
+use tauri_specta::Event as _;
+use tracing::{error, info, warn};
+use transport::HttpOptions;
 use types::Track;
+use webview_scope::WebviewScope;
 
+mod active_window;
 mod analytics_ext;
+mod audit;
+mod autosave;
+mod backpressure;
+#[cfg(feature = "build-types")]
+#[doc(hidden)]
+pub mod bindings;
+mod circuit_breaker;
 mod commands;
 mod config;
+mod connected_ids;
+mod consent;
+mod context_merge;
+mod context_provider;
+mod crash;
+mod data;
+mod dedup;
+#[cfg(feature = "deep-link")]
+mod deep_link;
+mod delivery_gate;
+mod do_not_track;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod env;
+#[cfg(feature = "build-types")]
+mod event_catalog;
+mod event_log;
+mod events;
+mod funnel;
+mod heartbeat;
+#[cfg(not(feature = "noop"))]
+mod http_transport;
+mod id_generator;
+mod idle;
+mod instance;
+mod integrity;
+mod lifecycle;
+#[cfg(feature = "local-store")]
+mod local_store;
+#[cfg(feature = "machine-id")]
+mod machine_id;
+mod memory;
+mod metered;
+mod metrics;
+mod middleware;
+mod migration;
+mod mock;
+mod offline;
+mod pacing;
+mod page_tracking;
+mod payload_size;
+#[cfg(feature = "plugin-store")]
+mod plugin_store;
+mod priority;
+mod property_filter;
+mod registry;
+mod replay;
 mod rudder_wrapper;
+mod sampling;
+mod sanitizer;
+mod send_result;
+mod storage;
+mod strict;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tracing-layer")]
+mod tracing_layer;
+mod track_event;
+#[cfg(feature = "tracking-plan")]
+mod tracking_plan;
+mod transform;
+mod transport;
 pub mod types;
+#[cfg(feature = "updater")]
+pub mod updater;
+mod webview_scope;
+mod window_metadata;
 
 const PLUGIN_NAME: &str = "rudderstack";
 
@@ -21,13 +165,45 @@ fn init_commands<R: Runtime>() -> tauri_specta::Builder<R> {
     tauri_specta::Builder::new()
         .plugin_name(PLUGIN_NAME)
         .commands(tauri_specta::collect_commands![
-            commands::send_analytics_alias<tauri::Wry>,
-            commands::send_analytics_group<tauri::Wry>,
-            commands::send_analytics_identify<tauri::Wry>,
-            commands::send_analytics_page<tauri::Wry>,
-            commands::send_analytics_screen<tauri::Wry>,
-            commands::send_analytics_track<tauri::Wry>
+            commands::send_analytics_alias<R>,
+            commands::send_analytics_group<R>,
+            commands::send_analytics_identify<R>,
+            commands::send_analytics_page<R>,
+            commands::send_analytics_screen<R>,
+            commands::send_analytics_track<R>,
+            commands::send_analytics_batch<R>,
+            commands::get_consent_state<R>,
+            commands::should_prompt_consent<R>,
+            commands::prompt_consent<R>,
+            commands::answer_consent<R>,
+            commands::set_consent_category<R>,
+            commands::get_metrics<R>,
+            commands::is_analytics_allowed<R>,
+            commands::check_analytics_connectivity<R>,
+            commands::get_audit_log<R>,
+            commands::increment_trait_counter<R>,
+            commands::export_analytics_data<R>,
+            commands::wipe_analytics_data<R>,
+            commands::get_analytics_status<R>,
+            commands::track_page_view<R>,
+            commands::report_activity<R>,
+            commands::set_analytics_user_id<R>
         ])
+        .events(tauri_specta::collect_events![
+            events::EventSent,
+            events::EventFailed,
+            events::EventDropped
+        ])
+        .typ::<funnel::FunnelStep>()
+        .typ::<types::ecommerce::ProductsSearched>()
+        .typ::<types::ecommerce::ProductViewed>()
+        .typ::<types::ecommerce::ProductAdded>()
+        .typ::<types::ecommerce::ProductRemoved>()
+        .typ::<types::ecommerce::CartViewed>()
+        .typ::<types::ecommerce::CheckoutStarted>()
+        .typ::<types::ecommerce::OrderCompleted>()
+        .typ::<types::ecommerce::OrderRefunded>()
+        .typ::<types::ecommerce::OrderCancelled>()
 }
 
 pub struct RudderStackBuilder {
@@ -35,7 +211,71 @@ pub struct RudderStackBuilder {
     key: String,
     anonymous_id: Option<String>,
     first_run: bool,
+    do_not_track: Option<Vec<String>>,
+    connected_ids_capacity: usize,
+    count_sessions: bool,
+    alias_on_identify: bool,
+    experiments_in_context: bool,
+    instance_name: Option<String>,
+    storage: Storage,
+    id_generator: Arc<dyn IdGenerator>,
+    #[cfg(feature = "machine-id")]
+    machine_id_salt: Option<String>,
+    config_store: Option<Arc<dyn ConfigStore>>,
+    #[cfg(feature = "plugin-store")]
+    plugin_store: Option<std::path::PathBuf>,
+    #[cfg(feature = "encryption")]
+    key_provider: Option<Arc<dyn KeyProvider>>,
+    identity_sources: Vec<IdentitySource>,
     context: types::Context,
+    context_providers: Vec<Box<dyn ContextProvider>>,
+    context_merge: context_merge::ContextMerge,
+    sample_rate: f64,
+    sampling_exempt_events: HashSet<String>,
+    events: Vec<EventDefinition>,
+    event_renames: EventRenames,
+    restrict_events: bool,
+    consent_policy: Option<ConsentPolicy>,
+    mock: bool,
+    dry_run: bool,
+    disable_in_debug: bool,
+    strict: bool,
+    active_window: bool,
+    track_pages: bool,
+    report_crashes: bool,
+    heartbeat: Option<Duration>,
+    idle_threshold: Option<Duration>,
+    #[cfg(feature = "tracing-layer")]
+    tracing_layer: Option<AnalyticsLayer>,
+    #[cfg(feature = "deep-link")]
+    track_deep_links: bool,
+    excluded_pages: Vec<String>,
+    track_lifecycle: bool,
+    allowed_webviews: Vec<String>,
+    recent_events: Option<usize>,
+    event_log: Option<(u64, usize)>,
+    event_log_format: EventLogFormat,
+    #[cfg(feature = "local-store")]
+    local_store: bool,
+    transformers: Vec<Box<dyn Transformer>>,
+    consent_destinations: HashSet<String>,
+    property_filter: PropertyFilter,
+    payload_limit: PayloadLimit,
+    command_middleware: Vec<Box<dyn CommandMiddleware>>,
+    transport: Option<Arc<dyn Transport>>,
+    http_options: HttpOptions,
+    max_concurrent_sends: usize,
+    max_pending_sends: Option<(usize, Backpressure)>,
+    metered_policy: MeteredPolicy,
+    metered_check_interval: Duration,
+    connectivity_probe: Option<Duration>,
+    circuit_breaker: Option<(u32, Duration)>,
+    max_event_age: Option<Duration>,
+    default_integrations: Option<serde_json::Value>,
+    memory_budget: Option<usize>,
+    low_memory: bool,
+    #[cfg(feature = "tracking-plan")]
+    tracking_plan: Option<TrackingPlan>,
 }
 
 impl RudderStackBuilder {
@@ -50,10 +290,89 @@ impl RudderStackBuilder {
             key: key.into(),
             anonymous_id: None,
             first_run: false,
+            do_not_track: None,
+            connected_ids_capacity: connected_ids::DEFAULT_CAPACITY,
+            count_sessions: false,
+            alias_on_identify: false,
+            experiments_in_context: false,
+            instance_name: None,
+            storage: Storage::default(),
+            id_generator: Arc::new(UuidV4),
+            #[cfg(feature = "machine-id")]
+            machine_id_salt: None,
+            config_store: None,
+            #[cfg(feature = "plugin-store")]
+            plugin_store: None,
+            #[cfg(feature = "encryption")]
+            key_provider: None,
+            identity_sources: Vec::new(),
             context: serde_json::Map::new(),
+            context_providers: Vec::new(),
+            context_merge: context_merge::ContextMerge::default(),
+            sample_rate: 1.0,
+            sampling_exempt_events: HashSet::new(),
+            events: Vec::new(),
+            event_renames: EventRenames::new(),
+            restrict_events: false,
+            consent_policy: None,
+            mock: false,
+            dry_run: false,
+            disable_in_debug: false,
+            strict: false,
+            active_window: false,
+            track_pages: false,
+            report_crashes: false,
+            heartbeat: None,
+            idle_threshold: None,
+            #[cfg(feature = "tracing-layer")]
+            tracing_layer: None,
+            #[cfg(feature = "deep-link")]
+            track_deep_links: false,
+            excluded_pages: Vec::new(),
+            track_lifecycle: false,
+            allowed_webviews: Vec::new(),
+            recent_events: None,
+            event_log: None,
+            event_log_format: EventLogFormat::default(),
+            #[cfg(feature = "local-store")]
+            local_store: false,
+            transformers: Vec::new(),
+            consent_destinations: HashSet::new(),
+            property_filter: PropertyFilter::default(),
+            payload_limit: PayloadLimit::default(),
+            command_middleware: Vec::new(),
+            transport: None,
+            http_options: HttpOptions::default(),
+            max_concurrent_sends: rudder_wrapper::DEFAULT_MAX_CONCURRENT_SENDS,
+            max_pending_sends: None,
+            metered_policy: MeteredPolicy::Deliver,
+            metered_check_interval: metered::DEFAULT_CHECK_INTERVAL,
+            connectivity_probe: None,
+            circuit_breaker: None,
+            max_event_age: None,
+            default_integrations: None,
+            memory_budget: None,
+            low_memory: false,
+            #[cfg(feature = "tracking-plan")]
+            tracking_plan: None,
         }
     }
 
+    /// Initializes the plugin with the data plane URL and write key from the
+    /// `RUDDERSTACK_DATA_PLANE` and `RUDDERSTACK_WRITE_KEY` environment variables,
+    /// read at runtime. \
+    /// To bake them in at compile time use `RudderStackBuilder::new(env!(..), env!(..))` instead.
+    ///
+    /// ```ignore
+    /// let builder = RudderStackBuilder::from_env()?.disable_in_debug();
+    /// ```
+    pub fn from_env() -> Result<Self, EnvError> {
+        Ok(Self::new(
+            env::var(DATA_PLANE_ENV_VAR)?,
+            env::var(WRITE_KEY_ENV_VAR)?,
+        ))
+    }
+
     /// WARNING: This will stop the internal anonymous ID from being generated.
     ///
     /// The anonymous ID of the user. this is optional and will be generated if not provided. if provided it will need to be provided on subsequent runs to maintain the same user.
@@ -68,6 +387,147 @@ impl RudderStackBuilder {
         self
     }
 
+    /// Disable sending when the user or the OS asked not to be tracked,
+    /// checked once on startup. Nothing is sent, including events of the plugin itself. \
+    /// The `DO_NOT_TRACK` environment variable is checked, plus the telemetry group policy on Windows.
+    /// Add more variables with [Self::do_not_track_env_var].
+    pub fn respect_do_not_track(mut self, respect: bool) -> Self {
+        self.do_not_track = respect.then(|| vec![do_not_track::DEFAULT_ENV_VAR.to_string()]);
+        self
+    }
+
+    /// Also disable sending when this environment variable is set to a value other than `0` or `false`,
+    /// e.g. `MY_APP_NO_TELEMETRY`. Enables [Self::respect_do_not_track].
+    pub fn do_not_track_env_var(mut self, name: impl Into<String>) -> Self {
+        self.do_not_track
+            .get_or_insert_with(|| vec![do_not_track::DEFAULT_ENV_VAR.to_string()])
+            .push(name.into());
+        self
+    }
+
+    /// The number of user IDs that are remembered to send an identify event only the first time
+    /// a user ID is set, the least recently used are evicted. Defaults to 100.
+    pub fn connected_ids_capacity(mut self, capacity: usize) -> Self {
+        self.connected_ids_capacity = capacity;
+        self
+    }
+
+    /// If set to true, the `total_sessions` trait counter is incremented on every app start,
+    /// see [AnalyticsExt::increment_trait_counter].
+    pub fn count_sessions(mut self, count_sessions: bool) -> Self {
+        self.count_sessions = count_sessions;
+        self
+    }
+
+    /// If set to true, an Alias event from the anonymous ID to the user ID is sent before the Identify event
+    /// when a user ID is set for the first time, for destinations that merge identities with aliases,
+    /// e.g. Mixpanel and Amplitude.
+    pub fn alias_on_identify(mut self, alias_on_identify: bool) -> Self {
+        self.alias_on_identify = alias_on_identify;
+        self
+    }
+
+    /// If set to true, the variants sent with [AnalyticsExt::track_experiment] are added to the
+    /// `experiments` context of all subsequent events, as a map of experiment to variant.
+    pub fn experiments_in_context(mut self, enabled: bool) -> Self {
+        self.experiments_in_context = enabled;
+        self
+    }
+
+    /// Register this plugin as a named instance, e.g. to send error telemetry to a second
+    /// RudderStack project next to product analytics. Use it with
+    /// [AnalyticsExt::instance] as `app.instance("errors")`. \
+    /// Named instances keep their own config file and are not available to the webview commands,
+    /// which use the instance without a name.
+    ///
+    /// ```ignore
+    /// .plugin(RudderStackBuilder::new(data_plane, errors_key).instance_name("errors").build())
+    /// ```
+    pub fn instance_name(mut self, name: impl Into<String>) -> Self {
+        self.instance_name = Some(name.into());
+        self
+    }
+
+    /// Where the anonymous ID and user ID are stored, defaults to [Storage::File]. \
+    /// [Storage::Keyring] requires the `keyring` feature.
+    ///
+    /// ```ignore
+    /// .storage(Storage::Keyring)
+    /// ```
+    pub fn storage(mut self, storage: Storage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Generate new anonymous IDs with this generator, e.g. [UuidV7] for time-ordered IDs. \
+    /// Defaults to random UUIDs ([UuidV4]). Existing anonymous IDs are kept.
+    pub fn id_generator(mut self, generator: impl IdGenerator) -> Self {
+        self.id_generator = Arc::new(generator);
+        self
+    }
+
+    /// Keep the identity in memory only, see [Storage::Memory]. \
+    /// Nothing is written to disk, including the [event log](Self::event_log) and local store,
+    /// and every run gets a fresh anonymous ID.
+    pub fn ephemeral_identity(mut self, ephemeral: bool) -> Self {
+        if ephemeral {
+            self.storage = Storage::Memory;
+        }
+        self
+    }
+
+    /// Persist the config in `store` instead of `tauri-rudderstack.json` in the app config dir,
+    /// e.g. in the app's own database. See [ConfigStore].
+    pub fn config_store(mut self, store: impl ConfigStore) -> Self {
+        self.config_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Persist the config in the `tauri-plugin-store` store at `path`, e.g. `"settings.json"`,
+    /// under the `rudderstack` key. \
+    /// The store plugin must be registered before this plugin. Takes precedence over [Self::config_store].
+    #[cfg(feature = "plugin-store")]
+    pub fn plugin_store(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.plugin_store = Some(path.into());
+        self
+    }
+
+    /// Encrypt the persisted config, which holds the user IDs and trait counters,
+    /// with AES-256-GCM using the key of `key_provider`. \
+    /// An existing plain text config is encrypted on the next save.
+    /// A [custom config store](Self::config_store) is not encrypted.
+    /// The [event log](Self::event_log) and the local store hold the events in plain text,
+    /// so they are disabled when the config is encrypted.
+    #[cfg(feature = "encryption")]
+    pub fn encrypt_config(mut self, key_provider: impl KeyProvider) -> Self {
+        self.key_provider = Some(Arc::new(key_provider));
+        self
+    }
+
+    /// Derive the anonymous ID from a hash of `salt` and the machine ID when no config exists,
+    /// so reinstalls keep the same anonymous user. Use a salt unique to the app,
+    /// so the ID can't be correlated across apps. \
+    /// An ID imported with [Self::import_anonymous_id] takes precedence.
+    #[cfg(feature = "machine-id")]
+    pub fn anonymous_id_from_machine(mut self, salt: impl Into<String>) -> Self {
+        self.machine_id_salt = Some(salt.into());
+        self
+    }
+
+    /// Import the anonymous ID stored by the analytics plugin the app used before, so products
+    /// switching vendors keep the continuity of unique-user metrics. \
+    /// The sources are read in order on the first run of this plugin, the first ID found is used.
+    /// Ignored if [RudderStackBuilder::anonymous_id] is set.
+    ///
+    /// ```ignore
+    /// .import_anonymous_id(IdentitySource::posthog())
+    /// .import_anonymous_id(IdentitySource::json_file("analytics/identity.json", "/anonymous_id"))
+    /// ```
+    pub fn import_anonymous_id(mut self, source: IdentitySource) -> Self {
+        self.identity_sources.push(source);
+        self
+    }
+
     /// Allows you to set the context that will be sent with every event.
     pub fn with_context<F>(mut self, f: F) -> Self
     where
@@ -77,34 +537,942 @@ impl RudderStackBuilder {
         self
     }
 
+    /// Add a [ContextProvider] that adds dynamic values to the context of every event when it is sent,
+    /// e.g. the focused window or feature flags.
+    pub fn context_provider(mut self, provider: impl ContextProvider) -> Self {
+        self.context_providers.push(Box::new(provider));
+        self
+    }
+
+    /// How the context of an event is merged into the global context, deep by default.
+    pub fn context_merge_depth(mut self, depth: MergeDepth) -> Self {
+        self.context_merge.depth = depth;
+        self
+    }
+
+    /// Which value is kept when the context of an event has a key of the global context,
+    /// the value of the event by default. Use [MergePrecedence::GlobalWins] so events,
+    /// e.g. from the webview, can't overwrite the global context.
+    pub fn context_merge_precedence(mut self, precedence: MergePrecedence) -> Self {
+        self.context_merge.precedence = precedence;
+        self
+    }
+
+    /// The fraction of [Track](types::Track) events that will be sent, between `0.0` and `1.0`. \
+    /// Defaults to `1.0`, which sends every event.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = rate;
+        self
+    }
+
+    /// Event names that are always sent regardless of sampling, e.g. purchases. \
+    /// Sends of these events are counted in [AnalyticsExt::forced_sends].
+    pub fn sampling_exempt_events<I, S>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.sampling_exempt_events
+            .extend(events.into_iter().map(Into::into));
+        self
+    }
+
+    /// Register an event in the event registry.
+    ///
+    /// Registered events can be limited to a range of app versions,
+    /// events sent outside of that range are dropped with a warning.
+    pub fn register_event(mut self, event: EventDefinition) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Rename track events before they are sent, e.g. after events were renamed but old call sites
+    /// or cached frontend bundles still use the old names. Calling this again adds the renames. \
+    /// [Allowed events](Self::allowed_events) are checked with the new names.
+    pub fn event_renames(mut self, renames: EventRenames) -> Self {
+        self.event_renames.extend(renames);
+        self
+    }
+
+    /// Only allow the webview to send track events whose names are registered. \
+    /// The names are added to the event registry, see [RudderStackBuilder::register_event].
+    ///
+    /// This prevents a compromised or buggy frontend from sending arbitrary event names.
+    pub fn allowed_events<I, S>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for event in events {
+            let event = event.into();
+            if !self.events.iter().any(|e| e.name() == event) {
+                self.events.push(EventDefinition::new(event));
+            }
+        }
+        self.restrict_events = true;
+        self
+    }
+
+    /// Require the user to grant consent before any events are sent.
+    /// Events marked as essential on the policy are always sent.
+    ///
+    /// Declaring a newer policy version resets users that answered an older version,
+    /// so they are re-prompted and non-essential events are suppressed until they answer again.
+    ///
+    /// The consent state is persisted by the plugin, use [AnalyticsExt::prompt_consent] and
+    /// [AnalyticsExt::answer_consent] (or the matching commands) to drive the consent flow.
+    pub fn consent_policy(mut self, policy: ConsentPolicy) -> Self {
+        self.consent_policy = Some(policy);
+        self
+    }
+
+    /// If set to true, no events are sent to the data plane.
+    /// Instead all outgoing messages are recorded in memory and can be inspected with
+    /// [AnalyticsExt::recorded_events] and [AnalyticsExt::assert_tracked].
+    ///
+    /// Use this to integration test your app without a live write key,
+    /// the `testing` feature provides a harness built on `tauri::test::mock_app()`.
+    pub fn mock(mut self, mock: bool) -> Self {
+        self.mock = mock;
+        self
+    }
+
+    /// If set to true, events are fully assembled (context merge, anonymous ID injection)
+    /// and logged to the `rudderstack::dry_run` tracing target, but never sent.
+    ///
+    /// Use this to verify payloads during development without polluting production data.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Don't send events from debug builds, so development doesn't pollute production analytics.
+    /// Events are handled like with [Self::dry_run] instead. \
+    /// Set the `RUDDERSTACK_ENABLED` environment variable to `1` to send them anyway.
+    pub fn disable_in_debug(mut self) -> Self {
+        self.disable_in_debug = true;
+        self
+    }
+
+    /// If set to true, the focused window is added to the context of every event as
+    /// `active_window` with its label, a hash of its title and how long it has been focused,
+    /// so events can be attributed to the window the user was interacting with.
+    pub fn active_window_context(mut self, enabled: bool) -> Self {
+        self.active_window = enabled;
+        self
+    }
+
+    /// If set to true, a Page event is sent for every page load and history change of the webviews,
+    /// like `watchURLChanges` of the guest bindings. The page is named after the path,
+    /// with the `url`, `path` and `title` as properties. Events are sent through the window,
+    /// so they get its [context](AnalyticsExt::add_to_context). \
+    /// Exclude paths with [Self::exclude_page]. Only available for the default instance.
+    pub fn track_pages(mut self, enabled: bool) -> Self {
+        self.track_pages = enabled;
+        self
+    }
+
+    /// If set to true, the lifecycle events of the RudderStack mobile SDKs are sent on Android
+    /// and iOS: `Application Opened` on launch and when the app returns to the foreground,
+    /// with a `from_background` property, and `Application Backgrounded`. \
+    /// Send push notification events with [AnalyticsExt::track_push_notification].
+    pub fn track_lifecycle(mut self, enabled: bool) -> Self {
+        self.track_lifecycle = enabled;
+        self
+    }
+
+    /// Don't send a Page event for this path with [Self::track_pages],
+    /// a pattern ending with `*` excludes all paths with this prefix, e.g. `/settings/*`.
+    pub fn exclude_page(mut self, pattern: impl Into<String>) -> Self {
+        self.excluded_pages.push(pattern.into());
+        self
+    }
+
+    /// Only let webviews with a matching label invoke the commands, e.g. to keep an embedded
+    /// third-party webview from sending events as the user. `*` matches any characters,
+    /// e.g. `settings-*`. Calls from other webviews are rejected. \
+    /// The label of the webview is matched, not of its window, since a window can host several
+    /// webviews. All webviews are allowed if no pattern is added.
+    pub fn allow_webview(mut self, pattern: impl Into<String>) -> Self {
+        self.allowed_webviews.push(pattern.into());
+        self
+    }
+
+    /// If set to true, a `Deep Link Opened` event is sent with the URL and its UTM parameters
+    /// for the deep links the app is opened with, and the campaign attribution is added to
+    /// the `campaign` context of subsequent events. \
+    /// Register `tauri-plugin-deep-link` before this plugin.
+    #[cfg(feature = "deep-link")]
+    pub fn track_deep_links(mut self, enabled: bool) -> Self {
+        self.track_deep_links = enabled;
+        self
+    }
+
+    /// Send an `App Active` event every `interval` while a window of the app is focused
+    /// and not minimized, so engagement time can be computed downstream.
+    pub fn heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat = Some(interval);
+        self
+    }
+
+    /// Send a `User Became Idle` event when there was no input or window focus for `threshold`,
+    /// and a `User Became Active` event on the next activity. The [heartbeat](Self::heartbeat)
+    /// pauses while the user is idle. \
+    /// The input in the webviews is reported by a script, the last activity is available
+    /// with [AnalyticsExt::last_active].
+    pub fn idle_threshold(mut self, threshold: Duration) -> Self {
+        self.idle_threshold = Some(threshold);
+        self
+    }
+
+    /// If set to true, an `Application Crashed` event with the panic message, location and app version
+    /// is sent when the app panics, before the previous panic hook runs. \
+    /// If it can't be delivered, e.g. while offline, it is sent on the next launch.
+    pub fn report_crashes(mut self, enabled: bool) -> Self {
+        self.report_crashes = enabled;
+        self
+    }
+
+    /// Send the log records matched by this [AnalyticsLayer] as track events with this instance.
+    /// Add the layer to the tracing subscriber of the app as well.
+    #[cfg(feature = "tracing-layer")]
+    pub fn tracing_layer(mut self, layer: AnalyticsLayer) -> Self {
+        self.tracing_layer = Some(layer);
+        self
+    }
+
+    /// If set to true, instrumentation mistakes panic in debug builds at the call site of the event
+    /// instead of being dropped or flagged: empty event names, tracking plan violations,
+    /// destinations without a consent category in a [DestinationConsent] transformer and payloads
+    /// that are larger than the data plane accepts after truncation.
+    /// Release builds keep the lenient behavior.
+    ///
+    /// Use this to catch instrumentation bugs during development instead of in the warehouse.
+    pub fn strict_mode(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Keep the last `capacity` events in memory, so they can be replayed against the
+    /// current rules with [AnalyticsExt::replay_recent_events].
+    ///
+    /// Use this during development to verify new rules against real traffic.
+    pub fn capture_recent_events(mut self, capacity: usize) -> Self {
+        self.recent_events = Some(capacity);
+        self
+    }
+
+    /// Append every outgoing event to a local `rudderstack-events.jsonl` file in the app data dir,
+    /// see [RudderStackBuilder::event_log_format] for other encodings.
+    ///
+    /// The file is rotated once it exceeds `max_file_bytes`, keeping at most `max_files` files.
+    /// This gives an auditable local record of the telemetry the app emitted.
+    /// The log is not encrypted, it is disabled with [Self::encrypt_config].
+    pub fn event_log(mut self, max_file_bytes: u64, max_files: usize) -> Self {
+        self.event_log = Some((max_file_bytes, max_files));
+        self
+    }
+
+    /// The on-disk encoding of the [event log](Self::event_log). Defaults to JSONL.
+    pub fn event_log_format(mut self, format: EventLogFormat) -> Self {
+        self.event_log_format = format;
+        self
+    }
+
+    /// Store every outgoing event in a local `rudderstack-events.sqlite` database in the app data dir,
+    /// for on-device analytics and air-gapped deployments. \
+    /// Events are stored before they are sent, also in mock and dry run mode,
+    /// and can be exported for a later sync with [AnalyticsExt::export_local_store].
+    /// The store is not encrypted, it is disabled with [Self::encrypt_config].
+    #[cfg(feature = "local-store")]
+    pub fn local_store(mut self, enabled: bool) -> Self {
+        self.local_store = enabled;
+        self
+    }
+
+    /// Remove these top-level keys from the properties and traits of every outgoing event,
+    /// including events sent by the plugin itself.
+    ///
+    /// ```ignore
+    /// .deny_properties(["email", "filePath"])
+    /// ```
+    pub fn deny_properties<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.property_filter
+            .deny_properties(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Keep only these top-level keys in the properties and traits of every outgoing event,
+    /// all other keys are removed. Calling this again extends the allowlist.
+    pub fn allow_properties<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.property_filter
+            .allow_properties(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Remove these top-level keys from the context of every outgoing event.
+    pub fn deny_context_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.property_filter
+            .deny_context(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Truncate these top-level string properties and traits, in this order, when a message
+    /// exceeds the 32KB limit of the RudderStack API, e.g. stack traces or log excerpts. \
+    /// Messages that are still too large are not sent and fail with [SendError::PayloadTooLarge].
+    pub fn truncate_properties<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.payload_limit
+            .truncate_properties(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add a [Transformer] that modifies events before they are sent.
+    /// Transformers run in the order they are added.
+    ///
+    /// See [DestinationConsent] for a built-in transformer that disables destinations
+    /// based on the consent categories of the user, [PageToScreen] to send Page events
+    /// as Screen events on desktop, and [PropertySanitizer] to normalize property values.
+    pub fn transformer(mut self, transformer: impl Transformer) -> Self {
+        // strict mode checks that events only enable destinations with a consent category
+        if let Some(consent) = (&transformer as &dyn Any).downcast_ref::<DestinationConsent>() {
+            self.consent_destinations
+                .extend(consent.destinations().cloned());
+        }
+        self.transformers.push(Box::new(transformer));
+        self
+    }
+
+    /// Add a [CommandMiddleware] for events sent from the webview, e.g. to attach
+    /// session tokens or to veto events from specific windows.
+    /// Middlewares run in the order they are added.
+    /// See [EventNameNormalizer] for a built-in middleware that normalizes event names.
+    pub fn command_middleware(mut self, middleware: impl CommandMiddleware) -> Self {
+        self.command_middleware.push(Box::new(middleware));
+        self
+    }
+
+    /// Deliver messages with a custom [Transport] instead of sending them to the data plane.
+    pub fn transport(mut self, transport: impl Transport) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// The timeout for connecting to the data plane. Defaults to 10 seconds.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http_options.connect_timeout = timeout;
+        self
+    }
+
+    /// The timeout for a whole request to the data plane, or `None` to wait indefinitely.
+    /// Defaults to 30 seconds.
+    pub fn request_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.http_options.request_timeout = timeout;
+        self
+    }
+
+    /// The maximum number of messages delivered at the same time, further messages wait
+    /// for a delivery to finish. This keeps a burst of events from exhausting the blocking
+    /// thread pool shared with other plugins when a [Transport] without
+    /// [async delivery](Transport::deliver_async) is used. Defaults to 4, values below 1 are treated as 1.
+    pub fn max_concurrent_sends(mut self, max: usize) -> Self {
+        self.max_concurrent_sends = max;
+        self
+    }
+
+    /// The maximum number of messages queued or being delivered, e.g. to bound the memory used
+    /// by logging-heavy sessions while the data plane is slow. New messages are handled by
+    /// `backpressure` while the limit is reached. Unlimited by default. \
+    /// Dropped messages are counted in [Metrics::overflowed] and emitted as [EventDropped].
+    pub fn max_pending_sends(mut self, max: usize, backpressure: Backpressure) -> Self {
+        self.max_pending_sends = Some((max, backpressure));
+        self
+    }
+
+    /// Drop events older than `max_age` instead of delivering them, so events held while offline
+    /// or resent from a previous run don't flood the data plane with stale data. Held events
+    /// are checked again before they are delivered. \
+    /// Dropped events are counted in [Metrics::expired] and emitted as [EventDropped].
+    pub fn max_event_age(mut self, max_age: Duration) -> Self {
+        self.max_event_age = Some(max_age);
+        self
+    }
+
+    /// How events are delivered while the connection is metered, e.g. when tethering to a phone.
+    /// Held events count towards the [pending sends](Self::max_pending_sends). \
+    /// Metered connections are detected with NetworkManager on Linux and on Windows,
+    /// events are delivered as usual where the OS does not tell. Defaults to [MeteredPolicy::Deliver].
+    pub fn metered_policy(mut self, policy: MeteredPolicy) -> Self {
+        self.metered_policy = policy;
+        self
+    }
+
+    /// How often the connection is checked under a [metered policy](Self::metered_policy).
+    /// Each check runs `busctl` on Linux and PowerShell on Windows. \
+    /// The interval doubles, up to an hour, while the OS does not tell. Defaults to 60 seconds.
+    pub fn metered_check_interval(mut self, interval: Duration) -> Self {
+        self.metered_check_interval = interval;
+        self
+    }
+
+    /// Hold the deliveries after the data plane could not be connected to, e.g. while offline,
+    /// instead of failing each of them after the [connect timeout](Self::connect_timeout).
+    /// The data plane is probed with the [health check](Transport::health_check) every
+    /// `probe_interval`, and the held events are delivered as soon as it is reachable. \
+    /// Held events count towards the [pending sends](Self::max_pending_sends).
+    pub fn monitor_connectivity(mut self, probe_interval: Duration) -> Self {
+        self.connectivity_probe = Some(probe_interval);
+        self
+    }
+
+    /// Stop attempting deliveries for `cool_down` after `failures` consecutive failed deliveries,
+    /// e.g. while the data plane is down or rate limiting, instead of sending a request for
+    /// every event. Only transport errors, server errors and 429 Too Many Requests count,
+    /// events rejected by the data plane don't, see [DeliveryError::status]. The events are held meanwhile, then a single
+    /// delivery probes the data plane and the others follow if it succeeds. \
+    /// Held events count towards the [pending sends](Self::max_pending_sends).
+    pub fn circuit_breaker(mut self, failures: u32, cool_down: Duration) -> Self {
+        self.circuit_breaker = Some((failures, cool_down));
+        self
+    }
+
+    /// How long idle connections to the data plane are kept alive, or `None` to keep them
+    /// indefinitely. Defaults to 90 seconds.
+    pub fn keep_alive(mut self, keep_alive: Option<Duration>) -> Self {
+        self.http_options.keep_alive = keep_alive;
+        self
+    }
+
+    /// Extra HTTP headers sent with every request to the data plane,
+    /// e.g. the auth header of an API gateway in front of it. \
+    /// Headers computed per request need a custom [Transport].
+    pub fn extra_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.http_options.headers.extend(headers);
+        self
+    }
+
+    /// Compress the bodies of batch requests with gzip, to save bandwidth on metered connections.
+    /// Other events are small and sent uncompressed. Requires the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    pub fn gzip_batches(mut self, enabled: bool) -> Self {
+        self.http_options.gzip_batches = enabled;
+        self
+    }
+
+    /// Trust a PEM encoded root certificate for the data plane connection, in addition to
+    /// the system ones, e.g. for a self-hosted data plane behind an internal CA. \
+    /// Requires the `native-tls` (default) or `rustls-tls` feature.
+    pub fn root_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.http_options.root_certificates.push(pem.into());
+        self
+    }
+
+    /// The integrations merged into the `integrations` field of every message,
+    /// e.g. to route desktop events to a different set of destinations than web. \
+    /// Integrations set on an event take precedence. See [types::Integrations] for a typed builder.
+    pub fn default_integrations(mut self, integrations: impl Into<serde_json::Value>) -> Self {
+        self.default_integrations = Some(integrations.into());
+        self
+    }
+
+    /// Limit the approximate memory, in bytes, used by the queued deliveries, the recent events
+    /// buffer, the sent message IDs, the connected user IDs and the messages recorded in mock mode.
+    /// The oldest items are evicted once the budget is exceeded, evicted deliveries are dropped
+    /// with [DropReason::Backpressured]. Deliveries in flight and the context are not counted.
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// A preset for low-end machines, limiting the memory budget to 256KB, the number of
+    /// recent events that are captured, the pending deliveries and the connected user IDs.
+    /// Without [max_pending_sends](Self::max_pending_sends), messages are rejected
+    /// while 64 deliveries are pending.
+    pub fn low_memory_mode(mut self) -> Self {
+        self.memory_budget = Some(memory::LOW_MEMORY_BUDGET);
+        self.low_memory = true;
+        self
+    }
+
+    /// Validate the properties of track events against a [TrackingPlan] before they are sent.
+    #[cfg(feature = "tracking-plan")]
+    pub fn tracking_plan(mut self, plan: TrackingPlan) -> Self {
+        self.tracking_plan = Some(plan);
+        self
+    }
+
+    /// With the `noop` feature nothing is sent or persisted,
+    /// whatever the builder was configured with.
+    #[cfg(feature = "noop")]
+    fn inert(self) -> Self {
+        Self {
+            storage: Storage::Memory,
+            config_store: None,
+            #[cfg(feature = "plugin-store")]
+            plugin_store: None,
+            #[cfg(feature = "local-store")]
+            local_store: false,
+            event_log: None,
+            report_crashes: false,
+            transport: None,
+            ..self
+        }
+    }
+
     pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        #[cfg(feature = "noop")]
+        return self.inert().build_plugin();
+        #[cfg(not(feature = "noop"))]
+        self.build_plugin()
+    }
+
+    fn build_plugin<R: Runtime>(self) -> TauriPlugin<R> {
         info!("Initializing RudderStack plugin");
         let specta = init_commands();
-        Builder::new(PLUGIN_NAME)
-            .invoke_handler(specta.invoke_handler())
+        let instance_name = self.instance_name.clone();
+        // the lifecycle events are only sent on mobile
+        let track_lifecycle =
+            self.track_lifecycle && cfg!(any(target_os = "android", target_os = "ios"));
+        // named instances are separate plugins without commands, the commands use the default instance
+        let builder = match &instance_name {
+            Some(name) => Builder::new(Box::leak(format!("{PLUGIN_NAME}-{name}").into_boxed_str())),
+            None => {
+                let handler = specta.invoke_handler();
+                let scope = WebviewScope::new(self.allowed_webviews.clone());
+                Builder::new(PLUGIN_NAME).invoke_handler(move |invoke| {
+                    let label = invoke.message.webview_ref().label().to_string();
+                    if !scope.is_allowed(&label) {
+                        warn!(webview = %label, "rejected analytics command from webview");
+                        invoke
+                            .resolver
+                            .reject(format!("webview {label} may not invoke analytics commands"));
+                        return true;
+                    }
+                    handler(invoke)
+                })
+            }
+        };
+        // the scripts report to the commands, which only exist on the default instance
+        let mut init_scripts = Vec::new();
+        if self.track_pages {
+            init_scripts.push(page_tracking::INIT_SCRIPT);
+        }
+        if self.idle_threshold.is_some() {
+            init_scripts.push(idle::INIT_SCRIPT);
+        }
+        let builder = match init_scripts.is_empty() || instance_name.is_some() {
+            true => builder,
+            false => builder.js_init_script(init_scripts.join("\n")),
+        };
+        builder
             .setup(move |app, _| {
+                if self.instance_name.is_none() {
+                    specta.mount_events(app);
+                }
+
+                let mut config_file =
+                    config::ConfigFile::new(self.instance_name.clone()).with_storage(self.storage);
+                if let Some(store) = self.config_store.clone() {
+                    config_file = config_file.with_store(store);
+                }
+                #[cfg(feature = "plugin-store")]
+                if let Some(path) = self.plugin_store.clone() {
+                    config_file = config_file.with_store(Arc::new(plugin_store::PluginStore::new(
+                        app.clone(),
+                        path,
+                        self.instance_name.as_deref(),
+                    )));
+                }
+                #[cfg(feature = "encryption")]
+                if let Some(key_provider) = self.key_provider.clone() {
+                    config_file = config_file.with_key_provider(key_provider);
+                }
+
                 // load the config from the file or create a new one
-                let config = config::Config::try_load(app);
+                let config = config::Config::try_load(app, &config_file);
 
                 // if first run is set, and loading the config failed, set the first run flag since a new uuid will be generated
                 let first_run = self.first_run && config.is_err();
 
-                let mut config = config.unwrap_or_default();
+                let new_config = config.is_err();
+                // the anonymous id of the last run, to detect unexpected identity resets
+                let marker = config::Config::read_marker(app, &config_file);
+                let loaded_anonymous_id = config
+                    .as_ref()
+                    .ok()
+                    .map(|config| config.anonymous_id().to_string());
+                let mut config =
+                    config.unwrap_or_else(|_| config::Config::new(self.id_generator.generate()));
+
+                // keep the anonymous id of the previous analytics plugin on the first run,
+                // or derive it from the machine id
+                if new_config && self.anonymous_id.is_none() && !config_file.is_ephemeral() {
+                    let id = migration::import_anonymous_id(app, &self.identity_sources);
+                    #[cfg(feature = "machine-id")]
+                    let id = id.or_else(|| {
+                        self.machine_id_salt
+                            .as_deref()
+                            .and_then(machine_id::derive_anonymous_id)
+                    });
+                    if let Some(id) = id {
+                        config.set_anonymous_id(id);
+                    }
+                }
 
                 // set the anonymous id if provided
                 if let Some(id) = self.anonymous_id {
                     config.set_anonymous_id(id);
                 };
-                // save the config
-                if let Err(err) = config.save(app) {
+
+                let identity_reset = integrity::check(
+                    marker,
+                    loaded_anonymous_id.as_deref(),
+                    config.anonymous_id(),
+                );
+
+                if self.count_sessions {
+                    config.increment_trait_counter("total_sessions");
+                }
+
+                #[cfg(feature = "updater")]
+                let previous_version =
+                    config.set_app_version(app.package_info().version.to_string());
+
+                // re-prompt for consent if the user answered an older policy version
+                if let Some(policy) = &self.consent_policy {
+                    if config
+                        .consent_mut()
+                        .expire_outdated(policy, chrono::Utc::now())
+                    {
+                        config.record_consent_change();
+                        info!("Consent policy version changed, consent needs to be renewed");
+                    }
+                }
+                // save the config with the sequence numbers of this launch reserved
+                config.reserve_sequence();
+                if let Err(err) = config.save(app, &config_file) {
                     error!("Failed to save config: {:?}", err);
                 }
-                let rudder_analytics = RudderWrapper::new(self.data_plane, self.key, config, self.context);
+                let sampler = Sampler::new(self.sample_rate, self.sampling_exempt_events);
+                let registry = EventRegistry::new(
+                    self.events,
+                    self.event_renames,
+                    app.package_info().version.clone(),
+                    self.restrict_events,
+                );
+                let custom_transport = self.transport.is_some();
+                let transport = self.transport.unwrap_or_else(|| {
+                    Arc::new(
+                        self.http_options
+                            .transport(self.data_plane, self.key.clone()),
+                    )
+                });
+                let rudder_analytics = RudderWrapper::new(
+                    transport,
+                    config,
+                    self.context,
+                    sampler,
+                    registry,
+                    self.consent_policy,
+                );
+                let rudder_analytics = if custom_transport {
+                    rudder_analytics
+                } else {
+                    rudder_analytics.with_default_transport(self.http_options, self.key)
+                };
+                let rudder_analytics = if self.mock {
+                    rudder_analytics.with_mock()
+                } else {
+                    rudder_analytics
+                };
+                let disabled_in_debug = self.disable_in_debug && env::is_disabled_debug_build();
+                if disabled_in_debug {
+                    info!("analytics are disabled in debug builds, set {ENABLED_ENV_VAR}=1");
+                }
+                let rudder_analytics = if self.dry_run || disabled_in_debug {
+                    rudder_analytics.with_dry_run()
+                } else {
+                    rudder_analytics
+                };
+                let rudder_analytics = if self.active_window {
+                    rudder_analytics.with_active_window()
+                } else {
+                    rudder_analytics
+                };
+                let rudder_analytics = match &self.do_not_track {
+                    Some(env_vars) if do_not_track::is_requested(env_vars) => {
+                        rudder_analytics.with_do_not_track()
+                    }
+                    _ => rudder_analytics,
+                };
+                let rudder_analytics = if self.track_pages {
+                    rudder_analytics
+                        .with_page_tracking(page_tracking::PageTracking::new(self.excluded_pages))
+                } else {
+                    rudder_analytics
+                };
+                let rudder_analytics = match self.idle_threshold {
+                    Some(threshold) => rudder_analytics.with_idle_threshold(threshold),
+                    None => rudder_analytics,
+                };
+                let rudder_analytics = match self.heartbeat {
+                    Some(interval) => rudder_analytics.with_heartbeat(interval),
+                    None => rudder_analytics,
+                };
+                let rudder_analytics = if self.experiments_in_context {
+                    rudder_analytics.with_experiments_in_context()
+                } else {
+                    rudder_analytics
+                };
+                let rudder_analytics = if self.alias_on_identify {
+                    rudder_analytics.with_alias_on_identify()
+                } else {
+                    rudder_analytics
+                };
+                let rudder_analytics = if self.strict && cfg!(debug_assertions) {
+                    rudder_analytics.with_strict_mode(self.consent_destinations)
+                } else {
+                    rudder_analytics
+                };
+                let rudder_analytics = match self.memory_budget {
+                    Some(limit) => rudder_analytics.with_memory_budget(limit),
+                    None => rudder_analytics,
+                };
+                let recent_events = match self.low_memory {
+                    true => self
+                        .recent_events
+                        .map(|capacity| capacity.min(memory::LOW_MEMORY_RECENT_EVENTS)),
+                    false => self.recent_events,
+                };
+                let rudder_analytics = match recent_events {
+                    Some(capacity) => rudder_analytics.with_recent_events(capacity),
+                    None => rudder_analytics,
+                };
+                let rudder_analytics = rudder_analytics
+                    .with_context_providers(self.context_providers)
+                    .with_context_merge(self.context_merge)
+                    .with_max_concurrent_sends(self.max_concurrent_sends)
+                    .with_metered_policy(self.metered_policy)
+                    .with_transformers(self.transformers)
+                    .with_property_filter(self.property_filter)
+                    .with_payload_limit(self.payload_limit)
+                    .with_command_middleware(self.command_middleware);
+                let max_pending_sends = match (self.low_memory, self.max_pending_sends) {
+                    (true, Some((max, backpressure))) => {
+                        Some((max.min(memory::LOW_MEMORY_PENDING_SENDS), backpressure))
+                    }
+                    (true, None) => Some((memory::LOW_MEMORY_PENDING_SENDS, Backpressure::Reject)),
+                    (false, max_pending_sends) => max_pending_sends,
+                };
+                let rudder_analytics = match max_pending_sends {
+                    Some((max, backpressure)) => {
+                        rudder_analytics.with_max_pending_sends(max, backpressure)
+                    }
+                    None => rudder_analytics,
+                };
+                let rudder_analytics = match self.connectivity_probe {
+                    Some(interval) => rudder_analytics.with_connectivity_monitor(interval),
+                    None => rudder_analytics,
+                };
+                let rudder_analytics = match self.circuit_breaker {
+                    Some((failures, cool_down)) => {
+                        rudder_analytics.with_circuit_breaker(failures, cool_down)
+                    }
+                    None => rudder_analytics,
+                };
+                let rudder_analytics = match self.max_event_age {
+                    Some(max_age) => rudder_analytics.with_max_event_age(max_age),
+                    None => rudder_analytics,
+                };
+                let rudder_analytics = match self.default_integrations {
+                    Some(integrations) => rudder_analytics.with_default_integrations(integrations),
+                    None => rudder_analytics,
+                };
+                let handle = app.clone();
+                let rudder_analytics = rudder_analytics.with_delivery_listener(Arc::new(
+                    move |message_id, event, result| {
+                        let emitted = match result {
+                            Ok(_) => events::EventSent {
+                                message_id: message_id.to_string(),
+                                event: event.to_string(),
+                            }
+                            .emit(&handle),
+                            Err(err) => events::EventFailed {
+                                message_id: message_id.to_string(),
+                                event: event.to_string(),
+                                error: format!("{:?}", err),
+                            }
+                            .emit(&handle),
+                        };
+                        if let Err(err) = emitted {
+                            error!("Failed to emit delivery status: {:?}", err);
+                        }
+                    },
+                ));
+                let handle = app.clone();
+                let rudder_analytics = rudder_analytics.with_drop_listener(Arc::new(
+                    move |message_id, event, reason| {
+                        let emitted = events::EventDropped {
+                            message_id: message_id.to_string(),
+                            event: event.to_string(),
+                            reason,
+                        }
+                        .emit(&handle);
+                        if let Err(err) = emitted {
+                            error!("Failed to emit drop status: {:?}", err);
+                        }
+                    },
+                ));
+                // nothing is written to disk with in-memory storage
+                let ephemeral = config_file.is_ephemeral();
+                if ephemeral && self.event_log.is_some() {
+                    warn!("The event log is disabled with in-memory storage");
+                }
+                // the event log and the local store are not encrypted
+                #[cfg(feature = "encryption")]
+                let encrypted = self.key_provider.is_some();
+                #[cfg(not(feature = "encryption"))]
+                let encrypted = false;
+                if encrypted && self.event_log.is_some() {
+                    error!("The event log is disabled with an encrypted config");
+                }
+                let event_log = self.event_log.filter(|_| !ephemeral && !encrypted);
+                let rudder_analytics = match (event_log, app.path().app_data_dir()) {
+                    (Some((max_file_bytes, max_files)), Ok(dir)) => rudder_analytics
+                        .with_event_log(EventLog::new(
+                            &dir,
+                            self.event_log_format,
+                            max_file_bytes,
+                            max_files,
+                        )),
+                    (Some(_), Err(err)) => {
+                        error!("Failed to get app data dir for the event log: {:?}", err);
+                        rudder_analytics
+                    }
+                    (None, _) => rudder_analytics,
+                };
+                #[cfg(feature = "local-store")]
+                if encrypted && self.local_store {
+                    error!("The local store is disabled with an encrypted config");
+                }
+                #[cfg(feature = "local-store")]
+                let rudder_analytics = match self.local_store && !ephemeral && !encrypted {
+                    true => match app
+                        .path()
+                        .app_data_dir()
+                        .map_err(local_store::LocalStoreError::from)
+                        .and_then(|dir| local_store::LocalStore::open(&dir))
+                    {
+                        Ok(local_store) => rudder_analytics.with_local_store(local_store),
+                        Err(err) => {
+                            error!("Failed to open the local store: {:?}", err);
+                            rudder_analytics
+                        }
+                    },
+                    false => rudder_analytics,
+                };
+                #[cfg(feature = "tracking-plan")]
+                let rudder_analytics = match self.tracking_plan {
+                    Some(plan) => rudder_analytics.with_tracking_plan(plan),
+                    None => rudder_analytics,
+                };
 
-                app.manage(rudder_analytics);
+                let rudder_analytics = rudder_analytics
+                    .with_config_file(config_file)
+                    .with_id_generator(self.id_generator)
+                    .with_connected_ids_capacity(match self.low_memory {
+                        true => self
+                            .connected_ids_capacity
+                            .min(memory::LOW_MEMORY_CONNECTED_IDS),
+                        false => self.connected_ids_capacity,
+                    })
+                    .with_identity_reset(identity_reset.clone());
+                let instance = match self.instance_name {
+                    Some(name) => {
+                        if app.try_state::<Instances>().is_none() {
+                            app.manage(Instances::default());
+                        }
+                        app.state::<Instances>()
+                            .insert(name.clone(), Arc::new(rudder_analytics));
+                        Instance::named(app, &name)
+                    }
+                    None => {
+                        app.manage(rudder_analytics);
+                        Some(Instance::default_instance(app))
+                    }
+                };
+
+                if let Some(instance) = &instance {
+                    autosave::start(instance.clone());
+                }
+
+                if let (Some(reset), Some(instance)) = (identity_reset, &instance) {
+                    instance.send_analytic_track(types::Track {
+                        event: "Anonymous ID Reset".to_string(),
+                        properties: Some(serde_json::json!({
+                            "previous_anonymous_id": reset.previous_anonymous_id,
+                            "reason": reset.reason,
+                        })),
+                        ..Track::default()
+                    });
+                }
+
+                #[cfg(feature = "updater")]
+                if let Some(instance) = &instance {
+                    updater::installed(instance, previous_version);
+                }
+
+                if let (true, Some(instance)) = (self.idle_threshold.is_some(), &instance) {
+                    idle::start(instance.clone());
+                }
 
-                if first_run {
-                    app.send_analytic_track(types::Track {
+                if let (true, Some(instance)) = (self.heartbeat.is_some(), &instance) {
+                    heartbeat::start(instance.clone());
+                }
+
+                if let Some(instance) = &instance {
+                    metered::start(instance.clone(), self.metered_check_interval);
+                }
+
+                if let (true, Some(instance)) = (self.report_crashes, &instance) {
+                    crash::send_pending(instance);
+                    crash::install(instance.clone());
+                }
+
+                #[cfg(feature = "tracing-layer")]
+                if let (Some(layer), Some(instance)) = (&self.tracing_layer, &instance) {
+                    let instance = instance.clone();
+                    layer.connect(move |track| {
+                        instance.send_analytic_track(track);
+                    });
+                }
+
+                #[cfg(feature = "deep-link")]
+                if let (true, Some(instance)) = (self.track_deep_links, &instance) {
+                    deep_link::track(instance);
+                }
+
+                if let (true, Some(instance)) = (track_lifecycle, &instance) {
+                    lifecycle::opened(instance, false);
+                }
+
+                if let (true, Some(instance)) = (first_run, instance) {
+                    instance.send_analytic_track(types::Track {
                         event: "First Run".to_string(),
                         ..Track::default()
                     });
@@ -112,12 +1480,53 @@ impl RudderStackBuilder {
 
                 Ok(())
             })
-            .on_event(|app, event| {
-                if let RunEvent::Exit = event {
-                    let host = app.state::<RudderWrapper>();
-                    if let Err(err) = host.save(app) {
-                        error!("Failed to save config: {:?}", err);
+            .on_event(move |app, event| {
+                let Some(instance) = Instance::get(app, instance_name.as_deref()) else {
+                    return;
+                };
+                match event {
+                    RunEvent::Exit => {
+                        instance.rudder().release_sequence();
+                        instance.rudder().metered_checks().stop();
+                        if let Err(err) = instance.rudder().save(app) {
+                            error!("Failed to save config: {:?}", err);
+                        }
+                    }
+                    RunEvent::WindowEvent {
+                        label,
+                        event: WindowEvent::Focused(focused),
+                        ..
+                    } => {
+                        let title = app.get_window(label).and_then(|window| window.title().ok());
+                        instance
+                            .rudder()
+                            .window_focus_changed(label, title, *focused);
+                        if *focused {
+                            idle::activity(&instance, label);
+                        }
                     }
+                    RunEvent::WindowEvent {
+                        label,
+                        event: WindowEvent::Destroyed,
+                        ..
+                    } => {
+                        instance.rudder().clear_window_context(label);
+                    }
+                    #[cfg(any(target_os = "android", target_os = "ios"))]
+                    RunEvent::WindowEvent {
+                        event: WindowEvent::Resumed,
+                        ..
+                    } if track_lifecycle => {
+                        lifecycle::opened(&instance, true);
+                    }
+                    #[cfg(any(target_os = "android", target_os = "ios"))]
+                    RunEvent::WindowEvent {
+                        event: WindowEvent::Suspended,
+                        ..
+                    } if track_lifecycle => {
+                        lifecycle::backgrounded(&instance);
+                    }
+                    _ => {}
                 }
             })
             .build()
@@ -129,25 +1538,12 @@ mod test {
     #[allow(unused_imports)]
     use super::*;
 
-    /// build the commands and export the types
-    /// this is used to generate the typescript bindings
-    /// this is run with
+    /// regenerates `guest-js/bindings.ts`, same as
     ///
-    /// `cargo test export_types --features build-types`
+    /// `cargo run --bin export-bindings --features build-types`
     #[test]
     #[cfg(feature = "build-types")]
     fn export_types() {
-        use super::*;
-
-        let builder = init_commands::<tauri::Wry>();
-        builder
-            .export(
-                specta_typescript::Typescript::default()
-                    .formatter(specta_typescript::formatter::eslint)
-                    .bigint(specta_typescript::BigIntExportBehavior::BigInt)
-                    .header("/* eslint-disable */ \n // @ts-nocheck"),
-                "./guest-js/bindings.ts",
-            )
-            .expect("failed to export specta types");
+        bindings::export(bindings::PATH).expect("failed to export specta types");
     }
 }
@@ -0,0 +1,47 @@
+//! The lifecycle events of the RudderStack mobile SDKs,
+//! see [RudderStackBuilder::track_lifecycle](crate::RudderStackBuilder::track_lifecycle).
+
+use serde_json::json;
+use tauri::Runtime;
+
+use crate::{instance::Instance, types::Track, AnalyticsExt as _};
+
+/// A push notification event of the RudderStack mobile SDKs,
+/// see [AnalyticsExt::track_push_notification](crate::AnalyticsExt::track_push_notification).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PushNotification {
+    /// `Push Notification Received`
+    Received,
+    /// `Push Notification Tapped`
+    Tapped,
+}
+
+impl PushNotification {
+    pub fn event_name(self) -> &'static str {
+        match self {
+            PushNotification::Received => "Push Notification Received",
+            PushNotification::Tapped => "Push Notification Tapped",
+        }
+    }
+}
+
+/// `Application Opened`, `from_background` is false on launch.
+pub(crate) fn opened<R: Runtime>(instance: &Instance<R>, from_background: bool) {
+    instance.send_analytic_track(Track {
+        event: "Application Opened".to_string(),
+        properties: Some(json!({
+            "from_background": from_background,
+            "version": instance.handle.package_info().version.to_string(),
+        })),
+        ..Track::default()
+    });
+}
+
+/// `Application Backgrounded`
+#[cfg(any(target_os = "android", target_os = "ios"))]
+pub(crate) fn backgrounded<R: Runtime>(instance: &Instance<R>) {
+    instance.send_analytic_track(Track {
+        event: "Application Backgrounded".to_string(),
+        ..Track::default()
+    });
+}
@@ -0,0 +1,199 @@
+use std::{
+    fs,
+    io::{self, Write as _},
+    path::Path,
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use crate::{
+    event_log::{EventLogFormat, LoggedEvent, LoggedMessage},
+    events::event_name,
+    rudderanalytics,
+};
+
+/// The name of the local store database in the app data dir.
+pub(crate) const LOCAL_STORE_FILE: &str = "rudderstack-events.sqlite";
+
+/// Stores every outgoing message in a local SQLite database, for on-device analytics
+/// and deployments that can't reach a data plane.
+pub(crate) struct LocalStore {
+    connection: Mutex<Connection>,
+}
+
+impl LocalStore {
+    /// Open or create the database in `dir`.
+    pub fn open(dir: &Path) -> Result<Self, LocalStoreError> {
+        fs::create_dir_all(dir)?;
+        let connection = Connection::open(dir.join(LOCAL_STORE_FILE))?;
+        connection.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                message_id TEXT NOT NULL,
+                event TEXT NOT NULL,
+                stored_at TEXT NOT NULL,
+                payload TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS events_event ON events (event);",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Store the message.
+    pub fn insert(
+        &self,
+        message: &rudderanalytics::message::Message,
+        message_id: &str,
+    ) -> Result<(), LocalStoreError> {
+        let payload = serde_json::to_string(&LoggedMessage::from(message.clone()))?;
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO events (message_id, event, stored_at, payload) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                message_id,
+                event_name(message),
+                Utc::now().to_rfc3339(),
+                payload
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// All stored events, oldest first.
+    pub fn events(&self) -> Result<Vec<LoggedEvent>, LocalStoreError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement =
+            connection.prepare("SELECT message_id, stored_at, payload FROM events ORDER BY id")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let (message_id, stored_at, payload) = row?;
+            events.push(LoggedEvent {
+                logged_at: DateTime::parse_from_rfc3339(&stored_at)
+                    .map(|at| at.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+                message_id: Some(message_id),
+                message: serde_json::from_str(&payload)?,
+            });
+        }
+        Ok(events)
+    }
+
+    /// Write all stored events to `path` as a JSONL event log,
+    /// which can be sent later with [AnalyticsExt::replay_from_file](crate::AnalyticsExt::replay_from_file). \
+    /// Returns the number of exported events.
+    pub fn export(&self, path: &Path) -> Result<usize, LocalStoreError> {
+        let events = self.events()?;
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        for event in &events {
+            file.write_all(&EventLogFormat::Jsonl.encode(event)?)?;
+        }
+        file.flush()?;
+        Ok(events.len())
+    }
+
+    /// Delete all stored events.
+    pub fn clear(&self) -> Result<(), LocalStoreError> {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM events", [])?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LocalStoreError {
+    #[error("the local store is not enabled")]
+    NotEnabled,
+    #[error("failed to get app data dir")]
+    AppDataDir(#[from] tauri::Error),
+    #[error("local store database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("failed to write the export: {0}")]
+    Io(#[from] io::Error),
+    #[error("failed to serialize event: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rudderanalytics::message::{Message, Track};
+
+    use super::*;
+
+    /// A fresh directory for a database, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "rudderstack-local-store-{name}-{}",
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn track(event: &str) -> Message {
+        Message::Track(Track {
+            anonymous_id: Some("anonymous-id".to_string()),
+            event: event.to_string(),
+            properties: Some(serde_json::json!({ "plan": "pro" })),
+            ..Default::default()
+        })
+    }
+
+    fn stored(events: Vec<LoggedEvent>) -> Vec<(Option<String>, Message)> {
+        events
+            .into_iter()
+            .map(|event| (event.message_id, event.message.into()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_events() {
+        let dir = TempDir::new("round-trip");
+        let store = LocalStore::open(&dir.0).unwrap();
+        store.insert(&track("Signed In"), "first").unwrap();
+        store.insert(&track("Signed Out"), "second").unwrap();
+        let expected = vec![
+            (Some("first".to_string()), track("Signed In")),
+            (Some("second".to_string()), track("Signed Out")),
+        ];
+        assert_eq!(stored(store.events().unwrap()), expected);
+
+        // the events are kept across restarts
+        drop(store);
+        let store = LocalStore::open(&dir.0).unwrap();
+        assert_eq!(stored(store.events().unwrap()), expected);
+
+        let export = dir.0.join("export.jsonl");
+        assert_eq!(store.export(&export).unwrap(), 2);
+        let exported = EventLogFormat::Jsonl.decode(&fs::read(&export).unwrap());
+        assert_eq!(stored(exported), expected);
+
+        store.clear().unwrap();
+        assert!(store.events().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,20 @@
+//! Derives the anonymous ID from a stable machine identifier,
+//! so reinstalling the app does not create a new anonymous user.
+
+/// A name-based UUID (v5) of the salt and the machine ID, the machine ID itself is never sent. \
+/// Returns `None` if the machine ID can't be read.
+pub(crate) fn derive_anonymous_id(salt: &str) -> Option<String> {
+    match machine_uid::get() {
+        Ok(machine_id) => {
+            let name = format!("{salt}:{}", machine_id.trim());
+            Some(uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, name.as_bytes()).to_string())
+        }
+        Err(err) => {
+            tracing::warn!(
+                "Failed to read the machine id, using a random anonymous id: {:?}",
+                err
+            );
+            None
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::Poll,
+};
+
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+/// The memory budget used by [RudderStackBuilder::low_memory_mode](crate::RudderStackBuilder::low_memory_mode).
+pub(crate) const LOW_MEMORY_BUDGET: usize = 256 * 1024;
+
+/// The number of recent events kept by [RudderStackBuilder::low_memory_mode](crate::RudderStackBuilder::low_memory_mode).
+pub(crate) const LOW_MEMORY_RECENT_EVENTS: usize = 16;
+
+/// The maximum number of pending deliveries with [RudderStackBuilder::low_memory_mode](crate::RudderStackBuilder::low_memory_mode).
+pub(crate) const LOW_MEMORY_PENDING_SENDS: usize = 64;
+
+/// The number of connected user IDs kept by [RudderStackBuilder::low_memory_mode](crate::RudderStackBuilder::low_memory_mode).
+pub(crate) const LOW_MEMORY_CONNECTED_IDS: usize = 10;
+
+/// A global memory budget shared by the in-memory plugin structures: the held deliveries,
+/// the recent events, the sent message IDs, the connected user IDs and the mock recorder.
+///
+/// Structures reserve the approximate size of every item they hold,
+/// and evict their oldest items when a reservation fails.
+#[derive(Debug)]
+pub(crate) struct MemoryBudget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// An unlimited budget, which still keeps track of the used memory.
+    pub fn unlimited() -> Self {
+        Self::new(usize::MAX)
+    }
+
+    /// Reserve `bytes`, returns false if that would exceed the budget.
+    pub fn try_reserve(&self, bytes: usize) -> bool {
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(bytes).filter(|total| *total <= self.limit)
+            })
+            .is_ok()
+    }
+
+    /// Release `bytes` that were reserved before.
+    pub fn release(&self, bytes: usize) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+
+    /// The number of bytes currently reserved.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Acquire)
+    }
+
+    /// The limit of the budget in bytes.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+/// The approximate memory used by a value, based on its serialized size.
+pub(crate) fn estimate_size<T: Serialize>(value: &T) -> usize {
+    std::mem::size_of::<T>() + serde_json::to_vec(value).map(|v| v.len()).unwrap_or(0)
+}
+
+/// The bytes a structure holds in a [MemoryBudget], released when it is dropped. \
+/// Nothing is accounted without a budget. Clones are not accounted either,
+/// e.g. the copy of the config that is saved.
+#[derive(Debug, Default)]
+pub(crate) struct BudgetShare {
+    budget: Option<Arc<MemoryBudget>>,
+    reserved: usize,
+}
+
+impl BudgetShare {
+    pub fn new(budget: Arc<MemoryBudget>) -> Self {
+        Self {
+            budget: Some(budget),
+            reserved: 0,
+        }
+    }
+
+    /// Reserve `bytes`, returns false if that would exceed the budget.
+    pub fn try_reserve(&mut self, bytes: usize) -> bool {
+        if let Some(budget) = &self.budget {
+            if !budget.try_reserve(bytes) {
+                return false;
+            }
+            self.reserved += bytes;
+        }
+        true
+    }
+
+    /// Release `bytes` that were reserved before.
+    pub fn release(&mut self, bytes: usize) {
+        if let Some(budget) = &self.budget {
+            let bytes = bytes.min(self.reserved);
+            budget.release(bytes);
+            self.reserved -= bytes;
+        }
+    }
+}
+
+impl Clone for BudgetShare {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Drop for BudgetShare {
+    fn drop(&mut self) {
+        self.release(self.reserved);
+    }
+}
+
+/// The deliveries waiting in a [MemoryBudget], e.g. for a slot of the send queue
+/// or while the deliveries are paused. The oldest are evicted when a new one does not fit.
+#[derive(Debug)]
+pub(crate) struct HeldDeliveries {
+    budget: Arc<MemoryBudget>,
+    held: Mutex<Held>,
+}
+
+#[derive(Debug, Default)]
+struct Held {
+    next_id: u64,
+    /// Oldest first.
+    deliveries: VecDeque<HeldEntry>,
+}
+
+#[derive(Debug)]
+struct HeldEntry {
+    id: u64,
+    size: usize,
+    evict: oneshot::Sender<()>,
+}
+
+impl HeldDeliveries {
+    pub fn new(budget: Arc<MemoryBudget>) -> Self {
+        Self {
+            budget,
+            held: Mutex::default(),
+        }
+    }
+
+    /// Hold a delivery of `size` bytes, evicting the oldest held deliveries until it fits. \
+    /// Returns `None` if it does not fit, e.g. when the other structures use the budget.
+    pub fn hold(self: &Arc<Self>, size: usize) -> Option<HeldDelivery> {
+        if size > self.budget.limit() {
+            return None;
+        }
+        let mut held = self.held.lock().unwrap();
+        while !self.budget.try_reserve(size) {
+            let oldest = held.deliveries.pop_front()?;
+            self.budget.release(oldest.size);
+            let _ = oldest.evict.send(());
+        }
+        let (evict, evicted) = oneshot::channel();
+        let id = held.next_id;
+        held.next_id += 1;
+        held.deliveries.push_back(HeldEntry { id, size, evict });
+        Some(HeldDelivery {
+            deliveries: self.clone(),
+            id,
+            evicted: Some(evicted),
+        })
+    }
+
+    /// Release the memory of the delivery, returns false if it was evicted.
+    fn release(&self, id: u64) -> bool {
+        let mut held = self.held.lock().unwrap();
+        let Some(index) = held.deliveries.iter().position(|entry| entry.id == id) else {
+            return false;
+        };
+        if let Some(entry) = held.deliveries.remove(index) {
+            self.budget.release(entry.size);
+        }
+        true
+    }
+}
+
+/// A delivery held in the memory budget until it starts, see [HeldDeliveries].
+#[derive(Debug)]
+pub(crate) struct HeldDelivery {
+    deliveries: Arc<HeldDeliveries>,
+    id: u64,
+    evicted: Option<oneshot::Receiver<()>>,
+}
+
+impl HeldDelivery {
+    /// Wait for `future` unless the delivery is evicted first, returns `None` if it was.
+    pub async fn wait<F: Future>(&mut self, future: F) -> Option<F::Output> {
+        let mut future = std::pin::pin!(future);
+        std::future::poll_fn(|cx| {
+            if let Some(evicted) = &mut self.evicted {
+                match Pin::new(evicted).poll(cx) {
+                    Poll::Ready(Ok(())) => return Poll::Ready(None),
+                    // released, it can't be evicted anymore
+                    Poll::Ready(Err(_)) => self.evicted = None,
+                    Poll::Pending => {}
+                }
+            }
+            future.as_mut().poll(cx).map(Some)
+        })
+        .await
+    }
+
+    /// Release the memory when the delivery starts, returns false if it was evicted.
+    pub fn start(self) -> bool {
+        self.deliveries.release(self.id)
+    }
+}
+
+impl Drop for HeldDelivery {
+    fn drop(&mut self) {
+        self.deliveries.release(self.id);
+    }
+}
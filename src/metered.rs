@@ -0,0 +1,210 @@
+//! Delivery on metered connections, see [RudderStackBuilder::metered_policy](crate::RudderStackBuilder::metered_policy).
+
+use std::{
+    io,
+    process::{Command, Output},
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use tauri::Runtime;
+
+use crate::{delivery_gate::PauseReason, instance::Instance};
+
+/// How often the connection is checked by default.
+pub(crate) const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The longest interval between checks while the OS does not tell.
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How events are delivered while the connection is metered, e.g. when tethering to a phone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MeteredPolicy {
+    /// Deliver events as usual.
+    #[default]
+    Deliver,
+    /// Hold the events and deliver them together every interval,
+    /// so the connection is woken up less often.
+    Batch(Duration),
+    /// Hold the events until an unmetered connection is available.
+    Pause,
+}
+
+/// Stops the connection checks of [start], e.g. when the app exits.
+#[derive(Debug, Default)]
+pub(crate) struct MeteredChecks {
+    stopped: Mutex<bool>,
+    stop: Condvar,
+}
+
+impl MeteredChecks {
+    /// Stop the checks, the checking thread exits.
+    pub fn stop(&self) {
+        *self.stopped.lock().unwrap() = true;
+        self.stop.notify_all();
+    }
+
+    /// Sleep for `duration`, returns false if the checks were stopped.
+    fn sleep(&self, duration: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap();
+        let (stopped, _) = self
+            .stop
+            .wait_timeout_while(stopped, duration, |stopped| !*stopped)
+            .unwrap();
+        !*stopped
+    }
+}
+
+/// Returns true if the current connection is metered, `None` if the OS does not tell,
+/// or an error if the check failed, e.g. when PowerShell is blocked by the execution policy. \
+/// Uses NetworkManager on Linux and the connection cost on Windows.
+pub(crate) fn is_metered() -> io::Result<Option<bool>> {
+    #[cfg(target_os = "linux")]
+    {
+        // NMMetered: 1 yes, 2 no, 3 guessed yes, 4 guessed no
+        let output = run(Command::new("busctl").args([
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ]))?;
+        Ok(match String::from_utf8_lossy(&output.stdout).trim() {
+            "u 1" | "u 3" => Some(true),
+            "u 2" | "u 4" => Some(false),
+            _ => None,
+        })
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt as _;
+
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        let output = run(Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]::GetInternetConnectionProfile().GetConnectionCost().NetworkCostType",
+            ])
+            .creation_flags(CREATE_NO_WINDOW))?;
+        Ok(match String::from_utf8_lossy(&output.stdout).trim() {
+            "Fixed" | "Variable" => Some(true),
+            "Unrestricted" => Some(false),
+            _ => None,
+        })
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        Ok(None)
+    }
+}
+
+/// The output of a successful command, an error if it could not run or failed.
+#[cfg_attr(not(any(target_os = "linux", windows)), allow(dead_code))]
+fn run(command: &mut Command) -> io::Result<Output> {
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(output)
+}
+
+/// Check the connection every `check_interval` and hold the deliveries under the policy,
+/// until the checks are stopped when the app exits.
+/// Deliveries continue if the OS does not tell whether the connection is metered or the check
+/// fails, and the interval doubles until it succeeds, so unsupported systems are not polled.
+pub(crate) fn start<R: Runtime>(instance: Instance<R>, check_interval: Duration) {
+    let policy = instance.rudder().metered_policy();
+    if policy == MeteredPolicy::Deliver {
+        return;
+    }
+    let check_interval = check_interval.max(Duration::from_secs(1));
+    let gate = instance.rudder().delivery_gate();
+    let checks = instance.rudder().metered_checks();
+    std::thread::spawn(move || {
+        let mut interval = check_interval;
+        let mut next_check = Instant::now();
+        let mut last_release = Instant::now();
+        loop {
+            if Instant::now() >= next_check {
+                match is_metered() {
+                    Ok(Some(true)) => {
+                        if !gate.is_paused(PauseReason::Metered) {
+                            tracing::debug!("connection is metered, holding deliveries");
+                            last_release = Instant::now();
+                        }
+                        gate.pause(PauseReason::Metered);
+                        interval = check_interval;
+                    }
+                    Ok(Some(false)) => {
+                        gate.resume(PauseReason::Metered);
+                        interval = check_interval;
+                    }
+                    Ok(None) => {
+                        gate.resume(PauseReason::Metered);
+                        interval = next_interval(interval, check_interval);
+                    }
+                    Err(err) => {
+                        tracing::debug!(
+                            ?interval,
+                            "Failed to check for a metered connection: {:?}",
+                            err
+                        );
+                        gate.resume(PauseReason::Metered);
+                        interval = next_interval(interval, check_interval);
+                    }
+                }
+                next_check = Instant::now() + interval;
+            }
+            let tick = match policy {
+                MeteredPolicy::Batch(batch) if gate.is_paused(PauseReason::Metered) => {
+                    if last_release.elapsed() >= batch {
+                        gate.release();
+                        last_release = Instant::now();
+                    }
+                    batch.clamp(Duration::from_secs(1), check_interval)
+                }
+                _ => next_check.saturating_duration_since(Instant::now()),
+            };
+            if !checks.sleep(tick) {
+                return;
+            }
+        }
+    });
+}
+
+/// The interval after a check where the OS did not tell whether the connection is metered,
+/// or that failed.
+fn next_interval(interval: Duration, check_interval: Duration) -> Duration {
+    (interval * 2).min(MAX_CHECK_INTERVAL.max(check_interval))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_up_to_an_hour() {
+        let interval = next_interval(DEFAULT_CHECK_INTERVAL, DEFAULT_CHECK_INTERVAL);
+        assert_eq!(interval, Duration::from_secs(120));
+        let interval = (0..10).fold(interval, |interval, _| {
+            next_interval(interval, DEFAULT_CHECK_INTERVAL)
+        });
+        assert_eq!(interval, MAX_CHECK_INTERVAL);
+        let long = Duration::from_secs(2 * 60 * 60);
+        assert_eq!(next_interval(long, long), long);
+    }
+
+    #[test]
+    fn stops_the_checks() {
+        let checks = MeteredChecks::default();
+        assert!(checks.sleep(Duration::from_millis(1)));
+        checks.stop();
+        let start = Instant::now();
+        assert!(!checks.sleep(Duration::from_secs(60)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
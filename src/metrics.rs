@@ -0,0 +1,87 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// A snapshot of the delivery counters of the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Metrics {
+    /// Events delivered to the data plane.
+    pub sent: u32,
+    /// Events that failed to be delivered.
+    pub failed: u32,
+    /// Events dropped before sending, e.g. by sampling, consent or the event registry.
+    pub dropped: u32,
+    /// Events dropped because their deadline, TTL or maximum age passed before they were sent.
+    pub expired: u32,
+    /// Events dropped because too many deliveries were pending or the memory budget was exceeded.
+    pub overflowed: u32,
+    /// Events currently waiting to be delivered.
+    pub queued: u32,
+}
+
+/// The atomic counters behind [Metrics].
+#[derive(Debug, Default)]
+pub(crate) struct MetricsCounters {
+    sent: AtomicU32,
+    failed: AtomicU32,
+    dropped: AtomicU32,
+    expired: AtomicU32,
+    overflowed: AtomicU32,
+    queued: AtomicU32,
+}
+
+impl MetricsCounters {
+    pub fn dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn expired(&self) {
+        self.expired.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn overflowed(&self) {
+        self.overflowed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a message that was rejected before it was queued.
+    pub fn rejected(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a queued message that expired before it was delivered.
+    pub fn expired_while_queued(&self) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        self.expired();
+    }
+
+    /// Record a queued message that was evicted from the memory budget before it was delivered.
+    pub fn overflowed_while_queued(&self) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        self.overflowed();
+    }
+
+    pub fn queued(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the result of a delivery that was queued before.
+    pub fn delivered<T, E>(&self, result: &Result<T, E>) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        match result {
+            Ok(_) => self.sent.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.failed.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn snapshot(&self) -> Metrics {
+        Metrics {
+            sent: self.sent.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            expired: self.expired.load(Ordering::Relaxed),
+            overflowed: self.overflowed.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+        }
+    }
+}
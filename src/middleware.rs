@@ -0,0 +1,177 @@
+use crate::{
+    transform::{TransformContext, Transformer},
+    types::{BatchMessage, Message},
+};
+
+/// A middleware for events sent from the webview through the plugin commands.
+///
+/// Middlewares run in the order they are added, with the label of the window that sent the event
+/// and the payload as received, before the event registry, transformers and enrichment.
+/// Any `Fn(&str, &mut Message) -> bool` can be used as a middleware.
+///
+/// ```ignore
+/// let middleware = |window: &str, message: &mut Message| window != "untrusted";
+/// ```
+pub trait CommandMiddleware: Send + Sync + 'static {
+    /// Modify the message sent from `window`. Return false to drop it.
+    fn handle(&self, window: &str, message: &mut Message) -> bool;
+}
+
+impl<F> CommandMiddleware for F
+where
+    F: Fn(&str, &mut Message) -> bool + Send + Sync + 'static,
+{
+    fn handle(&self, window: &str, message: &mut Message) -> bool {
+        self(window, message)
+    }
+}
+
+/// The case event names are converted to by an [EventNameNormalizer].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventNameCase {
+    /// Only trim and collapse the whitespace.
+    Unchanged,
+    /// `App Opened`, the naming of the RudderStack event spec.
+    TitleCase,
+    /// `app_opened`.
+    SnakeCase,
+}
+
+/// A built-in [CommandMiddleware] that normalizes the names of track, page and screen events,
+/// so `appOpened`, `App Opened` and `app_opened` end up as one event in the warehouse.
+///
+/// Names are trimmed and their whitespace is collapsed, then they are converted to the
+/// [EventNameCase]. Words are split at whitespace, `_`, `-` and camelCase boundaries. \
+/// It is also a [Transformer](crate::Transformer), to normalize the events sent from Rust.
+///
+/// ```ignore
+/// .command_middleware(EventNameNormalizer::new(EventNameCase::TitleCase))
+/// .transformer(EventNameNormalizer::new(EventNameCase::TitleCase))
+/// ```
+#[derive(Debug, Clone)]
+pub struct EventNameNormalizer {
+    case: EventNameCase,
+}
+
+impl EventNameNormalizer {
+    pub fn new(case: EventNameCase) -> Self {
+        Self { case }
+    }
+
+    /// The normalized event name.
+    pub fn normalize(&self, name: &str) -> String {
+        match self.case {
+            EventNameCase::Unchanged => name.split_whitespace().collect::<Vec<_>>().join(" "),
+            EventNameCase::TitleCase => words(name)
+                .iter()
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or_else(String::new, |first| {
+                        first
+                            .to_uppercase()
+                            .chain(chars.flat_map(char::to_lowercase))
+                            .collect()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            EventNameCase::SnakeCase => words(name)
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+        }
+    }
+
+    fn normalize_message(&self, message: &mut Message) {
+        let name = match message {
+            Message::Track(track) => &mut track.event,
+            Message::Page(page) => &mut page.name,
+            Message::Screen(screen) => &mut screen.name,
+            Message::Batch(batch) => {
+                for message in &mut batch.batch {
+                    let name = match message {
+                        BatchMessage::Track(track) => &mut track.event,
+                        BatchMessage::Page(page) => &mut page.name,
+                        BatchMessage::Screen(screen) => &mut screen.name,
+                        _ => continue,
+                    };
+                    *name = self.normalize(name);
+                }
+                return;
+            }
+            _ => return,
+        };
+        *name = self.normalize(name);
+    }
+}
+
+impl CommandMiddleware for EventNameNormalizer {
+    fn handle(&self, _window: &str, message: &mut Message) -> bool {
+        self.normalize_message(message);
+        true
+    }
+}
+
+impl Transformer for EventNameNormalizer {
+    fn transform(&self, message: &mut Message, _context: &TransformContext) -> bool {
+        self.normalize_message(message);
+        true
+    }
+}
+
+/// The words of an event name, split at whitespace, `_`, `-` and camelCase boundaries,
+/// e.g. `URLOpened` is split into `URL` and `Opened`.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in name.split(|c: char| c.is_whitespace() || c == '_' || c == '-') {
+        let chars: Vec<char> = part.chars().collect();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            let previous = i.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(i + 1);
+            // `aB`, `1B` and the `B` of `ABc`
+            let boundary = c.is_uppercase()
+                && previous.is_some_and(|previous| {
+                    previous.is_lowercase()
+                        || previous.is_numeric()
+                        || (previous.is_uppercase() && next.is_some_and(|next| next.is_lowercase()))
+                });
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.push(c);
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_event_names() {
+        let title_case = EventNameNormalizer::new(EventNameCase::TitleCase);
+        for name in [
+            "appOpened",
+            "App Opened",
+            "app_opened",
+            "  app   opened ",
+            "APP-OPENED",
+        ] {
+            assert_eq!(title_case.normalize(name), "App Opened");
+        }
+        assert_eq!(title_case.normalize("URLOpened"), "Url Opened");
+
+        let snake_case = EventNameNormalizer::new(EventNameCase::SnakeCase);
+        assert_eq!(snake_case.normalize("App Opened"), "app_opened");
+        assert_eq!(snake_case.normalize("fileSaved2Disk"), "file_saved2_disk");
+
+        let unchanged = EventNameNormalizer::new(EventNameCase::Unchanged);
+        assert_eq!(unchanged.normalize("  appOpened \t now "), "appOpened now");
+    }
+}
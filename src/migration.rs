@@ -0,0 +1,157 @@
+//! Import the anonymous ID of the analytics plugin the app used before,
+//! so switching vendors keeps the continuity of unique-user metrics.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tauri::{AppHandle, Manager as _, Runtime};
+use tracing::{debug, warn};
+
+/// Where a previous analytics plugin stored the anonymous ID of the user.
+#[derive(Debug, Clone)]
+pub struct IdentitySource {
+    path: PathBuf,
+    pointer: Option<String>,
+}
+
+impl IdentitySource {
+    /// A file relative to the app data dir that contains the anonymous ID as plain text.
+    pub fn text_file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            pointer: None,
+        }
+    }
+
+    /// A JSON file relative to the app data dir, with the anonymous ID at the
+    /// [JSON pointer](https://datatracker.ietf.org/doc/html/rfc6901) `pointer`, e.g. `/distinct_id`.
+    pub fn json_file(path: impl Into<PathBuf>, pointer: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            pointer: Some(pointer.into()),
+        }
+    }
+
+    /// The identity file of the Aptabase plugin, `aptabase.json` with the ID at `/user_id`.
+    pub fn aptabase() -> Self {
+        Self::json_file("aptabase.json", "/user_id")
+    }
+
+    /// The identity file of the PostHog plugin, `posthog.json` with the ID at `/distinct_id`.
+    pub fn posthog() -> Self {
+        Self::json_file("posthog.json", "/distinct_id")
+    }
+
+    /// Read the anonymous ID from the app data dir, see [Self::read_from].
+    fn read<R: Runtime>(&self, handle: &AppHandle<R>) -> Option<String> {
+        self.read_from(&handle.path().app_data_dir().ok()?)
+    }
+
+    /// Read the anonymous ID from the file relative to `dir`,
+    /// returns `None` if the file is missing or has no ID.
+    fn read_from(&self, dir: &Path) -> Option<String> {
+        let path = dir.join(&self.path);
+        let contents = std::fs::read_to_string(&path).ok()?;
+        let id = match &self.pointer {
+            None => Some(contents.trim().to_string()),
+            Some(pointer) => match serde_json::from_str::<Value>(&contents) {
+                Ok(json) => json.pointer(pointer).and_then(|id| match id {
+                    Value::String(id) => Some(id.clone()),
+                    Value::Number(id) => Some(id.to_string()),
+                    _ => None,
+                }),
+                Err(err) => {
+                    warn!("Failed to parse identity file {:?}: {:?}", path, err);
+                    None
+                }
+            },
+        };
+        id.filter(|id| !id.is_empty())
+    }
+}
+
+/// The anonymous ID of the first source that has one.
+pub(crate) fn import_anonymous_id<R: Runtime>(
+    handle: &AppHandle<R>,
+    sources: &[IdentitySource],
+) -> Option<String> {
+    sources.iter().find_map(|source| {
+        let id = source.read(handle)?;
+        debug!("imported anonymous id from {:?}", source.path);
+        Some(id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// A copy of the fixtures of the other plugins, removed when dropped.
+    struct AppDataDir(PathBuf);
+
+    impl AppDataDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "rudderstack-migration-{name}-{}",
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(
+                dir.join("aptabase.json"),
+                include_str!("../fixtures/migration/aptabase.json"),
+            )
+            .unwrap();
+            fs::write(
+                dir.join("posthog.json"),
+                include_str!("../fixtures/migration/posthog.json"),
+            )
+            .unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for AppDataDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn reads_the_aptabase_layout() {
+        let dir = AppDataDir::new("aptabase");
+        assert_eq!(
+            IdentitySource::aptabase().read_from(&dir.0).as_deref(),
+            Some("2f0c7e52-9b1e-4d3a-8c61-5a7e0b9d4f13")
+        );
+    }
+
+    #[test]
+    fn reads_the_posthog_layout() {
+        let dir = AppDataDir::new("posthog");
+        assert_eq!(
+            IdentitySource::posthog().read_from(&dir.0).as_deref(),
+            Some("018f3c2a-7d4e-7b21-9c5f-3e8a1d6b0c47")
+        );
+    }
+
+    #[test]
+    fn skips_missing_and_empty_ids() {
+        let dir = AppDataDir::new("missing");
+        fs::write(dir.0.join("empty.txt"), "\n").unwrap();
+        assert_eq!(
+            IdentitySource::text_file("empty.txt").read_from(&dir.0),
+            None
+        );
+        assert_eq!(
+            IdentitySource::text_file("missing.txt").read_from(&dir.0),
+            None
+        );
+        assert_eq!(
+            IdentitySource::json_file("posthog.json", "/missing").read_from(&dir.0),
+            None
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use rudderanalytics::message::{BatchMessage, Message};
+
+use crate::{
+    memory::{estimate_size, BudgetShare, MemoryBudget},
+    rudderanalytics,
+};
+
+/// Records outgoing messages instead of sending them to the data plane.
+#[derive(Default)]
+pub(crate) struct MockRecorder {
+    recorded: Mutex<Recorded>,
+}
+
+/// The recorded messages and their size, oldest first.
+#[derive(Default)]
+struct Recorded {
+    messages: VecDeque<(Message, usize)>,
+    budget: BudgetShare,
+}
+
+impl Recorded {
+    /// Evict the oldest message, returns false if there was nothing to evict.
+    fn evict(&mut self) -> bool {
+        match self.messages.pop_front() {
+            Some((_, size)) => {
+                self.budget.release(size);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl MockRecorder {
+    /// Account the recorded messages in the memory budget.
+    pub fn set_budget(&mut self, budget: Arc<MemoryBudget>) {
+        let recorded = self.recorded.get_mut().unwrap();
+        let messages = std::mem::take(&mut recorded.messages);
+        recorded.budget = BudgetShare::new(budget);
+        messages
+            .into_iter()
+            .for_each(|(message, _)| self.record(message));
+    }
+
+    /// Record a message, evicting the oldest messages if the memory budget is exceeded.
+    pub fn record(&self, message: Message) {
+        let size = estimate_size(&message);
+        let mut recorded = self.recorded.lock().unwrap();
+        while !recorded.budget.try_reserve(size) {
+            if !recorded.evict() {
+                tracing::debug!("memory budget exceeded, not recording message");
+                return;
+            }
+        }
+        recorded.messages.push_back((message, size));
+    }
+
+    pub fn recorded(&self) -> Vec<Message> {
+        let recorded = self.recorded.lock().unwrap();
+        recorded
+            .messages
+            .iter()
+            .map(|(message, _)| message.clone())
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        let mut recorded = self.recorded.lock().unwrap();
+        while recorded.evict() {}
+    }
+
+    /// Returns true if a track event with this name was recorded, including inside batches.
+    pub fn tracked(&self, event: &str) -> bool {
+        self.recorded
+            .lock()
+            .unwrap()
+            .messages
+            .iter()
+            .any(|(message, _)| match message {
+                Message::Track(track) => track.event == event,
+                Message::Batch(batch) => batch.batch.iter().any(
+                    |message| matches!(message, BatchMessage::Track(track) if track.event == event),
+                ),
+                _ => false,
+            })
+    }
+}
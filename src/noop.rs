@@ -0,0 +1,290 @@
+//! A stand-in for the `rudderanalytics` crate with the `noop` feature, nothing is sent.
+//!
+//! The message types match the crate, so the plugin compiles the same code path
+//! without the dependency.
+
+pub mod message {
+    //! The messages of the RudderStack API.
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+
+    /// An enum containing all values which may be sent to RudderStack's API.
+    #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+    #[serde(untagged)]
+    pub enum Message {
+        Identify(Identify),
+        Track(Track),
+        Page(Page),
+        Screen(Screen),
+        Group(Group),
+        Alias(Alias),
+        Batch(Batch),
+    }
+
+    /// An identify event.
+    #[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default)]
+    pub struct Identify {
+        /// The user id associated with this message.
+        #[serde(rename = "userId", skip_serializing_if = "Option::is_none")]
+        pub user_id: Option<String>,
+
+        /// The anonymous user id associated with this message.
+        #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+        pub anonymous_id: Option<String>,
+
+        /// The traits to assign to the user.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub traits: Option<Value>,
+
+        /// The timestamp associated with this message.
+        #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
+        pub original_timestamp: Option<DateTime<Utc>>,
+
+        /// Context associated with this message.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub context: Option<Value>,
+
+        /// Integrations to route this message to.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub integrations: Option<Value>,
+    }
+
+    /// A track event.
+    #[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default)]
+    pub struct Track {
+        /// The user id associated with this message.
+        #[serde(rename = "userId", skip_serializing_if = "Option::is_none")]
+        pub user_id: Option<String>,
+
+        /// The anonymous user id associated with this message.
+        #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+        pub anonymous_id: Option<String>,
+
+        /// The name of the event being tracked.
+        pub event: String,
+
+        /// The properties associated with the event.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub properties: Option<Value>,
+
+        /// The timestamp associated with this message.
+        #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
+        pub original_timestamp: Option<DateTime<Utc>>,
+
+        /// Context associated with this message.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub context: Option<Value>,
+
+        /// Integrations to route this message to.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub integrations: Option<Value>,
+    }
+
+    /// A page event.
+    #[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default)]
+    pub struct Page {
+        /// The user id associated with this message.
+        #[serde(rename = "userId", skip_serializing_if = "Option::is_none")]
+        pub user_id: Option<String>,
+
+        /// The anonymous user id associated with this message.
+        #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+        pub anonymous_id: Option<String>,
+
+        /// The name of the page being tracked.
+        pub name: String,
+
+        /// The properties associated with the event.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub properties: Option<Value>,
+
+        /// The timestamp associated with this message.
+        #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
+        pub original_timestamp: Option<DateTime<Utc>>,
+
+        /// Context associated with this message.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub context: Option<Value>,
+
+        /// Integrations to route this message to.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub integrations: Option<Value>,
+    }
+
+    /// A screen event.
+    #[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default)]
+    pub struct Screen {
+        /// The user id associated with this message.
+        #[serde(rename = "userId", skip_serializing_if = "Option::is_none")]
+        pub user_id: Option<String>,
+
+        /// The anonymous user id associated with this message.
+        #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+        pub anonymous_id: Option<String>,
+
+        /// The name of the screen being tracked.
+        pub name: String,
+
+        /// The properties associated with the event.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub properties: Option<Value>,
+
+        /// The timestamp associated with this message.
+        #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
+        pub original_timestamp: Option<DateTime<Utc>>,
+
+        /// Context associated with this message.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub context: Option<Value>,
+
+        /// Integrations to route this message to.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub integrations: Option<Value>,
+    }
+
+    /// A group event.
+    #[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default)]
+    pub struct Group {
+        /// The user id associated with this message.
+        #[serde(rename = "userId", skip_serializing_if = "Option::is_none")]
+        pub user_id: Option<String>,
+
+        /// The anonymous user id associated with this message.
+        #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+        pub anonymous_id: Option<String>,
+
+        /// The group the user is being associated with.
+        #[serde(rename = "groupId")]
+        pub group_id: String,
+
+        /// The traits to assign to the group.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub traits: Option<Value>,
+
+        /// The timestamp associated with this message.
+        #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
+        pub original_timestamp: Option<DateTime<Utc>>,
+
+        /// Context associated with this message.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub context: Option<Value>,
+
+        /// Integrations to route this message to.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub integrations: Option<Value>,
+    }
+
+    /// An alias event.
+    #[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default)]
+    pub struct Alias {
+        /// The user id associated with this message.
+        #[serde(rename = "userId")]
+        pub user_id: String,
+
+        /// The user's previous ID.
+        #[serde(rename = "previousId")]
+        pub previous_id: String,
+
+        /// The traits to assign to the alias.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub traits: Option<Value>,
+
+        /// The timestamp associated with this message.
+        #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
+        pub original_timestamp: Option<DateTime<Utc>>,
+
+        /// Context associated with this message.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub context: Option<Value>,
+
+        /// Integrations to route this message to.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub integrations: Option<Value>,
+    }
+
+    /// A batch of events.
+    #[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default)]
+    pub struct Batch {
+        /// The batch of messages to send.
+        pub batch: Vec<BatchMessage>,
+
+        /// Context associated with this message.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub context: Option<Value>,
+
+        /// Integrations to route this message to.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub integrations: Option<Value>,
+
+        /// The timestamp associated with this message.
+        #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
+        pub original_timestamp: Option<DateTime<Utc>>,
+    }
+
+    /// An enum containing all messages which may be placed inside a batch.
+    #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+    #[serde(tag = "type")]
+    pub enum BatchMessage {
+        #[serde(rename = "identify")]
+        Identify(Identify),
+        #[serde(rename = "track")]
+        Track(Track),
+        #[serde(rename = "page")]
+        Page(Page),
+        #[serde(rename = "screen")]
+        Screen(Screen),
+        #[serde(rename = "group")]
+        Group(Group),
+        #[serde(rename = "alias")]
+        Alias(Alias),
+    }
+}
+
+pub mod errors {
+    //! Errors of a delivery.
+
+    /// An enum of errors a delivery may produce.
+    #[derive(Debug, thiserror::Error)]
+    pub enum Error {
+        /// The given message is too large to be sent to RudderStack's API.
+        #[error("message too large")]
+        MessageTooLarge(String),
+
+        #[error("Invalid request")]
+        InvalidRequest(String),
+
+        #[error("Error sending request")]
+        SendRequestError(#[from] RequestError),
+    }
+
+    /// A stand-in for `reqwest::Error`, no requests are made with the `noop` feature.
+    #[derive(Debug, thiserror::Error)]
+    #[error("no requests are made with the noop feature")]
+    pub struct RequestError(());
+
+    impl RequestError {
+        pub fn is_connect(&self) -> bool {
+            false
+        }
+
+        pub fn is_timeout(&self) -> bool {
+            false
+        }
+
+        pub fn status(&self) -> Option<StatusCode> {
+            None
+        }
+    }
+
+    /// A stand-in for `reqwest::StatusCode`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct StatusCode(u16);
+
+    impl StatusCode {
+        pub fn as_u16(&self) -> u16 {
+            self.0
+        }
+    }
+}
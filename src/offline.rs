@@ -0,0 +1,50 @@
+//! Pausing deliveries while offline, see [RudderStackBuilder::monitor_connectivity](crate::RudderStackBuilder::monitor_connectivity).
+
+use std::{sync::Arc, time::Duration};
+
+use rudderanalytics::errors::Error;
+
+use crate::{
+    delivery_gate::{DeliveryGate, PauseReason},
+    rudderanalytics,
+    transport::Transport,
+};
+
+/// Returns true if the data plane could not be connected to, e.g. without network access.
+pub(crate) fn is_connection_error(err: &Error) -> bool {
+    matches!(err, Error::SendRequestError(err) if err.is_connect())
+}
+
+/// Pause the deliveries after a connection error, and probe the data plane every `interval`
+/// until it can be connected to. The waiting deliveries are sent right away when it is back.
+pub(crate) fn pause_deliveries(
+    gate: Arc<DeliveryGate>,
+    transport: Arc<dyn Transport>,
+    interval: Duration,
+) {
+    if !gate.pause(PauseReason::Offline) {
+        // already probing
+        return;
+    }
+    tracing::debug!("data plane is unreachable, holding deliveries");
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let transport = transport.clone();
+            let reachable = tauri::async_runtime::spawn_blocking(move || {
+                // transports without a health check are assumed to be reachable
+                match transport.health_check() {
+                    Ok(()) => true,
+                    Err(err) => !is_connection_error(&err),
+                }
+            })
+            .await
+            .unwrap_or(true);
+            if reachable {
+                tracing::debug!("data plane is reachable again, flushing deliveries");
+                gate.resume(PauseReason::Offline);
+                return;
+            }
+        }
+    });
+}
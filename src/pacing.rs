@@ -0,0 +1,142 @@
+//! Adaptive pacing of deliveries, shared by all deliveries.
+//!
+//! Throttling responses (429 and 503) double the delay between sends,
+//! and every successful delivery halves it again.
+//! Sends resume after the `Retry-After` header of the response, if the transport reports it.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::transport::DeliveryError;
+
+/// The delay after the first throttling response.
+const MIN_DELAY: Duration = Duration::from_millis(250);
+/// The maximum delay between sends.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct PacerState {
+    /// The delay between sends, zero when the data plane is not throttling.
+    delay: Duration,
+    /// The earliest time the next send may start.
+    next_send: Instant,
+}
+
+#[derive(Debug)]
+pub(crate) struct Pacer {
+    state: Mutex<PacerState>,
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(PacerState {
+                delay: Duration::ZERO,
+                next_send: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl Pacer {
+    /// Wait until the next send may start.
+    pub async fn wait(&self) {
+        let wait_until = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            let wait_until = state.next_send.max(now);
+            state.next_send = wait_until + state.delay;
+            wait_until
+        };
+        let now = Instant::now();
+        if wait_until > now {
+            tokio::time::sleep(wait_until - now).await;
+        }
+    }
+
+    /// Returns true if the data plane is currently throttling.
+    pub fn is_throttled(&self) -> bool {
+        !self.state.lock().unwrap().delay.is_zero()
+    }
+
+    /// Record the result of a send and adapt the delay.
+    pub fn record<T>(&self, result: &Result<T, DeliveryError>) {
+        match result {
+            Err(err) if err.is_throttled() => self.throttled(err.retry_after),
+            Ok(_) => self.succeeded(),
+            Err(_) => {}
+        }
+    }
+
+    /// Back off after a throttling response. \
+    /// `retry_after` is honored if known, otherwise the delay is doubled.
+    pub fn throttled(&self, retry_after: Option<Duration>) {
+        let mut state = self.state.lock().unwrap();
+        state.delay = (state.delay * 2).clamp(MIN_DELAY, MAX_DELAY);
+        let resume = Instant::now() + retry_after.unwrap_or(state.delay).min(MAX_DELAY);
+        state.next_send = state.next_send.max(resume);
+        tracing::warn!(delay = ?state.delay, ?retry_after, "data plane is throttling, slowing down");
+    }
+
+    fn succeeded(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.delay /= 2;
+        if state.delay < MIN_DELAY {
+            state.delay = Duration::ZERO;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttled(retry_after: Option<u64>) -> Result<(), DeliveryError> {
+        Err(DeliveryError::with_status(
+            429,
+            retry_after.map(Duration::from_secs),
+        ))
+    }
+
+    fn delay(pacer: &Pacer) -> Duration {
+        pacer.state.lock().unwrap().delay
+    }
+
+    #[test]
+    fn doubles_the_delay_when_throttled_and_halves_it_on_success() {
+        let pacer = Pacer::default();
+        pacer.record(&throttled(None));
+        assert_eq!(delay(&pacer), MIN_DELAY);
+        pacer.record(&throttled(None));
+        assert_eq!(delay(&pacer), MIN_DELAY * 2);
+        assert!(pacer.is_throttled());
+
+        pacer.record::<()>(&Err(DeliveryError::with_status(500, None)));
+        assert_eq!(delay(&pacer), MIN_DELAY * 2);
+
+        pacer.record(&Ok(()));
+        assert_eq!(delay(&pacer), MIN_DELAY);
+        pacer.record(&Ok(()));
+        assert!(!pacer.is_throttled());
+    }
+
+    #[test]
+    fn caps_the_delay() {
+        let pacer = Pacer::default();
+        for _ in 0..20 {
+            pacer.record(&throttled(None));
+        }
+        assert_eq!(delay(&pacer), MAX_DELAY);
+    }
+
+    #[test]
+    fn resumes_after_retry_after() {
+        let pacer = Pacer::default();
+        pacer.record(&throttled(Some(30)));
+        let next_send = pacer.state.lock().unwrap().next_send;
+        assert!(next_send >= Instant::now() + Duration::from_secs(29));
+        assert_eq!(delay(&pacer), MIN_DELAY);
+    }
+}
@@ -0,0 +1,38 @@
+// Reports the page loads and history changes of the webview to the rudderstack plugin.
+(function () {
+  if (window.__RUDDERSTACK_PAGE_TRACKING__ || !window.__TAURI_INTERNALS__) return;
+  window.__RUDDERSTACK_PAGE_TRACKING__ = true;
+
+  var lastUrl = null;
+  function track() {
+    if (window.location.href === lastUrl) return;
+    lastUrl = window.location.href;
+    window.__TAURI_INTERNALS__
+      .invoke("plugin:rudderstack|track_page_view", {
+        url: window.location.href,
+        title: document.title || null,
+      })
+      .catch(function () {});
+  }
+  // wait for the router to update the title
+  function trackLater() {
+    setTimeout(track, 0);
+  }
+
+  ["pushState", "replaceState"].forEach(function (name) {
+    var original = history[name];
+    history[name] = function () {
+      var result = original.apply(this, arguments);
+      trackLater();
+      return result;
+    };
+  });
+  window.addEventListener("popstate", trackLater);
+  window.addEventListener("hashchange", trackLater);
+
+  if (document.readyState === "loading") {
+    document.addEventListener("DOMContentLoaded", track);
+  } else {
+    track();
+  }
+})();
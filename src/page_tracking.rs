@@ -0,0 +1,47 @@
+//! Automatic page tracking of the webviews, see [RudderStackBuilder::track_pages](crate::RudderStackBuilder::track_pages).
+
+use crate::types::Page;
+
+/// Reports the page loads and history changes of the webview to the `track_page_view` command.
+pub(crate) const INIT_SCRIPT: &str = include_str!("page_tracking.js");
+
+pub(crate) struct PageTracking {
+    /// Excluded paths, a pattern ending with `*` excludes all paths with this prefix.
+    excluded: Vec<String>,
+}
+
+impl PageTracking {
+    pub fn new(excluded: Vec<String>) -> Self {
+        Self { excluded }
+    }
+
+    fn is_excluded(&self, path: &str) -> bool {
+        self.excluded
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => path.starts_with(prefix),
+                None => path == pattern,
+            })
+    }
+
+    /// The Page event for a navigation of the webview to `url`, `None` if the path is excluded. \
+    /// Like `watchURLChanges` of the guest bindings the page is named after the path.
+    pub fn page(&self, url: &str, title: Option<String>) -> Option<Page> {
+        let path = match tauri::Url::parse(url) {
+            Ok(parsed) => parsed.path().to_string(),
+            Err(_) => url.to_string(),
+        };
+        if self.is_excluded(&path) {
+            return None;
+        }
+        Some(Page {
+            name: path.clone(),
+            properties: Some(serde_json::json!({
+                "url": url,
+                "path": path,
+                "title": title,
+            })),
+            ..Page::default()
+        })
+    }
+}
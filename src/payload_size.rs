@@ -0,0 +1,135 @@
+//! The size limits of the RudderStack API, checked before a message is queued.
+
+use rudderanalytics::message::Message;
+use serde_json::Value;
+
+use crate::rudderanalytics;
+
+/// The maximum size of a serialized message accepted by the RudderStack API.
+pub(crate) const MAX_MESSAGE_BYTES: usize = 32 * 1024;
+/// The maximum size of a serialized batch accepted by the RudderStack API.
+pub(crate) const MAX_BATCH_BYTES: usize = 512 * 1024;
+
+/// Truncates string properties and traits so a message fits the size limit.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PayloadLimit {
+    /// The property and trait keys that may be truncated, in the order they are truncated.
+    truncatable: Vec<String>,
+}
+
+impl PayloadLimit {
+    pub fn truncate_properties(&mut self, keys: impl IntoIterator<Item = String>) {
+        self.truncatable.extend(keys);
+    }
+
+    /// Truncate the configured properties until the message fits the size limit. \
+    /// Returns the size of the message if it is still too large.
+    pub fn fit(&self, message: &mut Message) -> Result<(), usize> {
+        let max = max_bytes(message);
+        let mut size = serialized_size(message);
+        for key in &self.truncatable {
+            while size > max {
+                let Some(Value::String(value)) =
+                    properties_mut(message).and_then(|p| p.get_mut(key))
+                else {
+                    break;
+                };
+                if value.is_empty() {
+                    break;
+                }
+                let mut len = value.len().saturating_sub(size - max);
+                while !value.is_char_boundary(len) {
+                    len -= 1;
+                }
+                tracing::debug!(key, size, "message is too large, truncating property");
+                value.truncate(len);
+                size = serialized_size(message);
+            }
+        }
+        if size > max {
+            Err(size)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The size limit of the message.
+pub(crate) fn max_bytes(message: &Message) -> usize {
+    match message {
+        Message::Batch(_) => MAX_BATCH_BYTES,
+        _ => MAX_MESSAGE_BYTES,
+    }
+}
+
+/// The size of the payload the default transport sends for the message,
+/// including the metadata it adds, e.g. `context.library`, `messageId` and `sentAt`.
+fn serialized_size(message: &Message) -> usize {
+    #[cfg(not(feature = "noop"))]
+    if let Some(size) = crate::http_transport::payload_size(message) {
+        return size;
+    }
+    serde_json::to_vec(message).map_or(0, |json| json.len())
+}
+
+/// The properties or traits of the message, batches are not truncated.
+fn properties_mut(message: &mut Message) -> Option<&mut serde_json::Map<String, Value>> {
+    let properties = match message {
+        Message::Identify(identify) => &mut identify.traits,
+        Message::Track(track) => &mut track.properties,
+        Message::Page(page) => &mut page.properties,
+        Message::Screen(screen) => &mut screen.properties,
+        Message::Group(group) => &mut group.traits,
+        Message::Alias(alias) => &mut alias.traits,
+        Message::Batch(_) => return None,
+    };
+    properties.as_mut()?.as_object_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use rudderanalytics::message::Track;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn truncates_configured_properties() {
+        let track = |stack_trace: String| {
+            Message::Track(Track {
+                event: "Error".to_string(),
+                properties: Some(json!({ "stack_trace": stack_trace, "code": 42 })),
+                ..Track::default()
+            })
+        };
+        let mut limit = PayloadLimit::default();
+        let mut message = track("é".repeat(MAX_MESSAGE_BYTES));
+        let size = limit.fit(&mut message).unwrap_err();
+        assert!(size > MAX_MESSAGE_BYTES);
+
+        limit.truncate_properties(["stack_trace".to_string()]);
+        assert_eq!(limit.fit(&mut message), Ok(()));
+        assert!(serialized_size(&message) <= MAX_MESSAGE_BYTES);
+
+        let mut small = track("short".to_string());
+        limit.fit(&mut small).unwrap();
+        assert_eq!(small, track("short".to_string()));
+    }
+
+    #[test]
+    #[cfg(not(feature = "noop"))]
+    fn measures_the_sent_payload() {
+        let track = |padding: usize| {
+            Message::Track(Track {
+                event: "Error".to_string(),
+                anonymous_id: Some("anonymous-id".to_string()),
+                properties: Some(json!({ "stack_trace": "a".repeat(padding) })),
+                ..Track::default()
+            })
+        };
+        let bare = serde_json::to_vec(&track(0)).unwrap().len();
+        let mut message = track(MAX_MESSAGE_BYTES - bare - 10);
+        assert!(serde_json::to_vec(&message).unwrap().len() <= MAX_MESSAGE_BYTES);
+        assert!(PayloadLimit::default().fit(&mut message).is_err());
+    }
+}
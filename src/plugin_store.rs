@@ -0,0 +1,48 @@
+use std::{path::PathBuf, sync::Arc};
+
+use serde_json::Value;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::{Store, StoreExt as _};
+
+use crate::storage::{ConfigStore, StoreError};
+
+/// A [ConfigStore] that keeps the config under a key of a `tauri-plugin-store` store,
+/// so apps using that plugin get a single storage location and its change events.
+pub(crate) struct PluginStore<R: Runtime> {
+    app: AppHandle<R>,
+    path: PathBuf,
+    key: String,
+}
+
+impl<R: Runtime> PluginStore<R> {
+    /// `rudderstack` for the default instance, `rudderstack-<name>` for named ones.
+    pub fn new(app: AppHandle<R>, path: PathBuf, instance: Option<&str>) -> Self {
+        let key = match instance {
+            Some(name) => format!("rudderstack-{name}"),
+            None => "rudderstack".to_string(),
+        };
+        Self { app, path, key }
+    }
+
+    fn store(&self) -> Result<Arc<Store<R>>, StoreError> {
+        Ok(self.app.store(&self.path)?)
+    }
+}
+
+impl<R: Runtime> ConfigStore for PluginStore<R> {
+    fn load(&self) -> Result<Option<Value>, StoreError> {
+        Ok(self.store()?.get(&self.key))
+    }
+
+    fn save(&self, config: &Value) -> Result<(), StoreError> {
+        let store = self.store()?;
+        store.set(self.key.clone(), config.clone());
+        Ok(store.save()?)
+    }
+
+    fn delete(&self) -> Result<(), StoreError> {
+        let store = self.store()?;
+        store.delete(&self.key);
+        Ok(store.save()?)
+    }
+}
@@ -0,0 +1,188 @@
+//! Delivery priorities, identity and crash events are delivered first
+//! and low priority telemetry is shed first.
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::rudderanalytics;
+
+/// The priority class of a message.
+///
+/// Higher priorities are delivered first when deliveries queue up. `Debug` events are dropped
+/// while the data plane is throttling, and before other events when the
+/// [pending sends](crate::RudderStackBuilder::max_pending_sends) fill up.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
+    specta::Type,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Priority {
+    /// Low priority telemetry, e.g. diagnostics.
+    Debug,
+    /// Most events.
+    #[default]
+    Normal,
+    /// Events that should not be lost, e.g. crash reports and purchases.
+    Critical,
+    /// Identify and alias calls, so the events that follow are attributed to the user.
+    Identity,
+}
+
+impl Priority {
+    /// The default priority of a message, [Priority::Identity] for identify and alias calls.
+    pub(crate) fn of(message: &rudderanalytics::message::Message) -> Self {
+        use rudderanalytics::message::Message;
+        match message {
+            Message::Identify(_) | Message::Alias(_) => Priority::Identity,
+            _ => Priority::Normal,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Waiter {
+    priority: Priority,
+    /// Earlier deliveries of the same priority go first.
+    order: u64,
+    ready: oneshot::Sender<()>,
+}
+
+impl Waiter {
+    fn key(&self) -> (Priority, Reverse<u64>) {
+        (self.priority, Reverse(self.order))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+#[derive(Debug)]
+struct QueueState {
+    available: usize,
+    next_order: u64,
+    waiting: BinaryHeap<Waiter>,
+}
+
+/// Limits the simultaneous deliveries, handing free slots to the highest priority first.
+#[derive(Debug)]
+pub(crate) struct SendQueue {
+    state: Mutex<QueueState>,
+}
+
+impl SendQueue {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                available: max_concurrent.max(1),
+                next_order: 0,
+                waiting: BinaryHeap::new(),
+            }),
+        }
+    }
+
+    /// Wait for a delivery slot, released when the permit is dropped.
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> SendPermit {
+        let ready = {
+            let mut state = self.state.lock().unwrap();
+            if state.available > 0 {
+                state.available -= 1;
+                return SendPermit(self.clone());
+            }
+            let (sender, receiver) = oneshot::channel();
+            let order = state.next_order;
+            state.next_order += 1;
+            state.waiting.push(Waiter {
+                priority,
+                order,
+                ready: sender,
+            });
+            receiver
+        };
+        // the sender is only dropped after handing over the slot
+        let _ = ready.await;
+        SendPermit(self.clone())
+    }
+}
+
+/// A delivery slot of a [SendQueue].
+#[derive(Debug)]
+pub(crate) struct SendPermit(Arc<SendQueue>);
+
+impl Drop for SendPermit {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        while let Some(waiter) = state.waiting.pop() {
+            // the delivery may have been cancelled
+            if waiter.ready.send(()).is_ok() {
+                return;
+            }
+        }
+        state.available += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hands_slots_to_the_highest_priority_first() {
+        tauri::async_runtime::block_on(async {
+            let queue = Arc::new(SendQueue::new(1));
+            let permit = queue.acquire(Priority::Normal).await;
+            let order = Arc::new(Mutex::new(Vec::new()));
+            let mut tasks = Vec::new();
+            for priority in [Priority::Debug, Priority::Normal, Priority::Identity] {
+                let (waiting, order) = (queue.clone(), order.clone());
+                tasks.push(tauri::async_runtime::spawn(async move {
+                    let _permit = waiting.acquire(priority).await;
+                    order.lock().unwrap().push(priority);
+                }));
+                while queue.state.lock().unwrap().waiting.len() < tasks.len() {
+                    tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                }
+            }
+            drop(permit);
+            for task in tasks {
+                task.await.unwrap();
+            }
+            assert_eq!(
+                *order.lock().unwrap(),
+                [Priority::Identity, Priority::Normal, Priority::Debug]
+            );
+        });
+    }
+}
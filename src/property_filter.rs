@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+
+use rudderanalytics::message::{BatchMessage, Message};
+use serde_json::Value;
+
+use crate::rudderanalytics;
+
+/// Strips top-level keys from the properties, traits and context of every outgoing message.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PropertyFilter {
+    /// Property and trait keys that are removed.
+    denied_properties: HashSet<String>,
+    /// If set, only these property and trait keys are kept.
+    allowed_properties: Option<HashSet<String>>,
+    /// Context keys that are removed.
+    denied_context: HashSet<String>,
+}
+
+impl PropertyFilter {
+    pub fn deny_properties(&mut self, keys: impl IntoIterator<Item = String>) {
+        self.denied_properties.extend(keys);
+    }
+
+    pub fn allow_properties(&mut self, keys: impl IntoIterator<Item = String>) {
+        self.allowed_properties
+            .get_or_insert_with(HashSet::new)
+            .extend(keys);
+    }
+
+    pub fn deny_context(&mut self, keys: impl IntoIterator<Item = String>) {
+        self.denied_context.extend(keys);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.denied_properties.is_empty()
+            && self.allowed_properties.is_none()
+            && self.denied_context.is_empty()
+    }
+
+    /// Remove the filtered keys from the message.
+    pub fn apply(&self, message: &mut Message) {
+        match message {
+            Message::Identify(identify) => {
+                self.apply_fields(&mut identify.traits, &mut identify.context)
+            }
+            Message::Track(track) => self.apply_fields(&mut track.properties, &mut track.context),
+            Message::Page(page) => self.apply_fields(&mut page.properties, &mut page.context),
+            Message::Screen(screen) => {
+                self.apply_fields(&mut screen.properties, &mut screen.context)
+            }
+            Message::Group(group) => self.apply_fields(&mut group.traits, &mut group.context),
+            Message::Alias(alias) => self.apply_fields(&mut alias.traits, &mut alias.context),
+            Message::Batch(batch) => {
+                strip(&mut batch.context, |key| !self.denied_context.contains(key));
+                for message in &mut batch.batch {
+                    self.apply_batch_message(message);
+                }
+            }
+        }
+    }
+
+    fn apply_batch_message(&self, message: &mut BatchMessage) {
+        match message {
+            BatchMessage::Identify(identify) => {
+                self.apply_fields(&mut identify.traits, &mut identify.context)
+            }
+            BatchMessage::Track(track) => {
+                self.apply_fields(&mut track.properties, &mut track.context)
+            }
+            BatchMessage::Page(page) => self.apply_fields(&mut page.properties, &mut page.context),
+            BatchMessage::Screen(screen) => {
+                self.apply_fields(&mut screen.properties, &mut screen.context)
+            }
+            BatchMessage::Group(group) => self.apply_fields(&mut group.traits, &mut group.context),
+            BatchMessage::Alias(alias) => self.apply_fields(&mut alias.traits, &mut alias.context),
+        }
+    }
+
+    fn apply_fields(&self, properties: &mut Option<Value>, context: &mut Option<Value>) {
+        strip(properties, |key| {
+            !self.denied_properties.contains(key)
+                && self
+                    .allowed_properties
+                    .as_ref()
+                    .is_none_or(|allowed| allowed.contains(key))
+        });
+        strip(context, |key| !self.denied_context.contains(key));
+    }
+}
+
+/// Remove the top-level keys of an object that should not be kept.
+fn strip(value: &mut Option<Value>, keep: impl Fn(&str) -> bool) {
+    if let Some(Value::Object(object)) = value {
+        object.retain(|key, _| keep(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rudderanalytics::message::{Batch, Track};
+    use serde_json::json;
+
+    use super::*;
+
+    fn track(properties: Value) -> Track {
+        Track {
+            event: "Signed In".to_string(),
+            properties: Some(properties),
+            context: Some(json!({ "ip": "127.0.0.1", "locale": "en-US" })),
+            ..Track::default()
+        }
+    }
+
+    fn keys(strings: &[&str]) -> Vec<String> {
+        strings.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn denied_keys_take_precedence_over_allowed_keys() {
+        let mut filter = PropertyFilter::default();
+        filter.allow_properties(keys(&["plan", "email"]));
+        filter.deny_properties(keys(&["email"]));
+        filter.deny_context(keys(&["ip"]));
+
+        let mut message = Message::Track(track(json!({
+            "plan": "pro",
+            "email": "user@example.com",
+            "referrer": "mail",
+        })));
+        filter.apply(&mut message);
+        let Message::Track(track) = message else {
+            unreachable!();
+        };
+        assert_eq!(track.properties, Some(json!({ "plan": "pro" })));
+        assert_eq!(track.context, Some(json!({ "locale": "en-US" })));
+    }
+
+    #[test]
+    fn filters_top_level_keys_only() {
+        let mut filter = PropertyFilter::default();
+        filter.deny_properties(keys(&["email"]));
+
+        let properties = json!({
+            "email": "user@example.com",
+            "user": { "email": "user@example.com", "plan": "pro" },
+        });
+        let mut message = Message::Batch(Batch {
+            batch: vec![BatchMessage::Track(track(properties))],
+            context: Some(json!({ "ip": "127.0.0.1" })),
+            ..Batch::default()
+        });
+        filter.deny_context(keys(&["ip"]));
+        filter.apply(&mut message);
+        let Message::Batch(batch) = message else {
+            unreachable!();
+        };
+        assert_eq!(batch.context, Some(json!({})));
+        let [BatchMessage::Track(track)] = batch.batch.as_slice() else {
+            panic!("expected one track event, got {:?}", batch.batch);
+        };
+        // nested objects are kept as they are
+        assert_eq!(
+            track.properties,
+            Some(json!({ "user": { "email": "user@example.com", "plan": "pro" } }))
+        );
+        assert_eq!(track.context, Some(json!({ "locale": "en-US" })));
+    }
+}
@@ -0,0 +1,397 @@
+use std::{collections::HashMap, path::Path};
+
+use chrono::{DateTime, Duration, Utc};
+use semver::Version;
+use tracing::{error, warn};
+
+use crate::{
+    rudderanalytics::message as rudder,
+    types::{BatchMessage, Message},
+};
+
+/// A registered [Track](crate::types::Track) event.
+///
+/// Registered events can be limited to a range of app versions, so stale call sites
+/// can't reintroduce retired events into the warehouse.
+#[derive(Debug, Clone)]
+pub struct EventDefinition {
+    name: String,
+    min_version: Option<Version>,
+    max_version: Option<Version>,
+    ttl: Option<Duration>,
+}
+
+impl EventDefinition {
+    /// Create a new event definition with the name of the event.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            min_version: None,
+            max_version: None,
+            ttl: None,
+        }
+    }
+
+    /// The first app version (inclusive) that may send this event.
+    pub fn min_version(mut self, version: &str) -> Self {
+        self.min_version = parse_version(&self.name, version);
+        self
+    }
+
+    /// The last app version (inclusive) that may send this event.
+    /// Use this to retire deprecated events.
+    pub fn max_version(mut self, version: &str) -> Self {
+        self.max_version = parse_version(&self.name, version);
+        self
+    }
+
+    /// Drop the event instead of sending it once it is older than `ttl`,
+    /// counted from its `originalTimestamp`.
+    pub fn ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl = Duration::from_std(ttl).ok();
+        self
+    }
+
+    /// The name of the event.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns why the event may not be sent from this app version, if it may not.
+    fn check(&self, version: &Version) -> Result<(), Unsupported> {
+        if self.min_version.as_ref().is_some_and(|min| version < min) {
+            Err(Unsupported::NotReleased)
+        } else if self.max_version.as_ref().is_some_and(|max| version > max) {
+            Err(Unsupported::Retired)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Why a registered event is dropped in the current app version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unsupported {
+    /// The app version is older than the `min_version` of the event.
+    NotReleased,
+    /// The app version is newer than the `max_version` of the event.
+    Retired,
+}
+
+fn parse_version(event: &str, version: &str) -> Option<Version> {
+    match Version::parse(version) {
+        Ok(version) => Some(version),
+        Err(err) => {
+            error!(
+                "Invalid version {:?} for event {:?}: {:?}",
+                version, event, err
+            );
+            None
+        }
+    }
+}
+
+/// Maps old event names to their new names, so call sites that were not updated,
+/// e.g. in cached frontend bundles, keep producing the canonical names.
+///
+/// Track events are renamed before the event registry, tracking plan and transformers run.
+/// Names are mapped once, renames are not chained.
+///
+/// ```ignore
+/// let renames = EventRenames::new()
+///     .rename("signup", "Signed Up")
+///     .rename("login", "Signed In");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EventRenames {
+    names: HashMap<String, String>,
+}
+
+impl EventRenames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send events named `old` as `new`.
+    pub fn rename(mut self, old: impl Into<String>, new: impl Into<String>) -> Self {
+        self.names.insert(old.into(), new.into());
+        self
+    }
+
+    /// Create the renames from a json object that maps old names to new names.
+    pub fn from_json(json: &str) -> Result<Self, EventRenamesError> {
+        Ok(Self {
+            names: serde_json::from_str(json)?,
+        })
+    }
+
+    /// Load the renames from a json file at runtime. See [EventRenames::from_json].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EventRenamesError> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    pub(crate) fn extend(&mut self, renames: EventRenames) {
+        self.names.extend(renames.names);
+    }
+
+    /// The new name of the event, or the name itself if it was not renamed.
+    fn canonical<'a>(&'a self, event: &'a str) -> &'a str {
+        self.names.get(event).map_or(event, String::as_str)
+    }
+
+    fn rename_event(&self, event: &mut String) {
+        if let Some(new) = self.names.get(event.as_str()) {
+            tracing::trace!(old = %event, new = %new, "renaming event");
+            *event = new.clone();
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EventRenamesError {
+    #[error("failed to read event renames {0}")]
+    Read(#[from] std::io::Error),
+    #[error("failed to parse event renames {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// The registry of known events, checked before events are sent.
+pub(crate) struct EventRegistry {
+    events: HashMap<String, EventDefinition>,
+    renames: EventRenames,
+    app_version: Version,
+    /// If true, only registered events may be sent from the webview.
+    restricted: bool,
+}
+
+impl EventRegistry {
+    pub fn new(
+        events: Vec<EventDefinition>,
+        renames: EventRenames,
+        app_version: Version,
+        restricted: bool,
+    ) -> Self {
+        Self {
+            events: events
+                .into_iter()
+                .map(|event| (event.name.clone(), event))
+                .collect(),
+            renames,
+            app_version,
+            restricted,
+        }
+    }
+
+    /// Returns true if the event may be sent from the webview, by its new name if it was renamed.
+    pub fn is_allowed(&self, event: &str) -> bool {
+        !self.restricted || self.events.contains_key(self.renames.canonical(event))
+    }
+
+    /// Rename the track events of the message, see [EventRenames].
+    pub fn rename(&self, message: &mut Message) {
+        match message {
+            Message::Track(track) => self.renames.rename_event(&mut track.event),
+            Message::Batch(batch) => {
+                for message in &mut batch.batch {
+                    if let BatchMessage::Track(track) = message {
+                        self.renames.rename_event(&mut track.event);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns true if the event has outlived the TTL of its definition.
+    pub fn is_expired(&self, event: &str, timestamp: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.events
+            .get(event)
+            .and_then(|definition| definition.ttl)
+            .is_some_and(|ttl| now - timestamp > ttl)
+    }
+
+    /// Remove the track events that outlived the TTL of their definition from the message,
+    /// also from a batch. Batched events without an `originalTimestamp` use the one of the batch. \
+    /// Returns true if the whole message expired.
+    pub fn drop_expired(&self, message: &mut rudder::Message, now: DateTime<Utc>) -> bool {
+        let expired = |track: &rudder::Track, fallback: Option<DateTime<Utc>>| {
+            track
+                .original_timestamp
+                .or(fallback)
+                .is_some_and(|timestamp| self.is_expired(&track.event, timestamp, now))
+        };
+        match message {
+            rudder::Message::Track(track) => expired(track, None),
+            rudder::Message::Batch(batch) => {
+                let fallback = batch.original_timestamp;
+                batch.batch.retain(|message| match message {
+                    rudder::BatchMessage::Track(track) if expired(track, fallback) => {
+                        tracing::debug!(event = %track.event, "batched event outlived its TTL, removing it");
+                        false
+                    }
+                    _ => true,
+                });
+                batch.batch.is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true if the message may be sent from the current app version. \
+    /// The track events of a batch that are not supported are removed from it,
+    /// the batch is dropped if none is left.
+    pub fn allow(&self, message: &mut Message) -> bool {
+        match message {
+            Message::Track(track) => self.supports(&track.event).is_ok(),
+            Message::Batch(batch) => {
+                batch.batch.retain(|message| match message {
+                    BatchMessage::Track(track) => self.supports(&track.event).is_ok(),
+                    _ => true,
+                });
+                !batch.batch.is_empty()
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns why the event may not be sent from the current app version, warns if it may not.
+    fn supports(&self, event: &str) -> Result<(), Unsupported> {
+        let Some(definition) = self.events.get(event) else {
+            return Ok(());
+        };
+
+        let result = definition.check(&self.app_version);
+        if let Err(reason) = result {
+            warn!(
+                event,
+                app_version = %self.app_version,
+                ?reason,
+                "event is not supported in this app version, dropping it"
+            );
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Batch, Page, Track};
+
+    fn track(event: &str) -> Track {
+        Track {
+            event: event.to_string(),
+            ..Track::default()
+        }
+    }
+
+    fn registry(app_version: &str) -> EventRegistry {
+        EventRegistry::new(
+            vec![
+                EventDefinition::new("Legacy Export").max_version("2.3.0"),
+                EventDefinition::new("New Export").min_version("2.4.0"),
+            ],
+            EventRenames::default(),
+            Version::parse(app_version).unwrap(),
+            false,
+        )
+    }
+
+    #[test]
+    fn gates_events_by_app_version() {
+        let old = registry("2.3.0");
+        assert!(old.allow(&mut Message::Track(track("Legacy Export"))));
+        assert!(!old.allow(&mut Message::Track(track("New Export"))));
+        assert!(old.allow(&mut Message::Track(track("Unregistered"))));
+
+        let new = registry("2.4.0");
+        assert!(!new.allow(&mut Message::Track(track("Legacy Export"))));
+        assert!(new.allow(&mut Message::Track(track("New Export"))));
+    }
+
+    #[test]
+    fn removes_unsupported_events_from_batches() {
+        let registry = registry("2.4.0");
+        let mut message = Message::Batch(Batch {
+            batch: vec![
+                BatchMessage::Track(track("Legacy Export")),
+                BatchMessage::Track(track("New Export")),
+                BatchMessage::Page(Page::default()),
+            ],
+            ..Batch::default()
+        });
+        assert!(registry.allow(&mut message));
+        let Message::Batch(batch) = message else {
+            panic!("expected a batch");
+        };
+        assert_eq!(
+            batch.batch,
+            vec![
+                BatchMessage::Track(track("New Export")),
+                BatchMessage::Page(Page::default()),
+            ]
+        );
+
+        let mut message = Message::Batch(Batch {
+            batch: vec![BatchMessage::Track(track("Legacy Export"))],
+            ..Batch::default()
+        });
+        assert!(!registry.allow(&mut message));
+    }
+
+    #[test]
+    fn reports_why_events_are_dropped() {
+        let old = registry("2.3.0");
+        assert_eq!(old.supports("Legacy Export"), Ok(()));
+        assert_eq!(old.supports("New Export"), Err(Unsupported::NotReleased));
+
+        let new = registry("2.4.0");
+        assert_eq!(new.supports("Legacy Export"), Err(Unsupported::Retired));
+        assert_eq!(new.supports("Unregistered"), Ok(()));
+    }
+
+    #[test]
+    fn drops_expired_events_from_batches() {
+        let registry = EventRegistry::new(
+            vec![EventDefinition::new("Promo Viewed").ttl(std::time::Duration::from_secs(60))],
+            EventRenames::default(),
+            Version::new(1, 0, 0),
+            false,
+        );
+        let now = Utc::now();
+        let rudder_track = |event: &str| rudder::Track {
+            event: event.to_string(),
+            ..rudder::Track::default()
+        };
+        let mut message = rudder::Message::Batch(rudder::Batch {
+            batch: vec![
+                rudder::BatchMessage::Track(rudder_track("Promo Viewed")),
+                rudder::BatchMessage::Track(rudder_track("Signed In")),
+            ],
+            original_timestamp: Some(now - Duration::minutes(2)),
+            ..rudder::Batch::default()
+        });
+        assert!(!registry.drop_expired(&mut message, now));
+        let rudder::Message::Batch(batch) = &message else {
+            panic!("expected a batch");
+        };
+        assert_eq!(
+            batch.batch,
+            vec![rudder::BatchMessage::Track(rudder_track("Signed In"))]
+        );
+
+        let mut message = rudder::Message::Track(rudder::Track {
+            original_timestamp: Some(now - Duration::seconds(30)),
+            ..rudder_track("Promo Viewed")
+        });
+        assert!(!registry.drop_expired(&mut message, now));
+        assert!(registry.drop_expired(&mut message, now + Duration::minutes(1)));
+    }
+
+    #[test]
+    fn ignores_invalid_versions() {
+        let definition = EventDefinition::new("Legacy Export").max_version("not a version");
+        assert_eq!(definition.check(&Version::new(9, 0, 0)), Ok(()));
+    }
+}
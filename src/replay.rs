@@ -0,0 +1,106 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    memory::{estimate_size, MemoryBudget},
+    types::Message,
+};
+
+/// A captured event that differs when replayed against the current rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayDiff {
+    /// The event as it was passed to the plugin.
+    pub input: Message,
+    /// The event after the rules at capture time, `None` if it was dropped.
+    pub before: Option<Message>,
+    /// The event after the current rules, `None` if it would be dropped.
+    pub after: Option<Message>,
+}
+
+/// A captured event and the result of the rules at capture time.
+struct CapturedEvent {
+    input: Message,
+    output: Option<Message>,
+    /// The bytes reserved in the memory budget.
+    size: usize,
+}
+
+/// A ring buffer of the most recent events, used to replay real traffic against new rules.
+pub(crate) struct RecentEvents {
+    capacity: usize,
+    events: Mutex<VecDeque<CapturedEvent>>,
+    budget: Arc<MemoryBudget>,
+}
+
+impl RecentEvents {
+    pub fn new(capacity: usize, budget: Arc<MemoryBudget>) -> Self {
+        Self {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+            budget,
+        }
+    }
+
+    /// Capture an event, evicting the oldest events if the capacity or memory budget is exceeded.
+    pub fn capture(&self, input: Message, output: Option<Message>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let size = estimate_size(&input) + output.as_ref().map(estimate_size).unwrap_or(0);
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            self.evict(&mut events);
+        }
+        while !self.budget.try_reserve(size) {
+            if !self.evict(&mut events) {
+                tracing::debug!("memory budget exceeded, not capturing event");
+                return;
+            }
+        }
+        events.push_back(CapturedEvent {
+            input,
+            output,
+            size,
+        });
+    }
+
+    /// Drop all captured events.
+    pub fn clear(&self) {
+        let mut events = self.events.lock().unwrap();
+        while self.evict(&mut events) {}
+    }
+
+    /// Evict the oldest event, returns false if there was nothing to evict.
+    fn evict(&self, events: &mut VecDeque<CapturedEvent>) -> bool {
+        match events.pop_front() {
+            Some(event) => {
+                self.budget.release(event.size);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-run the last `count` events through `rules` and return the events whose result changed.
+    pub fn replay<F>(&self, count: usize, rules: F) -> Vec<ReplayDiff>
+    where
+        F: Fn(&mut Message) -> bool,
+    {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .skip(events.len().saturating_sub(count))
+            .filter_map(|event| {
+                let mut message = event.input.clone();
+                let after = rules(&mut message).then_some(message);
+                (after != event.output).then(|| ReplayDiff {
+                    input: event.input.clone(),
+                    before: event.output.clone(),
+                    after,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,12 +1,55 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
 
-use rudderanalytics::client::RudderAnalytics;
 use tauri::Runtime;
 
-use crate::config::{self, Config};
+use crate::{
+    active_window::ActiveWindow,
+    audit::{AuditChange, AuditEntry},
+    autosave::Autosave,
+    backpressure::{Backpressure, PendingSends},
+    circuit_breaker::{Admission, CircuitBreaker},
+    config::{self, Config, ConfigFile},
+    consent::{ConsentError, ConsentPolicy, ConsentState},
+    context_merge::ContextMerge,
+    context_provider::ContextProvider,
+    data::{AnalyticsData, WipeError},
+    dedup::SentMessageIds,
+    delivery_gate::DeliveryGate,
+    event_log::{EventLog, EventLogFormat, LoggedEvent},
+    events::{event_name, message_timestamp},
+    heartbeat::Heartbeat,
+    id_generator::{IdGenerator, UuidV4},
+    idle::IdleTracker,
+    integrity::{AnalyticsStatus, IdentityReset},
+    memory::{estimate_size, HeldDeliveries, MemoryBudget},
+    metered::{MeteredChecks, MeteredPolicy},
+    metrics::{Metrics, MetricsCounters},
+    middleware::CommandMiddleware,
+    mock::MockRecorder,
+    offline,
+    pacing::Pacer,
+    page_tracking::PageTracking,
+    payload_size::{self, PayloadLimit},
+    priority::{Priority, SendQueue},
+    property_filter::PropertyFilter,
+    registry::EventRegistry,
+    replay::{RecentEvents, ReplayDiff},
+    rudderanalytics,
+    sampling::Sampler,
+    send_result::{DropReason, SendError, SendResult},
+    transform::{SendDecision, TransformContext, Transformer},
+    transport::{Connectivity, DataPlaneError, DeliveryError, HttpOptions, Transport},
+};
 
 /// merge two json values
-fn merge(a: &mut serde_json::Value, b: &serde_json::Value) {
+pub(crate) fn merge(a: &mut serde_json::Value, b: &serde_json::Value) {
     match (a, b) {
         (serde_json::Value::Object(a), serde_json::Value::Object(b)) => {
             for (k, v) in b {
@@ -17,32 +60,548 @@ fn merge(a: &mut serde_json::Value, b: &serde_json::Value) {
     }
 }
 
+/// The global context, with the value shared by the messages cached until it changes.
+#[derive(Debug)]
+struct GlobalContext {
+    context: crate::types::Context,
+    shared: Option<Arc<serde_json::Value>>,
+}
+
+impl GlobalContext {
+    fn new(context: crate::types::Context) -> Self {
+        Self {
+            context,
+            shared: None,
+        }
+    }
+
+    /// The context shared by the messages, built once after every change.
+    fn shared(&mut self) -> Arc<serde_json::Value> {
+        let context = &self.context;
+        self.shared
+            .get_or_insert_with(|| Arc::new(serde_json::Value::Object(context.clone())))
+            .clone()
+    }
+
+    /// The context to change, the shared value is built again for the next message.
+    fn changed(&mut self) -> &mut crate::types::Context {
+        self.shared = None;
+        &mut self.context
+    }
+}
+
+/// Returns true if a message created at `created_at` is older than `max_age`.
+fn is_older_than(
+    created_at: chrono::DateTime<chrono::Utc>,
+    max_age: Option<chrono::Duration>,
+) -> bool {
+    max_age.is_some_and(|max_age| chrono::Utc::now() - created_at > max_age)
+}
+
+/// A message that passed [RudderWrapper::should_send].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Admitted {
+    pub priority: Priority,
+    /// Forced events bypass sampling, throttling and backpressure, see [Sampler].
+    pub forced: bool,
+}
+
+/// With the `noop` feature every message is dropped before it is processed.
+const DISABLED: bool = cfg!(feature = "noop");
+
+/// The default maximum number of simultaneous deliveries.
+pub(crate) const DEFAULT_MAX_CONCURRENT_SENDS: usize = 4;
+
+/// Called with the message ID, the event name and the result of every delivery.
+pub(crate) type DeliveryListener =
+    Arc<dyn Fn(&str, &str, &Result<(), DeliveryError>) + Send + Sync>;
+
+/// Called with the message ID, the event name and the reason when a message is dropped
+/// because it expired or too many deliveries are pending.
+pub(crate) type DropListener = Arc<dyn Fn(&str, &str, DropReason) + Send + Sync>;
 
 pub struct RudderWrapper {
-    rudder: Arc<RudderAnalytics>,
+    transport: RwLock<Arc<dyn Transport>>,
+    /// The settings and write key of the default transport, used to switch the data plane.
+    default_transport: Option<(HttpOptions, String)>,
+    /// Where the config of the instance is persisted, its file or a custom store,
+    /// and how it is encrypted.
+    config_file: ConfigFile,
+    identity_reset: Option<IdentityReset>,
+    id_generator: Arc<dyn IdGenerator>,
+    /// Reapplied to the new config when the data is wiped.
+    connected_ids_capacity: usize,
     config: Mutex<config::Config>,
-    context: Mutex<crate::types::Context>,
+    /// Saves the config in the background, e.g. when sequence numbers are reserved.
+    autosave: Arc<Autosave>,
+    sent_message_ids: Arc<Mutex<SentMessageIds>>,
+    /// The global context, shared with the messages being assembled.
+    context: Mutex<GlobalContext>,
+    /// The context of each window by label, merged on top of the global context.
+    window_contexts: Mutex<HashMap<String, crate::types::Context>>,
+    context_providers: Vec<Box<dyn ContextProvider>>,
+    context_merge: ContextMerge,
+    sampler: Sampler,
+    registry: Arc<EventRegistry>,
+    consent_policy: Option<ConsentPolicy>,
+    mock: Option<MockRecorder>,
+    dry_run: bool,
+    do_not_track: bool,
+    strict: bool,
+    /// The destinations mapped to a consent category, checked in strict mode.
+    consent_destinations: HashSet<String>,
+    alias_on_identify: bool,
+    experiments_in_context: bool,
+    active_window: Option<ActiveWindow>,
+    page_tracking: Option<PageTracking>,
+    heartbeat: Option<Heartbeat>,
+    idle_tracker: Option<IdleTracker>,
+    recent_events: Option<RecentEvents>,
+    event_log: Option<Arc<EventLog>>,
+    #[cfg(feature = "local-store")]
+    local_store: Option<crate::local_store::LocalStore>,
+    transformers: Vec<Box<dyn Transformer>>,
+    property_filter: PropertyFilter,
+    payload_limit: PayloadLimit,
+    command_middleware: Vec<Box<dyn CommandMiddleware>>,
+    default_integrations: Option<serde_json::Value>,
+    memory_budget: Arc<MemoryBudget>,
+    /// The queued deliveries in the memory budget, only accounted with a limited budget.
+    held_deliveries: Option<Arc<HeldDeliveries>>,
+    metrics: Arc<MetricsCounters>,
+    pacer: Arc<Pacer>,
+    /// Identifies the events of this launch, with `session_sequence` ordering them.
+    session_id: String,
+    session_sequence: AtomicU64,
+    /// Limits the number of simultaneous deliveries.
+    send_queue: Arc<SendQueue>,
+    /// Limits the number of queued and in-flight deliveries, unlimited if `None`.
+    pending_sends: Option<Arc<PendingSends>>,
+    /// Holds the deliveries while they are paused.
+    delivery_gate: Arc<DeliveryGate>,
+    metered_policy: MeteredPolicy,
+    /// Stops the metered connection checks when the app exits.
+    metered_checks: Arc<MeteredChecks>,
+    /// How often the data plane is probed while it can't be connected to.
+    connectivity_probe: Option<std::time::Duration>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    delivery_listener: Option<DeliveryListener>,
+    drop_listener: Option<DropListener>,
+    /// Messages older than this are dropped instead of delivered.
+    max_event_age: Option<chrono::Duration>,
+    #[cfg(feature = "tracking-plan")]
+    tracking_plan: Option<crate::tracking_plan::TrackingPlan>,
 }
 
 impl RudderWrapper {
     /// Create a new RudderWrapper instance
-    pub fn new(data_plane: String, key: String, config: Config, context: crate::types::Context) -> Self {
-        let rudder = Arc::new(RudderAnalytics::load(key, data_plane));
+    pub(crate) fn new(
+        transport: Arc<dyn Transport>,
+        config: Config,
+        context: crate::types::Context,
+        sampler: Sampler,
+        registry: EventRegistry,
+        consent_policy: Option<ConsentPolicy>,
+    ) -> Self {
+        let memory_budget = Arc::new(MemoryBudget::unlimited());
+        let mut config = config;
+        config.set_connected_ids_budget(memory_budget.clone());
+        let mut sent_message_ids = config.sent_message_ids().clone();
+        sent_message_ids.set_budget(memory_budget.clone());
+        let sent_message_ids = Arc::new(Mutex::new(sent_message_ids));
         Self {
-            rudder,
+            transport: RwLock::new(transport),
+            default_transport: None,
+            config_file: ConfigFile::default(),
+            identity_reset: None,
+            id_generator: Arc::new(UuidV4),
+            connected_ids_capacity: crate::connected_ids::DEFAULT_CAPACITY,
             config: Mutex::new(config),
-            context: Mutex::new(context),
+            autosave: Arc::default(),
+            sent_message_ids,
+            context: Mutex::new(GlobalContext::new(context)),
+            window_contexts: Mutex::default(),
+            context_providers: Vec::new(),
+            context_merge: ContextMerge::default(),
+            sampler,
+            registry: Arc::new(registry),
+            consent_policy,
+            mock: None,
+            dry_run: false,
+            do_not_track: false,
+            strict: false,
+            consent_destinations: HashSet::new(),
+            alias_on_identify: false,
+            experiments_in_context: false,
+            active_window: None,
+            page_tracking: None,
+            heartbeat: None,
+            idle_tracker: None,
+            recent_events: None,
+            event_log: None,
+            #[cfg(feature = "local-store")]
+            local_store: None,
+            transformers: Vec::new(),
+            property_filter: PropertyFilter::default(),
+            payload_limit: PayloadLimit::default(),
+            command_middleware: Vec::new(),
+            default_integrations: None,
+            memory_budget,
+            held_deliveries: None,
+            metrics: Arc::default(),
+            pacer: Arc::default(),
+            session_id: uuid::Uuid::new_v4().to_string(),
+            session_sequence: AtomicU64::new(0),
+            send_queue: Arc::new(SendQueue::new(DEFAULT_MAX_CONCURRENT_SENDS)),
+            pending_sends: None,
+            delivery_gate: Arc::default(),
+            metered_policy: MeteredPolicy::default(),
+            metered_checks: Arc::default(),
+            connectivity_probe: None,
+            circuit_breaker: None,
+            delivery_listener: None,
+            drop_listener: None,
+            max_event_age: None,
+            #[cfg(feature = "tracking-plan")]
+            tracking_plan: None,
         }
     }
 
+    /// Record all outgoing messages in memory instead of sending them to the data plane.
+    pub(crate) fn with_mock(mut self) -> Self {
+        let mut mock = MockRecorder::default();
+        mock.set_budget(self.memory_budget.clone());
+        self.mock = Some(mock);
+        self
+    }
+
+    /// Log the fully assembled messages instead of sending them to the data plane.
+    pub(crate) fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Panic on instrumentation mistakes instead of dropping or flagging the event.
+    /// Events must not enable destinations outside of `consent_destinations`, if there are any.
+    pub(crate) fn with_strict_mode(mut self, consent_destinations: HashSet<String>) -> Self {
+        self.strict = true;
+        self.consent_destinations = consent_destinations;
+        self
+    }
+
+    /// Send an Alias event before the Identify event when a user ID is connected for the first time.
+    pub(crate) fn with_alias_on_identify(mut self) -> Self {
+        self.alias_on_identify = true;
+        self
+    }
+
+    /// Send Page events for the navigations reported by the webviews.
+    pub(crate) fn with_page_tracking(mut self, page_tracking: PageTracking) -> Self {
+        self.page_tracking = Some(page_tracking);
+        self
+    }
+
+    /// Add the variants the user is exposed to to the `experiments` context.
+    pub(crate) fn with_experiments_in_context(mut self) -> Self {
+        self.experiments_in_context = true;
+        self
+    }
+
+    /// Persist the config to this file, e.g. the file of a named instance.
+    pub(crate) fn with_config_file(mut self, config_file: ConfigFile) -> Self {
+        self.config_file = config_file;
+        self
+    }
+
+    /// Generate new anonymous IDs with this generator when the data is wiped.
+    pub(crate) fn with_id_generator(mut self, id_generator: Arc<dyn IdGenerator>) -> Self {
+        self.id_generator = id_generator;
+        self
+    }
+
+    /// Remember at most `capacity` connected user IDs, also after the data is wiped.
+    pub(crate) fn with_connected_ids_capacity(mut self, capacity: usize) -> Self {
+        self.connected_ids_capacity = capacity;
+        self.config
+            .get_mut()
+            .unwrap()
+            .set_connected_ids_capacity(capacity);
+        self
+    }
+
+    /// Remember the settings of the default transport, so the data plane can be changed later.
+    pub(crate) fn with_default_transport(mut self, options: HttpOptions, key: String) -> Self {
+        self.default_transport = Some((options, key));
+        self
+    }
+
+    /// Send all subsequent events to another data plane.
+    /// Events that are already being delivered still go to the previous one.
+    pub(crate) fn set_data_plane(&self, data_plane: String) -> Result<(), DataPlaneError> {
+        let Some((options, key)) = &self.default_transport else {
+            return Err(DataPlaneError::CustomTransport);
+        };
+        let transport = Arc::new(options.transport(data_plane, key.clone()));
+        *self.transport.write().unwrap() = transport;
+        Ok(())
+    }
+
+    /// Add the focused window to the context of every event.
+    pub(crate) fn with_active_window(mut self) -> Self {
+        self.active_window = Some(ActiveWindow::default());
+        self
+    }
+
+    /// Send `App Active` events every `interval` while a window is focused.
+    pub(crate) fn with_heartbeat(mut self, interval: std::time::Duration) -> Self {
+        self.heartbeat = Some(Heartbeat::new(interval));
+        self
+    }
+
+    pub(crate) fn heartbeat(&self) -> Option<&Heartbeat> {
+        self.heartbeat.as_ref()
+    }
+
+    /// Detect when the user is idle for `threshold`.
+    pub(crate) fn with_idle_threshold(mut self, threshold: std::time::Duration) -> Self {
+        self.idle_tracker = Some(IdleTracker::new(threshold));
+        self
+    }
+
+    pub(crate) fn idle_tracker(&self) -> Option<&IdleTracker> {
+        self.idle_tracker.as_ref()
+    }
+
+    /// Returns true if idle detection is enabled and the user is idle.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.idle_tracker.as_ref().is_some_and(IdleTracker::is_idle)
+    }
+
+    /// Record a focus change of a window, if the active window is tracked or the heartbeat is enabled.
+    pub(crate) fn window_focus_changed(&self, label: &str, title: Option<String>, focused: bool) {
+        if let Some(active_window) = &self.active_window {
+            active_window.focus_changed(label, title, focused);
+        }
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.focus_changed(label, focused);
+        }
+    }
+
+    /// Deliver at most `max` messages at the same time, at least one.
+    pub(crate) fn with_max_concurrent_sends(mut self, max: usize) -> Self {
+        self.send_queue = Arc::new(SendQueue::new(max));
+        self
+    }
+
+    /// Apply `backpressure` to new messages while `max` deliveries are queued or in flight.
+    pub(crate) fn with_max_pending_sends(mut self, max: usize, backpressure: Backpressure) -> Self {
+        self.pending_sends = Some(Arc::new(PendingSends::new(max, backpressure)));
+        self
+    }
+
+    /// Hold the deliveries under `policy` while the connection is metered.
+    pub(crate) fn with_metered_policy(mut self, policy: MeteredPolicy) -> Self {
+        self.metered_policy = policy;
+        self
+    }
+
+    pub(crate) fn metered_policy(&self) -> MeteredPolicy {
+        self.metered_policy
+    }
+
+    pub(crate) fn metered_checks(&self) -> Arc<MeteredChecks> {
+        self.metered_checks.clone()
+    }
+
+    /// Hold the deliveries after a connection error until the data plane is reachable,
+    /// probing it every `interval`.
+    pub(crate) fn with_connectivity_monitor(mut self, interval: std::time::Duration) -> Self {
+        self.connectivity_probe = Some(interval);
+        self
+    }
+
+    /// Hold the deliveries for `cool_down` after `threshold` consecutive failures.
+    pub(crate) fn with_circuit_breaker(
+        mut self,
+        threshold: u32,
+        cool_down: std::time::Duration,
+    ) -> Self {
+        self.circuit_breaker = Some(Arc::new(CircuitBreaker::new(
+            threshold,
+            cool_down,
+            self.delivery_gate.clone(),
+        )));
+        self
+    }
+
+    pub(crate) fn delivery_gate(&self) -> Arc<DeliveryGate> {
+        self.delivery_gate.clone()
+    }
+
+    /// Call `listener` with the result of every delivery.
+    pub(crate) fn with_delivery_listener(mut self, listener: DeliveryListener) -> Self {
+        self.delivery_listener = Some(listener);
+        self
+    }
+
+    /// Call `listener` when a message expires or overflows the pending deliveries.
+    pub(crate) fn with_drop_listener(mut self, listener: DropListener) -> Self {
+        self.drop_listener = Some(listener);
+        self
+    }
+
+    /// Drop the messages older than `max_age`, also while they wait to be delivered.
+    pub(crate) fn with_max_event_age(mut self, max_age: std::time::Duration) -> Self {
+        self.max_event_age = chrono::Duration::from_std(max_age).ok();
+        self
+    }
+
+    /// Merge `integrations` into the `integrations` field of every message.
+    pub(crate) fn with_default_integrations(mut self, integrations: serde_json::Value) -> Self {
+        self.default_integrations = Some(integrations);
+        self
+    }
+
+    /// Limit the memory used by the queued deliveries, the recent events, the sent message IDs,
+    /// the connected user IDs and the mock recorder, evicting their oldest items.
+    /// Must be called before the recent events are added.
+    pub(crate) fn with_memory_budget(mut self, limit: usize) -> Self {
+        let budget = Arc::new(MemoryBudget::new(limit));
+        self.sent_message_ids
+            .lock()
+            .unwrap()
+            .set_budget(budget.clone());
+        self.config
+            .lock()
+            .unwrap()
+            .set_connected_ids_budget(budget.clone());
+        if let Some(mock) = &mut self.mock {
+            mock.set_budget(budget.clone());
+        }
+        self.held_deliveries = Some(Arc::new(HeldDeliveries::new(budget.clone())));
+        self.memory_budget = budget;
+        self
+    }
+
+    /// Keep the last `capacity` events in memory so they can be replayed against the current rules.
+    pub(crate) fn with_recent_events(mut self, capacity: usize) -> Self {
+        self.recent_events = Some(RecentEvents::new(capacity, self.memory_budget.clone()));
+        self
+    }
+
+    /// The bytes used by in-memory structures and the limit of the memory budget.
+    pub(crate) fn memory_usage(&self) -> (usize, usize) {
+        (self.memory_budget.used(), self.memory_budget.limit())
+    }
+
+    /// Append every outgoing message to a local JSONL file.
+    pub(crate) fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = Some(Arc::new(event_log));
+        self
+    }
+
+    /// Send nothing, because the user or the OS asked not to be tracked.
+    pub(crate) fn with_do_not_track(mut self) -> Self {
+        self.do_not_track = true;
+        self
+    }
+
+    /// The unexpected identity reset detected on startup, reported by [Self::status].
+    pub(crate) fn with_identity_reset(mut self, identity_reset: Option<IdentityReset>) -> Self {
+        self.identity_reset = identity_reset;
+        self
+    }
+
+    /// Store every outgoing message in the local SQLite database.
+    #[cfg(feature = "local-store")]
+    pub(crate) fn with_local_store(mut self, local_store: crate::local_store::LocalStore) -> Self {
+        self.local_store = Some(local_store);
+        self
+    }
+
+    /// Export the events of the local store as a JSONL event log.
+    #[cfg(feature = "local-store")]
+    pub(crate) fn export_local_store(
+        &self,
+        path: &Path,
+    ) -> Result<usize, crate::local_store::LocalStoreError> {
+        match &self.local_store {
+            Some(local_store) => local_store.export(path),
+            None => Err(crate::local_store::LocalStoreError::NotEnabled),
+        }
+    }
+
+    /// Evaluate the context providers for every message and add their values to its context.
+    pub(crate) fn with_context_providers(
+        mut self,
+        context_providers: Vec<Box<dyn ContextProvider>>,
+    ) -> Self {
+        self.context_providers = context_providers;
+        self
+    }
+
+    /// Merge the context of events into the global context with this strategy.
+    pub(crate) fn with_context_merge(mut self, context_merge: ContextMerge) -> Self {
+        self.context_merge = context_merge;
+        self
+    }
+
+    /// Run the transformers on every event, in order, before it is sent.
+    pub(crate) fn with_transformers(mut self, transformers: Vec<Box<dyn Transformer>>) -> Self {
+        self.transformers = transformers;
+        self
+    }
+
+    /// Strip the filtered property, trait and context keys from every outgoing message.
+    pub(crate) fn with_property_filter(mut self, property_filter: PropertyFilter) -> Self {
+        self.property_filter = property_filter;
+        self
+    }
+
+    pub(crate) fn with_payload_limit(mut self, payload_limit: PayloadLimit) -> Self {
+        self.payload_limit = payload_limit;
+        self
+    }
+
+    /// Run events sent from the webview through `middleware` before they are sent.
+    pub(crate) fn with_command_middleware(
+        mut self,
+        middleware: Vec<Box<dyn CommandMiddleware>>,
+    ) -> Self {
+        self.command_middleware = middleware;
+        self
+    }
+
+    /// Validate all events against the tracking plan before they are sent.
+    #[cfg(feature = "tracking-plan")]
+    pub(crate) fn with_tracking_plan(mut self, plan: crate::tracking_plan::TrackingPlan) -> Self {
+        self.tracking_plan = Some(plan);
+        self
+    }
+
     /// Get the anonymous id asigned to this client
     pub fn get_anonymous_id(&self) -> String {
         self.config.lock().unwrap().anonymous_id().to_string()
     }
 
+    pub(crate) fn autosave(&self) -> Arc<Autosave> {
+        self.autosave.clone()
+    }
+
+    /// Release the reserved sequence numbers that were not used, before the config is saved on exit.
+    pub(crate) fn release_sequence(&self) {
+        self.config.lock().unwrap().release_sequence();
+    }
+
     pub fn save<R: Runtime>(&self, app: &tauri::AppHandle<R>) -> Result<(), config::ClientIdError> {
-        let config = self.config.lock().unwrap();
-        config.save(app)
+        self.config_to_save().save(app, &self.config_file)
+    }
+
+    /// The config with the IDs of the recently sent messages, as it is saved.
+    fn config_to_save(&self) -> std::sync::MutexGuard<'_, Config> {
+        let mut config = self.config.lock().unwrap();
+        config.set_sent_message_ids(self.sent_message_ids.lock().unwrap().clone());
+        config
     }
 
     pub(crate) fn add_to_context(
@@ -50,21 +609,443 @@ impl RudderWrapper {
         key: String,
         value: serde_json::Value,
     ) -> Option<serde_json::Value> {
-        let mut context = self.context.lock().unwrap();
-        context.insert(key, value)
+        self.context.lock().unwrap().changed().insert(key, value)
     }
 
     pub(crate) fn remove_from_context(&self, key: &str) -> Option<serde_json::Value> {
-        let mut context = self.context.lock().unwrap();
-        context.remove(key)
+        self.context.lock().unwrap().changed().remove(key)
     }
 
     pub(crate) fn get_context(&self) -> serde_json::Map<String, serde_json::Value> {
-        self.context.lock().unwrap().clone()
+        self.context.lock().unwrap().context.clone()
     }
 
     pub(crate) fn clear_context(&self) {
-        self.context.lock().unwrap().clear();
+        self.context.lock().unwrap().changed().clear();
+    }
+
+    /// Add the variant to the `experiments` context, if enabled.
+    pub(crate) fn add_experiment(&self, experiment: &str, variant: &str) {
+        if !self.experiments_in_context {
+            return;
+        }
+        let mut context = self.context.lock().unwrap();
+        let experiments = context
+            .changed()
+            .entry("experiments")
+            .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+        if !experiments.is_object() {
+            *experiments = serde_json::Value::Object(serde_json::Map::new());
+        }
+        if let Some(experiments) = experiments.as_object_mut() {
+            experiments.insert(experiment.to_string(), variant.into());
+        }
+    }
+
+    pub(crate) fn add_to_window_context(
+        &self,
+        label: &str,
+        key: String,
+        value: serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        let mut window_contexts = self.window_contexts.lock().unwrap();
+        window_contexts
+            .entry(label.to_string())
+            .or_default()
+            .insert(key, value)
+    }
+
+    pub(crate) fn remove_from_window_context(
+        &self,
+        label: &str,
+        key: &str,
+    ) -> Option<serde_json::Value> {
+        let mut window_contexts = self.window_contexts.lock().unwrap();
+        window_contexts.get_mut(label)?.remove(key)
+    }
+
+    pub(crate) fn window_context(&self, label: &str) -> serde_json::Map<String, serde_json::Value> {
+        let window_contexts = self.window_contexts.lock().unwrap();
+        window_contexts.get(label).cloned().unwrap_or_default()
+    }
+
+    /// Clear the context of the window, also called when the window is destroyed.
+    pub(crate) fn clear_window_context(&self, label: &str) {
+        self.window_contexts.lock().unwrap().remove(label);
+    }
+
+    /// Put the context of the window under the context of the message,
+    /// the values of the message take precedence.
+    pub(crate) fn apply_window_context(&self, label: &str, message: &mut crate::types::Message) {
+        let window_context = self.window_context(label);
+        if window_context.is_empty() {
+            return;
+        }
+        let context = message.context_mut();
+        let mut layered = serde_json::Value::Object(window_context);
+        if let Some(message_context) = context.take() {
+            merge(&mut layered, &message_context);
+        }
+        *context = Some(layered);
+    }
+
+    /// The Page event for a navigation reported by a webview,
+    /// `None` if page tracking is disabled or the path is excluded.
+    pub(crate) fn page_view(&self, url: &str, title: Option<String>) -> Option<crate::types::Page> {
+        self.page_tracking.as_ref()?.page(url, title)
+    }
+
+    /// Returns the priority of the message if it passes the rules and sampling and should be sent,
+    /// and whether it is forced. \
+    /// The message may be modified, e.g. to add tracking plan violations to the context.
+    #[track_caller]
+    pub(crate) fn should_send(
+        &self,
+        message: &mut crate::types::Message,
+    ) -> Result<Admitted, DropReason> {
+        if DISABLED {
+            return Err(DropReason::Disabled);
+        }
+        if self.strict {
+            self.check_strict(message);
+        }
+        self.apply_default_integrations(message);
+        if drop_past_send_by(message, chrono::Utc::now()) {
+            tracing::debug!("event passed its send by deadline, dropping it");
+            self.metrics.expired();
+            return Err(DropReason::Expired);
+        }
+        let input = self.recent_events.as_ref().map(|_| message.clone());
+        let decision = SendDecision::new(message.priority());
+        let allowed = self.apply_rules(message, &decision);
+        if let (Some(recent_events), Some(input)) = (&self.recent_events, input) {
+            recent_events.capture(input, allowed.then(|| message.clone()));
+        }
+        let forced = self
+            .sampler
+            .is_forced(message, decision.is_exempt_from_sampling());
+        let throttled = decision.is_low_priority() && self.is_throttled();
+        let result = if !allowed {
+            Err(DropReason::Filtered)
+        } else if !self.sampler.allow(message, forced) {
+            Err(DropReason::Sampled)
+        } else if throttled && !forced {
+            Err(DropReason::Throttled)
+        } else {
+            if throttled {
+                self.sampler.record_forced_send();
+            }
+            Ok(Admitted {
+                priority: decision.priority(),
+                forced,
+            })
+        };
+        if result.is_err() {
+            self.metrics.dropped();
+        }
+        result
+    }
+
+    /// Panic if the message has instrumentation problems, see [RudderStackBuilder::strict_mode](crate::RudderStackBuilder::strict_mode).
+    #[track_caller]
+    fn check_strict(&self, message: &crate::types::Message) {
+        #[allow(unused_mut)]
+        let mut problems = crate::strict::problems(message, &self.consent_destinations);
+        #[cfg(feature = "tracking-plan")]
+        if let Some(plan) = &self.tracking_plan {
+            problems.extend(plan.violations(message));
+        }
+        if !problems.is_empty() {
+            crate::strict::fail(message, &problems);
+        }
+    }
+
+    /// Merge the default integrations into the message and every message in a batch,
+    /// the integrations of the message take precedence.
+    fn apply_default_integrations(&self, message: &mut crate::types::Message) {
+        let Some(defaults) = &self.default_integrations else {
+            return;
+        };
+        for integrations in message.all_integrations_mut() {
+            let mut merged = defaults.clone();
+            if let Some(overrides) = integrations.as_ref() {
+                merge(&mut merged, overrides);
+            }
+            *integrations = Some(merged);
+        }
+    }
+
+    /// Run an event sent from the webview through the command middleware. \
+    /// Returns false if the message should be dropped.
+    pub(crate) fn apply_command_middleware(
+        &self,
+        window: &str,
+        message: &mut crate::types::Message,
+    ) -> bool {
+        let allowed = self
+            .command_middleware
+            .iter()
+            .all(|middleware| middleware.handle(window, message));
+        if !allowed {
+            tracing::debug!(window, "command middleware dropped the event");
+            self.metrics.dropped();
+        }
+        allowed
+    }
+
+    /// Check that the data plane is reachable.
+    pub(crate) fn check_connectivity(&self) -> tauri::async_runtime::JoinHandle<Connectivity> {
+        let transport = self.transport.read().unwrap().clone();
+        tauri::async_runtime::spawn_blocking(move || Connectivity::check(transport.as_ref()))
+    }
+
+    /// A snapshot of the delivery counters.
+    pub(crate) fn metrics(&self) -> Metrics {
+        self.metrics.snapshot()
+    }
+
+    /// Returns true if the data plane is throttling, low priority messages are dropped then.
+    fn is_throttled(&self) -> bool {
+        let throttled = self.pacer.is_throttled();
+        if throttled {
+            tracing::debug!("data plane is throttling, dropping low priority event");
+        }
+        throttled
+    }
+
+    /// Run the message through the event renames, event registry, tracking plan and transformers,
+    /// which may update the `decision`. \
+    /// Returns false if the message should be dropped.
+    fn apply_rules(&self, message: &mut crate::types::Message, decision: &SendDecision) -> bool {
+        self.registry.rename(message);
+        if !self.registry.allow(message) {
+            return false;
+        }
+        #[cfg(feature = "tracking-plan")]
+        if let Some(plan) = &self.tracking_plan {
+            if !plan.validate(message) {
+                return false;
+            }
+        }
+        if self.transformers.is_empty() {
+            return true;
+        }
+        let consent = self.consent_state();
+        let context = TransformContext {
+            consent: &consent,
+            decision,
+        };
+        self.transformers
+            .iter()
+            .all(|transformer| transformer.transform(message, &context))
+    }
+
+    /// Re-run the last `count` captured events through the current rules without sending them,
+    /// and return the events whose result changed.
+    pub(crate) fn replay_recent_events(&self, count: usize) -> Vec<ReplayDiff> {
+        match &self.recent_events {
+            Some(recent_events) => recent_events.replay(count, |message| {
+                self.apply_rules(message, &SendDecision::default())
+            }),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns true if the webview is allowed to send a track event with this name.
+    pub(crate) fn is_event_allowed(&self, event: &str) -> bool {
+        self.registry.is_allowed(event)
+    }
+
+    /// The number of forced events that were sent instead of dropped.
+    pub(crate) fn forced_sends(&self) -> u64 {
+        self.sampler.forced_sends()
+    }
+
+    /// The messages recorded in mock mode, empty if mock mode is disabled.
+    pub(crate) fn recorded_events(&self) -> Vec<rudderanalytics::message::Message> {
+        self.mock
+            .as_ref()
+            .map(MockRecorder::recorded)
+            .unwrap_or_default()
+    }
+
+    /// Clear the messages recorded in mock mode.
+    pub(crate) fn clear_recorded_events(&self) {
+        if let Some(mock) = &self.mock {
+            mock.clear();
+        }
+    }
+
+    /// Returns true if a track event with this name was recorded in mock mode.
+    pub(crate) fn was_tracked(&self, event: &str) -> bool {
+        self.mock.as_ref().is_some_and(|mock| mock.tracked(event))
+    }
+
+    /// The consent policy, if none was configured a policy with version `0` is used.
+    fn consent_policy(&self) -> ConsentPolicy {
+        self.consent_policy
+            .clone()
+            .unwrap_or_else(|| ConsentPolicy::new(0))
+    }
+
+    /// Returns true if no consent policy is configured, the message is essential,
+    /// or the user granted consent.
+    fn has_consent(&self, msg: &rudderanalytics::message::Message) -> bool {
+        let Some(policy) = &self.consent_policy else {
+            return true;
+        };
+        if let rudderanalytics::message::Message::Track(track) = msg {
+            if policy.is_essential(&track.event) {
+                return true;
+            }
+        }
+        self.config.lock().unwrap().consent().is_granted(policy)
+    }
+
+    /// Returns true if non-essential events are currently delivered to the data plane.
+    pub(crate) fn is_analytics_allowed(&self) -> bool {
+        if self.mock.is_some() || self.dry_run || self.do_not_track {
+            return false;
+        }
+        match &self.consent_policy {
+            Some(policy) => self.config.lock().unwrap().consent().is_granted(policy),
+            None => true,
+        }
+    }
+
+    pub(crate) fn increment_trait_counter(&self, name: &str) -> u64 {
+        self.config.lock().unwrap().increment_trait_counter(name)
+    }
+
+    /// The trait counters as identify traits, the given traits take precedence.
+    fn traits_with_counters(&self, traits: Option<serde_json::Value>) -> Option<serde_json::Value> {
+        let counters = self.config.lock().unwrap().trait_counters().clone();
+        if counters.is_empty() {
+            return traits;
+        }
+        let mut merged = serde_json::json!(counters);
+        if let Some(traits) = &traits {
+            merge(&mut merged, traits);
+        }
+        Some(merged)
+    }
+
+    pub(crate) fn status(&self) -> AnalyticsStatus {
+        AnalyticsStatus {
+            allowed: self.is_analytics_allowed(),
+            anonymous_id: self.get_anonymous_id(),
+            identity_reset: self.identity_reset.clone(),
+        }
+    }
+
+    /// All analytics data stored on this device.
+    pub(crate) fn export_data(&self) -> AnalyticsData {
+        let (anonymous_id, user_id, traits, config) = {
+            let config = self.config.lock().unwrap();
+            (
+                config.anonymous_id().to_string(),
+                config.user_id().map(|id| id.to_string()),
+                serde_json::json!(config.trait_counters()),
+                serde_json::to_value(&*config).unwrap_or_default(),
+            )
+        };
+        #[allow(unused_mut)]
+        let mut events: Vec<LoggedEvent> = self
+            .event_log
+            .as_ref()
+            .map(|event_log| event_log.read_all())
+            .unwrap_or_default();
+        #[cfg(feature = "local-store")]
+        if let Some(local_store) = &self.local_store {
+            match local_store.events() {
+                Ok(stored) => events.extend(stored),
+                Err(err) => tracing::error!("Failed to read the local store: {:?}", err),
+            }
+        }
+        AnalyticsData {
+            anonymous_id,
+            user_id,
+            traits,
+            context: serde_json::Value::Object(self.get_context()),
+            config,
+            events: events
+                .iter()
+                .filter_map(|event| serde_json::to_value(event).ok())
+                .collect(),
+        }
+    }
+
+    /// Delete all analytics data stored on this device and reset the identity and context in memory,
+    /// subsequent events use a new anonymous ID.
+    pub(crate) fn wipe_data<R: Runtime>(&self, app: &tauri::AppHandle<R>) -> Result<(), WipeError> {
+        self.clear_data();
+        Config::delete(app, &self.config_file)?;
+        if let Some(event_log) = &self.event_log {
+            event_log.clear()?;
+        }
+        #[cfg(feature = "local-store")]
+        if let Some(local_store) = &self.local_store {
+            local_store.clear()?;
+        }
+        Ok(())
+    }
+
+    /// Reset the identity, the global and window contexts and the recent events in memory.
+    fn clear_data(&self) {
+        let mut config = Config::new(self.id_generator.generate());
+        config.set_connected_ids_capacity(self.connected_ids_capacity);
+        config.set_connected_ids_budget(self.memory_budget.clone());
+        *self.config.lock().unwrap() = config;
+        let mut sent_message_ids = SentMessageIds::default();
+        sent_message_ids.set_budget(self.memory_budget.clone());
+        *self.sent_message_ids.lock().unwrap() = sent_message_ids;
+        self.clear_context();
+        self.window_contexts.lock().unwrap().clear();
+        if let Some(recent_events) = &self.recent_events {
+            recent_events.clear();
+        }
+    }
+
+    pub(crate) fn audit_log(&self) -> Vec<AuditEntry> {
+        self.config.lock().unwrap().audit_log()
+    }
+
+    pub(crate) fn consent_state(&self) -> ConsentState {
+        self.config.lock().unwrap().consent().clone()
+    }
+
+    pub(crate) fn should_prompt_consent(&self) -> bool {
+        let policy = self.consent_policy();
+        self.config
+            .lock()
+            .unwrap()
+            .consent()
+            .should_prompt(&policy, chrono::Utc::now())
+    }
+
+    pub(crate) fn prompt_consent(&self) -> Result<ConsentState, ConsentError> {
+        let policy = self.consent_policy();
+        let mut config = self.config.lock().unwrap();
+        config.consent_mut().prompt(&policy, chrono::Utc::now())?;
+        config.record_consent_change();
+        Ok(config.consent().clone())
+    }
+
+    pub(crate) fn set_consent_category(&self, category: String, granted: bool) -> ConsentState {
+        let mut config = self.config.lock().unwrap();
+        config.consent_mut().set_category(category.clone(), granted);
+        config.record_audit(AuditChange::ConsentCategoryChanged { category, granted });
+        config.consent().clone()
+    }
+
+    pub(crate) fn answer_consent(&self, granted: bool) -> Result<ConsentState, ConsentError> {
+        let policy = self.consent_policy();
+        let mut config = self.config.lock().unwrap();
+        config
+            .consent_mut()
+            .answer(granted, &policy, chrono::Utc::now())?;
+        config.record_consent_change();
+        Ok(config.consent().clone())
     }
 
     /// Set the anonymous id for this client
@@ -78,59 +1059,136 @@ impl RudderWrapper {
     /// This will be used in all subsequent events
     /// it will overwrite the previous user id
     pub(crate) fn set_user_id(&self, user_id: Option<String>) {
-        let should_send_identify = {
+        self.identify_user(user_id, None);
+    }
+
+    /// Set the user id like [Self::set_user_id]. If `identify` is set it is sent as the
+    /// Identify event, also if the user id was connected before, so the traits of the user
+    /// are recorded with a single Identify event.
+    pub(crate) fn identify_user(
+        &self,
+        user_id: Option<String>,
+        identify: Option<crate::types::Identify>,
+    ) {
+        let is_new_user = {
             let mut config = self.config.lock().unwrap();
             let result = config.set_user_id(user_id.clone());
             result == Some(false)
         };
 
-        if should_send_identify {
-            self.send(rudderanalytics::message::Message::Identify(
-                rudderanalytics::message::Identify {
-                    user_id,
-                    anonymous_id: Some(self.get_anonymous_id()),
-                    ..Default::default()
-                },
+        if let (true, true, Some(user_id)) = (is_new_user, self.alias_on_identify, user_id) {
+            self.send_message(crate::types::Message::Alias(crate::types::Alias {
+                user_id,
+                previous_id: self.get_anonymous_id(),
+                ..Default::default()
+            }));
+        }
+        if is_new_user || identify.is_some() {
+            self.send_message(crate::types::Message::Identify(
+                identify.unwrap_or_default(),
             ));
         }
     }
 
+    /// Send a message through the rules, sampling and transformers, see [Self::should_send],
+    /// then like [Self::send].
+    #[track_caller]
+    pub(crate) fn send_message(&self, mut message: crate::types::Message) -> SendResult {
+        match self.should_send(&mut message) {
+            Ok(admitted) => self.send_with_priority(
+                crate::types::convert_message(message),
+                admitted.priority,
+                admitted.forced,
+            ),
+            Err(reason) => Self::skipped(reason),
+        }
+    }
+
     /// Function that will receive user event data
     /// and after validation
     /// modify it to Ruddermessage format and send the event to data plane url \
-    /// add anonymous_id to all messages except alias.
+    /// add anonymous_id to all messages except alias, unless the message has its own. \
+    /// Non-essential events are dropped if a consent policy is configured and the user has not granted consent. \
+    /// Every message gets a `sequence` number in its context, persisted across restarts,
+    /// and a `session_sequence` number restarting at 1 for every `session_id`.
+    /// The numbers are assigned once the message is queued, so the messages dropped before
+    /// leave no gaps, messages that expire while held are reported as dropped. \
+    /// The context providers are evaluated for every message. \
+    /// Identify events get the trait counters in their traits.
+    /// The filtered property, trait and context keys are removed last.
     /// NOTE: this function will try to acquire a lock on the config.
-    pub fn send(
+    #[track_caller]
+    pub fn send(&self, msg: rudderanalytics::message::Message) -> SendResult {
+        let priority = Priority::of(&msg);
+        self.send_with_priority(msg, priority, false)
+    }
+
+    /// Send the message like [Self::send], delivering it before the queued messages
+    /// of lower priority. Forced messages are not dropped when too many deliveries are pending.
+    #[track_caller]
+    pub(crate) fn send_with_priority(
         &self,
         msg: rudderanalytics::message::Message,
-    ) -> tauri::async_runtime::JoinHandle<Result<(), rudderanalytics::errors::Error>> {
-        let rudder = self.rudder.clone();
+        priority: Priority,
+        forced: bool,
+    ) -> SendResult {
+        if DISABLED {
+            return Self::skipped(DropReason::Disabled);
+        }
+        match self.assemble(msg) {
+            Some(msg) => self.dispatch(msg, None, true, priority, forced),
+            None => Self::skipped(DropReason::NoConsent),
+        }
+    }
+
+    /// Add the identity and context to the message, see [Self::send]. \
+    /// Returns `None` if the message is dropped for lack of consent.
+    pub(crate) fn assemble(
+        &self,
+        mut msg: rudderanalytics::message::Message,
+    ) -> Option<rudderanalytics::message::Message> {
+        if !self.has_consent(&msg) {
+            tracing::debug!("consent not granted, dropping event");
+            self.metrics.dropped();
+            return None;
+        }
+        // The client sets `sentAt` when the message is transmitted and would fill a missing
+        // `originalTimestamp` with the same time, so delayed messages would appear to happen
+        // at delivery. Both come from the device clock, so the data plane can still correct skew.
+        original_timestamp_mut(&mut msg).get_or_insert_with(chrono::Utc::now);
         let anonymous_id = self.get_anonymous_id();
 
-        let user_id = {
-            self.config
-                .lock()
-                .unwrap()
-                .user_id()
-                .map(|id| id.to_string())
-        };
-        let mut context = {
-            let context = self.context.lock().unwrap();
-            serde_json::Value::Object(context.clone())
+        let user_id = self
+            .config
+            .lock()
+            .unwrap()
+            .user_id()
+            .map(|id| id.to_string());
+        // the per-message keys are layered on the shared context, alias messages don't use it
+        let global_context = self.context.lock().unwrap().shared();
+        let message_context = |event_context: Option<serde_json::Value>| {
+            let mut overlay = serde_json::Map::new();
+            overlay.insert("session_id".to_string(), self.session_id.clone().into());
+            if let Some(active_window) = self.active_window.as_ref().and_then(ActiveWindow::context)
+            {
+                overlay.insert("active_window".to_string(), active_window);
+            }
+            for provider in &self.context_providers {
+                provider.provide(&mut overlay);
+            }
+            Some(
+                self.context_merge
+                    .layer(&global_context, overlay, event_context.as_ref()),
+            )
         };
-        let msg = match msg {
+        let mut msg = match msg {
             rudderanalytics::message::Message::Identify(identify) => {
-                let context = {
-                    let mut context = context.clone();
-                    if let Some(identify_context) = identify.context {
-                        merge(&mut context, &identify_context);
-                    }
-                    Some(context)
-                };
+                let context = message_context(identify.context);
                 rudderanalytics::message::Message::Identify(rudderanalytics::message::Identify {
-                    anonymous_id: Some(anonymous_id),
+                    anonymous_id: identify.anonymous_id.or(Some(anonymous_id)),
                     user_id,
                     context,
+                    traits: self.traits_with_counters(identify.traits),
                     ..identify
                 })
             }
@@ -138,68 +1196,43 @@ impl RudderWrapper {
                 rudderanalytics::message::Message::Alias(alias)
             }
             rudderanalytics::message::Message::Group(group) => {
-                let context = {
-                    if let Some(group_context) = group.context {
-                        merge(&mut context, &group_context);
-                    }
-                    Some(context)
-                };
+                let context = message_context(group.context);
                 rudderanalytics::message::Message::Group(rudderanalytics::message::Group {
-                    anonymous_id: Some(anonymous_id),
+                    anonymous_id: group.anonymous_id.or(Some(anonymous_id)),
                     user_id,
                     context,
                     ..group
                 })
             }
             rudderanalytics::message::Message::Page(page) => {
-                let context = {
-                    if let Some(page_context) = page.context {
-                        merge(&mut context, &page_context);
-                    }
-                    Some(context)
-                };
+                let context = message_context(page.context);
                 rudderanalytics::message::Message::Page(rudderanalytics::message::Page {
-                    anonymous_id: Some(anonymous_id),
+                    anonymous_id: page.anonymous_id.or(Some(anonymous_id)),
                     user_id,
                     context,
                     ..page
                 })
             }
             rudderanalytics::message::Message::Screen(screen) => {
-                let context = {
-                    if let Some(screen_context) = screen.context {
-                        merge(&mut context, &screen_context);
-                    }
-                    Some(context)
-                };
+                let context = message_context(screen.context);
                 rudderanalytics::message::Message::Screen(rudderanalytics::message::Screen {
-                    anonymous_id: Some(anonymous_id),
+                    anonymous_id: screen.anonymous_id.or(Some(anonymous_id)),
                     user_id,
                     context,
                     ..screen
                 })
             }
             rudderanalytics::message::Message::Track(track) => {
-                let context = {
-                    if let Some(track_context) = track.context {
-                        merge(&mut context, &track_context);
-                    }
-                    Some(context)
-                };
+                let context = message_context(track.context);
                 rudderanalytics::message::Message::Track(rudderanalytics::message::Track {
-                    anonymous_id: Some(anonymous_id),
+                    anonymous_id: track.anonymous_id.or(Some(anonymous_id)),
                     user_id,
                     context,
                     ..track
                 })
             }
             rudderanalytics::message::Message::Batch(batch) => {
-                let context = {
-                    if let Some(batch_context) = batch.context {
-                        merge(&mut context, &batch_context);
-                    }
-                    Some(context)
-                };
+                let context = message_context(batch.context);
                 rudderanalytics::message::Message::Batch(rudderanalytics::message::Batch {
                     batch: batch
                         .batch
@@ -211,7 +1244,528 @@ impl RudderWrapper {
                 })
             }
         };
-        tauri::async_runtime::spawn_blocking(move || rudder.send(&msg))
+        if !self.property_filter.is_empty() {
+            self.property_filter.apply(&mut msg);
+        }
+        Some(msg)
+    }
+
+    /// Give the assembled message the next `sequence` and `session_sequence` numbers,
+    /// once it is queued for delivery, see [Self::send].
+    pub(crate) fn number(&self, msg: &mut rudderanalytics::message::Message) {
+        let (sequence, reserved) = self.config.lock().unwrap().next_sequence();
+        if reserved {
+            self.autosave.request();
+        }
+        let session_sequence = match msg {
+            rudderanalytics::message::Message::Alias(_) => None,
+            _ => Some(self.session_sequence.fetch_add(1, Ordering::Relaxed) + 1),
+        };
+        set_sequence(msg, sequence, session_sequence);
+    }
+
+    /// The result of a message that was dropped before it got an ID.
+    pub(crate) fn skipped(reason: DropReason) -> SendResult {
+        SendResult::dropped(uuid::Uuid::new_v4().to_string(), reason)
+    }
+
+    /// Send an already assembled message, or record / log it in mock and dry run mode. \
+    /// A new message ID is generated if `message_id` is `None`, messages with an ID that was
+    /// sent recently are skipped. If `log_event` is true the message is appended to the event log
+    /// and stored in the local store, also in mock and dry run mode. The event log is written
+    /// when the message is delivered, in mock and dry run mode right away. \
+    /// New messages are numbered once they are queued, see [Self::number]. \
+    /// Nothing is sent, logged or stored if do not track is set or with the `noop` feature.
+    #[track_caller]
+    fn dispatch(
+        &self,
+        mut msg: rudderanalytics::message::Message,
+        message_id: Option<String>,
+        log_event: bool,
+        priority: Priority,
+        forced: bool,
+    ) -> SendResult {
+        let is_new = message_id.is_none();
+        let message_id = message_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        if DISABLED {
+            return SendResult::dropped(message_id, DropReason::Disabled);
+        }
+        if self.do_not_track {
+            tracing::trace!("do not track is set, dropping event");
+            self.metrics.dropped();
+            return SendResult::dropped(message_id, DropReason::DoNotTrack);
+        }
+        if self.registry.drop_expired(&mut msg, chrono::Utc::now()) {
+            tracing::debug!(%message_id, "event outlived its TTL, dropping it");
+            self.metrics.expired();
+            self.notify_dropped(&message_id, &msg, DropReason::Expired);
+            return SendResult::dropped(message_id, DropReason::Expired);
+        }
+        let created_at = message_timestamp(&msg).unwrap_or_else(chrono::Utc::now);
+        if is_older_than(created_at, self.max_event_age) {
+            tracing::debug!(%message_id, "message is older than the maximum event age, dropping it");
+            self.metrics.expired();
+            self.notify_dropped(&message_id, &msg, DropReason::Expired);
+            return SendResult::dropped(message_id, DropReason::Expired);
+        }
+        if self.sent_message_ids.lock().unwrap().contains(&message_id) {
+            tracing::debug!(%message_id, "message was already sent, skipping duplicate");
+            return SendResult::dropped(message_id, DropReason::Duplicate);
+        }
+        // resent and replayed messages keep their numbers, dry run messages are not numbered
+        let numbered = is_new && log_event && !self.dry_run;
+        if numbered {
+            // the size is checked with the widest numbers, they are assigned once it is queued
+            set_sequence(&mut msg, u64::MAX, Some(u64::MAX));
+        }
+        if let Err(size) = self.payload_limit.fit(&mut msg) {
+            let max = payload_size::max_bytes(&msg);
+            // resent and replayed messages were checked when they were first sent
+            if self.strict && is_new {
+                crate::strict::fail(&msg, &[crate::strict::too_large(size, max)]);
+            }
+            tracing::error!(%message_id, size, max, "message is too large for the data plane");
+            self.metrics.rejected();
+            return SendResult::too_large(message_id, size, max);
+        }
+        let pending_sends = self
+            .pending_sends
+            .as_ref()
+            .filter(|_| self.mock.is_none() && !self.dry_run);
+        let slot = match pending_sends {
+            Some(pending_sends) => match pending_sends.reserve(priority) {
+                Some(slot) => Some(slot),
+                None if forced => {
+                    tracing::debug!(%message_id, "sending forced event despite pending deliveries");
+                    self.sampler.record_forced_send();
+                    None
+                }
+                None => {
+                    tracing::debug!(%message_id, "too many pending deliveries, dropping event");
+                    self.metrics.overflowed();
+                    self.notify_dropped(&message_id, &msg, DropReason::Backpressured);
+                    return SendResult::dropped(message_id, DropReason::Backpressured);
+                }
+            },
+            None => None,
+        };
+        let held_deliveries = self
+            .held_deliveries
+            .as_ref()
+            .filter(|_| self.mock.is_none() && !self.dry_run);
+        let held = match held_deliveries {
+            Some(held_deliveries) => match held_deliveries.hold(estimate_size(&msg)) {
+                Some(held) => Some(held),
+                None if forced => None,
+                None => {
+                    tracing::debug!(%message_id, "memory budget exceeded, dropping event");
+                    self.metrics.overflowed();
+                    self.notify_dropped(&message_id, &msg, DropReason::Backpressured);
+                    return SendResult::dropped(message_id, DropReason::Backpressured);
+                }
+            },
+            None => None,
+        };
+        if numbered {
+            self.number(&mut msg);
+        }
+        #[cfg(feature = "local-store")]
+        if let Some(local_store) = self.local_store.as_ref().filter(|_| log_event) {
+            if let Err(err) = local_store.insert(&msg, &message_id) {
+                tracing::error!("Failed to store event locally: {:?}", err);
+            }
+        }
+        if self.dry_run {
+            match serde_json::to_string_pretty(&msg) {
+                Ok(payload) => {
+                    tracing::info!(target: "rudderstack::dry_run", %message_id, "{}", payload)
+                }
+                Err(err) => tracing::error!("Failed to serialize dry run payload: {:?}", err),
+            }
+        }
+        if self.mock.is_some() || self.dry_run {
+            if let Some(event_log) = self.event_log.as_ref().filter(|_| log_event) {
+                if let Err(err) = event_log.append(&msg, &message_id) {
+                    tracing::error!("Failed to write event log: {:?}", err);
+                }
+            }
+        }
+        if let Some(mock) = &self.mock {
+            mock.record(msg);
+            return SendResult::delivered(message_id);
+        }
+        if self.dry_run {
+            return SendResult::dropped(message_id, DropReason::DryRun);
+        }
+        let transport = self.transport.read().unwrap().clone();
+        let event_log = self.event_log.clone().filter(|_| log_event);
+        let metrics = self.metrics.clone();
+        let delivery_listener = self.delivery_listener.clone();
+        let drop_listener = self.drop_listener.clone();
+        let max_event_age = self.max_event_age;
+        let registry = self.registry.clone();
+        let sent_message_ids = self.sent_message_ids.clone();
+        let autosave = self.autosave.clone();
+        let pacer = self.pacer.clone();
+        let send_queue = self.send_queue.clone();
+        let delivery_gate = self.delivery_gate.clone();
+        let connectivity_probe = self.connectivity_probe;
+        let circuit_breaker = self.circuit_breaker.clone();
+        metrics.queued();
+        let queued_id = message_id.clone();
+        let handle = tauri::async_runtime::spawn(async move {
+            // released when the delivery finished
+            let _slot = slot;
+            let waiting = async {
+                // only one delivery probes the data plane while the circuit is half-open
+                let probe = loop {
+                    delivery_gate.wait().await;
+                    match &circuit_breaker {
+                        Some(breaker) => match breaker.admit().await {
+                            Admission::Deliver => break None,
+                            Admission::Probe(probe) => break Some(probe),
+                            Admission::Wait => continue,
+                        },
+                        None => break None,
+                    }
+                };
+                (probe, send_queue.acquire(priority).await)
+            };
+            // the memory of a held delivery is released when it starts
+            let waited = match held {
+                Some(mut held) => held.wait(waiting).await.filter(|_| held.start()),
+                None => Some(waiting.await),
+            };
+            let Some((_probe, _permit)) = waited else {
+                tracing::debug!(%message_id, "memory budget exceeded, evicted queued event");
+                metrics.overflowed_while_queued();
+                if let Some(listener) = drop_listener {
+                    listener(&message_id, &event_name(&msg), DropReason::Backpressured);
+                }
+                return Err(SendError::Dropped(DropReason::Backpressured));
+            };
+            // the message may have been held, e.g. while offline
+            if is_older_than(created_at, max_event_age)
+                || registry.drop_expired(&mut msg, chrono::Utc::now())
+            {
+                tracing::debug!(%message_id, "message expired while waiting, dropping it");
+                metrics.expired_while_queued();
+                if let Some(listener) = drop_listener {
+                    listener(&message_id, &event_name(&msg), DropReason::Expired);
+                }
+                return Err(SendError::Dropped(DropReason::Expired));
+            }
+            if let Some(event_log) = event_log {
+                if let Err(err) = event_log.append(&msg, &message_id) {
+                    tracing::error!("Failed to write event log: {:?}", err);
+                }
+            }
+            pacer.wait().await;
+            let event = event_name(&msg);
+            let result = transport
+                .clone()
+                .deliver_with_id(msg, message_id.clone())
+                .await;
+            if let (Some(interval), Err(err)) = (connectivity_probe, &result) {
+                if offline::is_connection_error(&err.error) {
+                    offline::pause_deliveries(delivery_gate, transport, interval);
+                }
+            }
+            pacer.record(&result);
+            if let Some(breaker) = &circuit_breaker {
+                breaker.record(&result);
+            }
+            if result.is_ok() {
+                sent_message_ids.lock().unwrap().insert(message_id.clone());
+                // remembered across restarts, also if the app crashes
+                autosave.request();
+            }
+            metrics.delivered(&result);
+            if let Some(listener) = delivery_listener {
+                listener(&message_id, &event, &result);
+            }
+            result.map_err(SendError::from)
+        });
+        SendResult::queued(queued_id, handle)
+    }
+
+    fn notify_dropped(
+        &self,
+        message_id: &str,
+        msg: &rudderanalytics::message::Message,
+        reason: DropReason,
+    ) {
+        if let Some(listener) = &self.drop_listener {
+            listener(message_id, &event_name(msg), reason);
+        }
+    }
+
+    /// Where the crash report is kept until it is delivered, `None` if nothing is written to disk.
+    /// The report holds the identity, so it is not written when the identity is kept in the keyring.
+    pub(crate) fn crash_report_path<R: Runtime>(
+        &self,
+        app: &tauri::AppHandle<R>,
+    ) -> Option<std::path::PathBuf> {
+        if self.config_file.is_ephemeral() || self.config_file.uses_keyring() {
+            return None;
+        }
+        self.config_file.crash_report_path(app).ok()
+    }
+
+    /// Write the crash report, encrypted like the config.
+    pub(crate) fn write_crash_report(
+        &self,
+        path: &std::path::Path,
+        bytes: Vec<u8>,
+    ) -> Result<(), config::ClientIdError> {
+        self.config_file.write(path, bytes)
+    }
+
+    /// Read the crash report written by [Self::write_crash_report].
+    pub(crate) fn read_crash_report(
+        &self,
+        path: &std::path::Path,
+    ) -> Result<Vec<u8>, config::ClientIdError> {
+        self.config_file.read(path)
+    }
+
+    /// Send an assembled message with the ID it was assigned before, e.g. a crash report
+    /// that could not be delivered in the last run. \
+    /// The message is dropped if the user no longer consents.
+    pub(crate) fn resend(
+        &self,
+        msg: rudderanalytics::message::Message,
+        message_id: String,
+        priority: Priority,
+    ) -> SendResult {
+        if !self.has_consent(&msg) {
+            tracing::debug!(%message_id, "consent not granted, dropping resent event");
+            self.metrics.dropped();
+            return SendResult::dropped(message_id, DropReason::NoConsent);
+        }
+        self.dispatch(msg, Some(message_id), true, priority, false)
+    }
+
+    /// Deliver an assembled message on the current thread, bypassing the pacing and the event log,
+    /// e.g. for a crash report while the process is dying. \
+    /// Nothing is delivered in do not track, mock and dry run mode.
+    pub(crate) fn deliver_blocking(
+        &self,
+        msg: &rudderanalytics::message::Message,
+        message_id: &str,
+    ) -> Result<(), DeliveryError> {
+        if self.do_not_track || self.mock.is_some() || self.dry_run {
+            return Ok(());
+        }
+        let transport = self.transport.read().unwrap().clone();
+        transport.deliver(msg)?;
+        self.sent_message_ids
+            .lock()
+            .unwrap()
+            .insert(message_id.to_string());
+        Ok(())
+    }
+
+    /// Resubmit the events recorded in an event log, the format is picked by the file extension. \
+    /// The events keep their identity and `originalTimestamp` and are not written to the event log
+    /// again. Events that were already delivered, that the user no longer consents to or that
+    /// the current rules drop are skipped.
+    pub(crate) fn replay_from_file(&self, path: &Path) -> std::io::Result<Vec<SendResult>> {
+        let bytes = std::fs::read(path)?;
+        let handles = EventLogFormat::from_path(path)
+            .decode(&bytes)
+            .into_iter()
+            .map(replayed_message)
+            .map(|(msg, message_id)| self.replay(msg, message_id))
+            .collect();
+        Ok(handles)
+    }
+
+    /// Send a logged message again if the user consents and it passes the current rules,
+    /// sampling and transformers, see [Self::should_send].
+    fn replay(
+        &self,
+        msg: rudderanalytics::message::Message,
+        message_id: Option<String>,
+    ) -> SendResult {
+        let dropped = |reason| match &message_id {
+            Some(message_id) => SendResult::dropped(message_id.clone(), reason),
+            None => Self::skipped(reason),
+        };
+        if !self.has_consent(&msg) {
+            tracing::debug!("consent not granted, dropping replayed event");
+            self.metrics.dropped();
+            return dropped(DropReason::NoConsent);
+        }
+        let Some(mut message) = plugin_message(&msg) else {
+            tracing::error!("Failed to convert the replayed event, dropping it");
+            return dropped(DropReason::Filtered);
+        };
+        match self.should_send(&mut message) {
+            Ok(admitted) => {
+                let mut replayed = crate::types::convert_message(message);
+                restore_user_ids(msg, &mut replayed);
+                self.dispatch(
+                    replayed,
+                    message_id,
+                    false,
+                    admitted.priority,
+                    admitted.forced,
+                )
+            }
+            Err(reason) => dropped(reason),
+        }
+    }
+}
+
+/// Remove the track events past their send by deadline from the message, also from a batch. \
+/// Returns true if the whole message expired.
+fn drop_past_send_by(
+    message: &mut crate::types::Message,
+    now: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    let expired = |track: &crate::types::Track| track.send_by.is_some_and(|send_by| send_by < now);
+    match message {
+        crate::types::Message::Track(track) => expired(track),
+        crate::types::Message::Batch(batch) => {
+            batch.batch.retain(|message| {
+                !matches!(message, crate::types::BatchMessage::Track(track) if expired(track))
+            });
+            batch.batch.is_empty()
+        }
+        _ => false,
+    }
+}
+
+/// The message of an event log record and its ID. \
+/// If the message has no `originalTimestamp`, the time it was logged is used.
+fn replayed_message(event: LoggedEvent) -> (rudderanalytics::message::Message, Option<String>) {
+    let mut msg = rudderanalytics::message::Message::from(event.message);
+    original_timestamp_mut(&mut msg).get_or_insert(event.logged_at);
+    (msg, event.message_id)
+}
+
+/// The plugin message of an assembled message, to run the rules on a replayed message.
+/// The user IDs are not part of the plugin messages, see [restore_user_ids].
+fn plugin_message(msg: &rudderanalytics::message::Message) -> Option<crate::types::Message> {
+    use rudderanalytics::message::Message;
+
+    let (variant, fields) = match msg {
+        Message::Identify(identify) => ("Identify", serde_json::to_value(identify)),
+        Message::Track(track) => ("Track", serde_json::to_value(track)),
+        Message::Page(page) => ("Page", serde_json::to_value(page)),
+        Message::Screen(screen) => ("Screen", serde_json::to_value(screen)),
+        Message::Group(group) => ("Group", serde_json::to_value(group)),
+        Message::Alias(alias) => ("Alias", serde_json::to_value(alias)),
+        Message::Batch(batch) => ("Batch", serde_json::to_value(batch)),
+    };
+    let mut message = serde_json::Map::new();
+    message.insert(variant.to_string(), fields.ok()?);
+    serde_json::from_value(serde_json::Value::Object(message)).ok()
+}
+
+/// Set the user ID of the assembled `original` message on the `replayed` message,
+/// which lost it in the conversion to a plugin message. Alias messages keep their own.
+fn restore_user_ids(
+    mut original: rudderanalytics::message::Message,
+    replayed: &mut rudderanalytics::message::Message,
+) {
+    use rudderanalytics::message::Message;
+
+    let user_id = match &mut original {
+        Message::Batch(batch) => batch
+            .batch
+            .iter_mut()
+            .find_map(|message| batch_user_id_mut(message).and_then(|id| id.clone())),
+        message => user_id_mut(message).and_then(|id| id.clone()),
+    };
+    match replayed {
+        Message::Batch(batch) => {
+            for user_id_field in batch.batch.iter_mut().filter_map(batch_user_id_mut) {
+                *user_id_field = user_id.clone();
+            }
+        }
+        message => {
+            if let Some(user_id_field) = user_id_mut(message) {
+                *user_id_field = user_id;
+            }
+        }
+    }
+}
+
+/// The `userId` field of a rudder message, `None` for alias messages and batches.
+fn user_id_mut(msg: &mut rudderanalytics::message::Message) -> Option<&mut Option<String>> {
+    use rudderanalytics::message::Message;
+
+    match msg {
+        Message::Identify(identify) => Some(&mut identify.user_id),
+        Message::Track(track) => Some(&mut track.user_id),
+        Message::Page(page) => Some(&mut page.user_id),
+        Message::Screen(screen) => Some(&mut screen.user_id),
+        Message::Group(group) => Some(&mut group.user_id),
+        Message::Alias(_) | Message::Batch(_) => None,
+    }
+}
+
+/// The `userId` field of a message in a batch, `None` for alias messages.
+fn batch_user_id_mut(
+    msg: &mut rudderanalytics::message::BatchMessage,
+) -> Option<&mut Option<String>> {
+    use rudderanalytics::message::BatchMessage;
+
+    match msg {
+        BatchMessage::Identify(identify) => Some(&mut identify.user_id),
+        BatchMessage::Track(track) => Some(&mut track.user_id),
+        BatchMessage::Page(page) => Some(&mut page.user_id),
+        BatchMessage::Screen(screen) => Some(&mut screen.user_id),
+        BatchMessage::Group(group) => Some(&mut group.user_id),
+        BatchMessage::Alias(_) => None,
+    }
+}
+
+/// Set the `sequence` and `session_sequence` numbers in the context of a rudder message.
+fn set_sequence(
+    msg: &mut rudderanalytics::message::Message,
+    sequence: u64,
+    session_sequence: Option<u64>,
+) {
+    use rudderanalytics::message::Message;
+
+    let context = match msg {
+        Message::Identify(identify) => &mut identify.context,
+        Message::Track(track) => &mut track.context,
+        Message::Page(page) => &mut page.context,
+        Message::Screen(screen) => &mut screen.context,
+        Message::Group(group) => &mut group.context,
+        Message::Alias(alias) => &mut alias.context,
+        Message::Batch(batch) => &mut batch.context,
+    };
+    let Some(context) = context
+        .get_or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+    else {
+        return;
+    };
+    context.insert("sequence".to_string(), sequence.into());
+    if let Some(session_sequence) = session_sequence {
+        context.insert("session_sequence".to_string(), session_sequence.into());
+    }
+}
+
+/// The `originalTimestamp` field of a rudder message.
+fn original_timestamp_mut(
+    msg: &mut rudderanalytics::message::Message,
+) -> &mut Option<chrono::DateTime<chrono::Utc>> {
+    use rudderanalytics::message::Message;
+
+    match msg {
+        Message::Identify(identify) => &mut identify.original_timestamp,
+        Message::Track(track) => &mut track.original_timestamp,
+        Message::Page(page) => &mut page.original_timestamp,
+        Message::Screen(screen) => &mut screen.original_timestamp,
+        Message::Group(group) => &mut group.original_timestamp,
+        Message::Alias(alias) => &mut alias.original_timestamp,
+        Message::Batch(batch) => &mut batch.original_timestamp,
     }
 }
 
@@ -225,7 +1779,7 @@ fn handle_batch_message(
     match batch_message {
         rudderanalytics::message::BatchMessage::Identify(identify) => {
             let identify = rudderanalytics::message::Identify {
-                anonymous_id: Some(anonymous_id),
+                anonymous_id: identify.anonymous_id.or(Some(anonymous_id)),
                 user_id,
                 ..identify
             };
@@ -236,7 +1790,7 @@ fn handle_batch_message(
         }
         rudderanalytics::message::BatchMessage::Group(group) => {
             let group = rudderanalytics::message::Group {
-                anonymous_id: Some(anonymous_id),
+                anonymous_id: group.anonymous_id.or(Some(anonymous_id)),
                 user_id,
                 ..group
             };
@@ -244,7 +1798,7 @@ fn handle_batch_message(
         }
         rudderanalytics::message::BatchMessage::Page(page) => {
             let page = rudderanalytics::message::Page {
-                anonymous_id: Some(anonymous_id),
+                anonymous_id: page.anonymous_id.or(Some(anonymous_id)),
                 user_id,
                 ..page
             };
@@ -252,7 +1806,7 @@ fn handle_batch_message(
         }
         rudderanalytics::message::BatchMessage::Screen(screen) => {
             let screen = rudderanalytics::message::Screen {
-                anonymous_id: Some(anonymous_id),
+                anonymous_id: screen.anonymous_id.or(Some(anonymous_id)),
                 user_id,
                 ..screen
             };
@@ -260,7 +1814,7 @@ fn handle_batch_message(
         }
         rudderanalytics::message::BatchMessage::Track(track) => {
             let track = rudderanalytics::message::Track {
-                anonymous_id: Some(anonymous_id),
+                anonymous_id: track.anonymous_id.or(Some(anonymous_id)),
                 user_id,
                 ..track
             };
@@ -268,3 +1822,480 @@ fn handle_batch_message(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, future::IntoFuture as _, time::Duration};
+
+    use super::*;
+    use crate::{
+        delivery_gate::PauseReason,
+        registry::{EventDefinition, EventRenames},
+        send_result::SendStatus,
+        types::{Batch, BatchMessage, Message, Track},
+    };
+
+    /// Records the delivered messages.
+    #[derive(Default)]
+    struct RecordingTransport(Mutex<Vec<rudderanalytics::message::Message>>);
+
+    impl Transport for RecordingTransport {
+        fn deliver(
+            &self,
+            message: &rudderanalytics::message::Message,
+        ) -> Result<(), DeliveryError> {
+            self.0.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    fn wrapper(transport: Arc<RecordingTransport>, events: Vec<EventDefinition>) -> RudderWrapper {
+        wrapper_with_config(transport, Config::new("anonymous-id".to_string()), events)
+    }
+
+    fn wrapper_with_config(
+        transport: Arc<RecordingTransport>,
+        config: Config,
+        events: Vec<EventDefinition>,
+    ) -> RudderWrapper {
+        RudderWrapper::new(
+            transport,
+            config,
+            crate::types::Context::default(),
+            Sampler::new(1.0, HashSet::new()),
+            EventRegistry::new(
+                events,
+                EventRenames::default(),
+                semver::Version::new(1, 0, 0),
+                false,
+            ),
+            None,
+        )
+    }
+
+    fn track(event: &str) -> Track {
+        Track {
+            event: event.to_string(),
+            ..Track::default()
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "noop")]
+    fn noop_drops_every_message() {
+        let transport = Arc::new(RecordingTransport::default());
+        let rudder = wrapper(transport.clone(), Vec::new());
+
+        let result = rudder.send_message(Message::Track(track("Signed In")));
+        assert!(matches!(
+            result.status(),
+            SendStatus::Dropped(DropReason::Disabled)
+        ));
+        let assembled = rudderanalytics::message::Message::Track(Default::default());
+        assert!(matches!(
+            rudder.send(assembled).status(),
+            SendStatus::Dropped(DropReason::Disabled)
+        ));
+        assert!(transport.0.lock().unwrap().is_empty());
+        assert_eq!(rudder.metrics().sent, 0);
+    }
+
+    #[test]
+    fn layers_the_message_keys_on_the_global_context() {
+        let rudder = wrapper(Arc::new(RecordingTransport::default()), Vec::new());
+        let context = || match rudder
+            .assemble(rudderanalytics::message::Message::Track(Default::default()))
+        {
+            Some(rudderanalytics::message::Message::Track(track)) => track.context.unwrap(),
+            other => panic!("unexpected message: {other:?}"),
+        };
+        rudder.add_to_context("app".to_string(), serde_json::json!({ "name": "app" }));
+
+        let first = context();
+        assert_eq!(first["app"], serde_json::json!({ "name": "app" }));
+        assert!(first["session_id"].is_string());
+
+        rudder.add_to_context("locale".to_string(), "en".into());
+        assert_eq!(context()["locale"], "en");
+        assert_eq!(
+            serde_json::Value::Object(rudder.get_context()),
+            serde_json::json!({ "app": { "name": "app" }, "locale": "en" })
+        );
+    }
+
+    #[test]
+    fn numbers_only_the_queued_messages() {
+        let transport = Arc::new(RecordingTransport::default());
+        let rudder =
+            wrapper(transport.clone(), Vec::new()).with_max_event_age(Duration::from_secs(60));
+        let send = |original_timestamp| {
+            let result = rudder.send(rudderanalytics::message::Message::Track(
+                rudderanalytics::message::Track {
+                    event: "Signed In".to_string(),
+                    original_timestamp,
+                    ..Default::default()
+                },
+            ));
+            tauri::async_runtime::block_on(result.into_future())
+        };
+
+        assert!(matches!(send(None), SendStatus::Delivered));
+        let stale = chrono::Utc::now() - chrono::Duration::hours(1);
+        assert!(matches!(
+            send(Some(stale)),
+            SendStatus::Dropped(DropReason::Expired)
+        ));
+        assert!(matches!(send(None), SendStatus::Delivered));
+
+        let numbers: Vec<_> = transport
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|message| match message {
+                rudderanalytics::message::Message::Track(track) => {
+                    let context = track.context.as_ref().unwrap();
+                    (
+                        context["sequence"].as_u64(),
+                        context["session_sequence"].as_u64(),
+                    )
+                }
+                other => panic!("unexpected message: {other:?}"),
+            })
+            .collect();
+        let [(Some(sequence), Some(1)), next] = numbers.as_slice() else {
+            panic!("expected two numbered messages, got {numbers:?}");
+        };
+        assert_eq!(*next, (Some(sequence + 1), Some(2)));
+    }
+
+    #[test]
+    fn evicts_the_oldest_queued_messages_over_the_memory_budget() {
+        let transport = Arc::new(RecordingTransport::default());
+        let rudder = wrapper(transport.clone(), Vec::new()).with_memory_budget(6 * 1024);
+        let gate = rudder.delivery_gate();
+        let large = |event: &str| {
+            Message::Track(Track {
+                properties: Some(serde_json::json!({ "payload": "x".repeat(4 * 1024) })),
+                ..track(event)
+            })
+        };
+
+        gate.pause(PauseReason::Metered);
+        let first = rudder.send_message(large("First"));
+        let second = rudder.send_message(large("Second"));
+        let status = tauri::async_runtime::block_on(first.into_future());
+        assert!(matches!(
+            status,
+            SendStatus::Dropped(DropReason::Backpressured)
+        ));
+        gate.resume(PauseReason::Metered);
+        let status = tauri::async_runtime::block_on(second.into_future());
+        assert!(matches!(status, SendStatus::Delivered));
+
+        let delivered = transport.0.lock().unwrap();
+        let [rudderanalytics::message::Message::Track(track)] = delivered.as_slice() else {
+            panic!("expected one track event, got {:?}", delivered);
+        };
+        assert_eq!(track.event, "Second");
+        assert_eq!(rudder.metrics().overflowed, 1);
+    }
+
+    #[test]
+    fn drops_events_that_outlive_their_ttl_while_held() {
+        let transport = Arc::new(RecordingTransport::default());
+        let rudder = wrapper(
+            transport.clone(),
+            vec![EventDefinition::new("Promo Viewed").ttl(Duration::from_millis(50))],
+        );
+        let gate = rudder.delivery_gate();
+
+        gate.pause(PauseReason::Metered);
+        let result = rudder.send_message(Message::Track(track("Promo Viewed")));
+        std::thread::sleep(Duration::from_millis(100));
+        gate.resume(PauseReason::Metered);
+        let status = tauri::async_runtime::block_on(result.into_future());
+        assert!(matches!(status, SendStatus::Dropped(DropReason::Expired)));
+        assert!(transport.0.lock().unwrap().is_empty());
+
+        gate.pause(PauseReason::Metered);
+        let result = rudder.send_message(Message::Batch(Batch {
+            batch: vec![
+                BatchMessage::Track(track("Promo Viewed")),
+                BatchMessage::Track(track("Signed In")),
+            ],
+            ..Batch::default()
+        }));
+        std::thread::sleep(Duration::from_millis(100));
+        gate.resume(PauseReason::Metered);
+        let status = tauri::async_runtime::block_on(result.into_future());
+        assert!(matches!(status, SendStatus::Delivered));
+        let delivered = transport.0.lock().unwrap();
+        let [rudderanalytics::message::Message::Batch(batch)] = delivered.as_slice() else {
+            panic!("expected one batch, got {:?}", delivered);
+        };
+        let events: Vec<_> = batch
+            .batch
+            .iter()
+            .filter_map(|message| match message {
+                rudderanalytics::message::BatchMessage::Track(track) => Some(track.event.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(events, ["Signed In"]);
+    }
+
+    #[test]
+    fn skips_repeated_message_ids_across_restarts() {
+        let transport = Arc::new(RecordingTransport::default());
+        let rudder = wrapper(transport.clone(), Vec::new());
+        let message = rudderanalytics::message::Message::Track(rudderanalytics::message::Track {
+            event: "Order Completed".to_string(),
+            ..Default::default()
+        });
+        let resend = |rudder: &RudderWrapper| {
+            rudder.resend(message.clone(), "message-1".to_string(), Priority::Normal)
+        };
+
+        let status = tauri::async_runtime::block_on(resend(&rudder).into_future());
+        assert!(matches!(status, SendStatus::Delivered));
+        assert!(rudder.autosave().is_pending());
+        assert!(matches!(
+            resend(&rudder).status(),
+            SendStatus::Dropped(DropReason::Duplicate)
+        ));
+
+        let saved = serde_json::to_value(&*rudder.config_to_save()).unwrap();
+        let restarted = wrapper_with_config(
+            transport.clone(),
+            serde_json::from_value(saved).unwrap(),
+            Vec::new(),
+        );
+        assert!(matches!(
+            resend(&restarted).status(),
+            SendStatus::Dropped(DropReason::Duplicate)
+        ));
+        assert_eq!(transport.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn saves_the_audit_log() {
+        let rudder = wrapper(Arc::new(RecordingTransport::default()), Vec::new());
+        rudder.set_user_id(Some("user-1".to_string()));
+        rudder.set_consent_category("marketing".to_string(), false);
+
+        let saved = serde_json::to_value(&*rudder.config_to_save()).unwrap();
+        let restarted: Config = serde_json::from_value(saved).unwrap();
+        let changes: Vec<_> = restarted
+            .audit_log()
+            .into_iter()
+            .map(|entry| entry.change)
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                AuditChange::UserIdSet {
+                    user_id: "user-1".to_string()
+                },
+                AuditChange::ConsentCategoryChanged {
+                    category: "marketing".to_string(),
+                    granted: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn replays_logged_events_through_the_consent_and_rules() {
+        let logged = |event: &str| LoggedEvent {
+            logged_at: chrono::Utc::now(),
+            message_id: Some(format!("{event}-id")),
+            message: rudderanalytics::message::Message::Track(rudderanalytics::message::Track {
+                user_id: Some("user-1".to_string()),
+                anonymous_id: Some("anonymous-id".to_string()),
+                event: event.to_string(),
+                ..Default::default()
+            })
+            .into(),
+        };
+        let path =
+            std::env::temp_dir().join(format!("rudderstack-replay-{}.jsonl", std::process::id()));
+        let log: Vec<_> = [logged("Signed In"), logged("Legacy Event")]
+            .iter()
+            .flat_map(|event| EventLogFormat::Jsonl.encode(event).unwrap())
+            .collect();
+        std::fs::write(&path, log).unwrap();
+        let replay = |rudder: &RudderWrapper| -> Vec<SendStatus> {
+            rudder
+                .replay_from_file(&path)
+                .unwrap()
+                .into_iter()
+                .map(|result| tauri::async_runtime::block_on(result.into_future()))
+                .collect()
+        };
+
+        let transport = Arc::new(RecordingTransport::default());
+        let rudder = wrapper(
+            transport.clone(),
+            vec![EventDefinition::new("Legacy Event").max_version("0.9.0")],
+        );
+        let statuses = replay(&rudder);
+        assert!(matches!(
+            statuses.as_slice(),
+            [
+                SendStatus::Delivered,
+                SendStatus::Dropped(DropReason::Filtered)
+            ]
+        ));
+        {
+            let delivered = transport.0.lock().unwrap();
+            let [rudderanalytics::message::Message::Track(track)] = delivered.as_slice() else {
+                panic!("expected one track event, got {:?}", delivered);
+            };
+            assert_eq!(track.event, "Signed In");
+            assert_eq!(track.user_id.as_deref(), Some("user-1"));
+        }
+
+        let transport = Arc::new(RecordingTransport::default());
+        let rudder = RudderWrapper::new(
+            transport.clone(),
+            Config::new("anonymous-id".to_string()),
+            crate::types::Context::default(),
+            Sampler::new(1.0, HashSet::new()),
+            EventRegistry::new(
+                Vec::new(),
+                EventRenames::default(),
+                semver::Version::new(1, 0, 0),
+                false,
+            ),
+            Some(ConsentPolicy::new(1)),
+        );
+        let statuses = replay(&rudder);
+        std::fs::remove_file(&path).unwrap();
+        assert!(statuses
+            .iter()
+            .all(|status| matches!(status, SendStatus::Dropped(DropReason::NoConsent))));
+        assert!(transport.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn resends_crash_reports_only_with_consent() {
+        let transport = Arc::new(RecordingTransport::default());
+        let rudder = RudderWrapper::new(
+            transport.clone(),
+            Config::new("anonymous-id".to_string()),
+            crate::types::Context::default(),
+            Sampler::new(1.0, HashSet::new()),
+            EventRegistry::new(
+                Vec::new(),
+                EventRenames::default(),
+                semver::Version::new(1, 0, 0),
+                false,
+            ),
+            Some(ConsentPolicy::new(1)),
+        );
+        let report = || {
+            rudderanalytics::message::Message::Track(rudderanalytics::message::Track {
+                anonymous_id: Some("anonymous-id".to_string()),
+                event: "Application Crashed".to_string(),
+                ..Default::default()
+            })
+        };
+        let resend = |message_id: &str| {
+            tauri::async_runtime::block_on(
+                rudder
+                    .resend(report(), message_id.to_string(), Priority::Critical)
+                    .into_future(),
+            )
+        };
+
+        assert!(matches!(
+            resend("revoked"),
+            SendStatus::Dropped(DropReason::NoConsent)
+        ));
+        assert!(transport.0.lock().unwrap().is_empty());
+
+        rudder.prompt_consent().unwrap();
+        rudder.answer_consent(true).unwrap();
+        assert!(matches!(resend("granted"), SendStatus::Delivered));
+        assert_eq!(transport.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn sends_one_identify_with_the_traits_of_the_user() {
+        let transport = Arc::new(RecordingTransport::default());
+        let rudder = wrapper(transport.clone(), Vec::new());
+        let identify = |plan: &str| crate::types::Identify {
+            traits: Some(serde_json::json!({ "plan": plan })),
+            ..Default::default()
+        };
+        let plan = |message: &rudderanalytics::message::Message| match message {
+            rudderanalytics::message::Message::Identify(identify) => {
+                identify.traits.as_ref().unwrap()["plan"].clone()
+            }
+            other => panic!("expected an identify event, got {:?}", other),
+        };
+
+        rudder.identify_user(Some("user-1".to_string()), Some(identify("free")));
+        rudder.identify_user(Some("user-1".to_string()), Some(identify("pro")));
+        rudder.set_user_id(Some("user-1".to_string()));
+        tauri::async_runtime::block_on(async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        });
+
+        // the deliveries run concurrently
+        let mut plans: Vec<_> = transport.0.lock().unwrap().iter().map(plan).collect();
+        plans.sort_by_key(|plan| plan.to_string());
+        assert_eq!(plans, ["free", "pro"]);
+    }
+
+    #[test]
+    fn wipes_the_identity_and_context() {
+        let rudder = wrapper(Arc::new(RecordingTransport::default()), Vec::new())
+            .with_experiments_in_context();
+        rudder.set_user_id(Some("user-1".to_string()));
+        rudder.increment_trait_counter("total_sessions");
+        rudder.add_to_context(
+            "campaign".to_string(),
+            serde_json::json!({ "source": "mail" }),
+        );
+        rudder.add_experiment("checkout", "b");
+        rudder.add_to_window_context("main", "screen".to_string(), "settings".into());
+
+        rudder.clear_data();
+
+        let data = rudder.export_data();
+        assert_ne!(data.anonymous_id, "anonymous-id");
+        assert_eq!(data.user_id, None);
+        assert_eq!(data.traits, serde_json::json!({}));
+        assert_eq!(data.context, serde_json::json!({}));
+        assert!(data.events.is_empty());
+        assert!(rudder.audit_log().is_empty());
+        assert!(rudder.window_context("main").is_empty());
+    }
+
+    #[test]
+    fn drops_batched_events_past_their_send_by_deadline() {
+        let now = chrono::Utc::now();
+        let stale = Track {
+            send_by: Some(now - chrono::Duration::seconds(1)),
+            ..track("Impression")
+        };
+        let mut message = Message::Batch(Batch {
+            batch: vec![
+                BatchMessage::Track(stale.clone()),
+                BatchMessage::Track(track("Signed In")),
+            ],
+            ..Batch::default()
+        });
+        assert!(!drop_past_send_by(&mut message, now));
+        assert_eq!(
+            message,
+            Message::Batch(Batch {
+                batch: vec![BatchMessage::Track(track("Signed In"))],
+                ..Batch::default()
+            })
+        );
+        assert!(drop_past_send_by(&mut Message::Track(stale), now));
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tracing::debug;
+
+use crate::types::Message;
+
+/// Decides which [Track](crate::types::Track) events are sent to the data plane.
+///
+/// Events that are marked with `force`, whose names are in the exemption list, or that a
+/// transformer exempted are forced: they bypass sampling, throttling and backpressure.
+/// Forced events are counted when a bypass happened, so forced sends can be audited.
+pub(crate) struct Sampler {
+    /// The fraction of events to send, between `0.0` and `1.0`.
+    rate: f64,
+    /// Event names that are never sampled out.
+    exempt_events: HashSet<String>,
+    /// The number of forced events that bypassed a drop.
+    forced_sends: AtomicU64,
+}
+
+impl Sampler {
+    pub fn new(rate: f64, exempt_events: HashSet<String>) -> Self {
+        Self {
+            rate: rate.clamp(0.0, 1.0),
+            exempt_events,
+            forced_sends: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns true if the message is a forced track event, see [Sampler].
+    pub fn is_forced(&self, message: &Message, exempt: bool) -> bool {
+        let Message::Track(track) = message else {
+            return false;
+        };
+        exempt || track.force || self.exempt_events.contains(&track.event)
+    }
+
+    /// Returns true if the message should be sent.
+    pub fn allow(&self, message: &Message, forced: bool) -> bool {
+        let Message::Track(track) = message else {
+            return true;
+        };
+        if self.sample() {
+            return true;
+        }
+        if forced {
+            self.record_forced_send();
+            return true;
+        }
+        debug!(event = %track.event, "event dropped by sampling");
+        false
+    }
+
+    /// Count a forced event that was sent instead of dropped.
+    pub fn record_forced_send(&self) {
+        self.forced_sends.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The number of forced events that bypassed a drop.
+    pub fn forced_sends(&self) -> u64 {
+        self.forced_sends.load(Ordering::Relaxed)
+    }
+
+    fn sample(&self) -> bool {
+        if self.rate >= 1.0 {
+            return true;
+        }
+        if self.rate <= 0.0 {
+            return false;
+        }
+        // uuid v4 is backed by a random source, so use it instead of pulling in `rand`
+        let roll = (uuid::Uuid::new_v4().as_u128() >> 64) as f64 / u64::MAX as f64;
+        roll < self.rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Track;
+
+    fn track(force: bool) -> Message {
+        Message::Track(Track {
+            event: "Exported".to_string(),
+            force,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn counts_forced_sends_only_when_sampling_is_bypassed() {
+        let sending = Sampler::new(1.0, HashSet::new());
+        assert!(sending.is_forced(&track(true), false));
+        assert!(sending.allow(&track(true), true));
+        assert_eq!(sending.forced_sends(), 0);
+
+        let dropping = Sampler::new(0.0, HashSet::from(["Exported".to_string()]));
+        assert!(dropping.is_forced(&track(false), false));
+        assert!(dropping.allow(&track(false), true));
+        assert!(!dropping.allow(&track(false), false));
+        assert_eq!(dropping.forced_sends(), 1);
+    }
+}
@@ -0,0 +1,203 @@
+//! Normalization of property values, see [PropertySanitizer].
+
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+
+use crate::{
+    transform::{TransformContext, Transformer},
+    types::{BatchMessage, Message},
+};
+
+/// A built-in [Transformer] that normalizes the properties and traits of events,
+/// for destinations that reject values the data plane accepts.
+///
+/// - Non-finite numbers become `null`.
+/// - Date strings, e.g. RFC 2822 or the output of JavaScript's `Date.toString()`,
+///   become ISO-8601 timestamps in UTC. `chrono` values are already serialized as ISO-8601.
+/// - Objects nested deeper than the maximum depth are flattened into dotted keys,
+///   e.g. `{ "a": { "b": 1 } }` becomes `{ "a.b": 1 }`.
+/// - Strings longer than the maximum length are truncated.
+///
+/// ```ignore
+/// .transformer(PropertySanitizer::new().max_depth(3).max_string_length(255))
+/// ```
+#[derive(Debug, Clone)]
+pub struct PropertySanitizer {
+    max_depth: usize,
+    max_string_length: usize,
+    normalize_dates: bool,
+}
+
+impl Default for PropertySanitizer {
+    fn default() -> Self {
+        Self {
+            max_depth: 5,
+            max_string_length: 1024,
+            normalize_dates: true,
+        }
+    }
+}
+
+impl PropertySanitizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flatten objects nested deeper than `depth` levels below the properties. Defaults to 5.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth.max(1);
+        self
+    }
+
+    /// Truncate strings to at most `length` characters. Defaults to 1024.
+    pub fn max_string_length(mut self, length: usize) -> Self {
+        self.max_string_length = length;
+        self
+    }
+
+    /// Convert date strings to ISO-8601 timestamps. Defaults to true.
+    pub fn normalize_dates(mut self, enabled: bool) -> Self {
+        self.normalize_dates = enabled;
+        self
+    }
+
+    fn sanitize(&self, value: &mut Value, depth: usize) {
+        match value {
+            Value::Number(number) if number.as_f64().is_some_and(|n| !n.is_finite()) => {
+                *value = Value::Null;
+            }
+            Value::String(string) => {
+                if let Some(date) = self.normalize_dates.then(|| iso_date(string)).flatten() {
+                    *string = date;
+                }
+                if let Some((end, _)) = string.char_indices().nth(self.max_string_length) {
+                    string.truncate(end);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.sanitize(item, depth + 1);
+                }
+            }
+            Value::Object(fields) => {
+                if depth >= self.max_depth {
+                    let mut flat = Map::new();
+                    flatten(String::new(), std::mem::take(fields), &mut flat);
+                    *fields = flat;
+                }
+                for field in fields.values_mut() {
+                    self.sanitize(field, depth + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn sanitize_fields(&self, fields: &mut Option<Value>) {
+        if let Some(Value::Object(fields)) = fields {
+            for field in fields.values_mut() {
+                self.sanitize(field, 1);
+            }
+        }
+    }
+
+    fn sanitize_batch_message(&self, message: &mut BatchMessage) {
+        match message {
+            BatchMessage::Identify(identify) => self.sanitize_fields(&mut identify.traits),
+            BatchMessage::Track(track) => self.sanitize_fields(&mut track.properties),
+            BatchMessage::Page(page) => self.sanitize_fields(&mut page.properties),
+            BatchMessage::Screen(screen) => self.sanitize_fields(&mut screen.properties),
+            BatchMessage::Group(group) => self.sanitize_fields(&mut group.traits),
+            BatchMessage::Alias(alias) => self.sanitize_fields(&mut alias.traits),
+        }
+    }
+}
+
+impl Transformer for PropertySanitizer {
+    fn transform(&self, message: &mut Message, _context: &TransformContext) -> bool {
+        match message {
+            Message::Identify(identify) => self.sanitize_fields(&mut identify.traits),
+            Message::Track(track) => self.sanitize_fields(&mut track.properties),
+            Message::Page(page) => self.sanitize_fields(&mut page.properties),
+            Message::Screen(screen) => self.sanitize_fields(&mut screen.properties),
+            Message::Group(group) => self.sanitize_fields(&mut group.traits),
+            Message::Alias(alias) => self.sanitize_fields(&mut alias.traits),
+            Message::Batch(batch) => {
+                for message in &mut batch.batch {
+                    self.sanitize_batch_message(message);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Move the leaves of `fields` into `flat` with dotted keys,
+/// arrays of objects or arrays are kept as JSON strings.
+fn flatten(prefix: String, fields: Map<String, Value>, flat: &mut Map<String, Value>) {
+    for (key, value) in fields {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Value::Object(fields) => flatten(key, fields, flat),
+            Value::Array(items) if items.iter().any(|i| i.is_object() || i.is_array()) => {
+                flat.insert(key, Value::String(Value::Array(items).to_string()));
+            }
+            value => {
+                flat.insert(key, value);
+            }
+        }
+    }
+}
+
+/// The date as an ISO-8601 timestamp in UTC, if the string is a date in a format other than ISO-8601.
+fn iso_date(value: &str) -> Option<String> {
+    // too short or long for a date, skips the parsing for most strings
+    if !(19..=80).contains(&value.len()) || DateTime::parse_from_rfc3339(value).is_ok() {
+        return None;
+    }
+    let date = DateTime::parse_from_rfc2822(value)
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| {
+            // `Date.toString()`, e.g. `Tue Jan 02 2024 10:00:00 GMT+0100 (Central European Standard Time)`
+            let value = value.split(" (").next().unwrap_or(value);
+            DateTime::parse_from_str(value, "%a %b %d %Y %H:%M:%S GMT%z")
+                .map(|date| date.with_timezone(&Utc))
+        })
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").map(|date| date.and_utc())
+        })
+        .ok()?;
+    Some(date.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn normalizes_property_values() {
+        let sanitizer = PropertySanitizer::new().max_depth(2).max_string_length(24);
+        let mut properties = Some(json!({
+            "sent": "Tue, 2 Jan 2024 10:00:00 +0100",
+            "opened": "Tue Jan 02 2024 10:00:00 GMT+0100 (Central European Standard Time)",
+            "name": "a".repeat(30),
+            "nested": { "a": { "b": { "c": 1 } }, "list": [{ "d": 2 }] },
+        }));
+        sanitizer.sanitize_fields(&mut properties);
+        assert_eq!(
+            properties,
+            Some(json!({
+                "sent": "2024-01-02T09:00:00.000Z",
+                "opened": "2024-01-02T09:00:00.000Z",
+                "name": "a".repeat(24),
+                "nested": { "a": { "b.c": 1 }, "list": [{ "d": 2 }] },
+            }))
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    future::{Future, IntoFuture},
+    pin::Pin,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+
+use crate::transport::DeliveryError;
+
+/// Why a message was not delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum DropReason {
+    /// The user has not granted consent under the [ConsentPolicy](crate::ConsentPolicy).
+    NoConsent,
+    /// Do not track is set.
+    DoNotTrack,
+    /// The event passed its send by deadline, outlived its TTL or the maximum event age.
+    Expired,
+    /// A rule, transformer or middleware dropped the event, or the event is invalid.
+    Filtered,
+    /// The event was sampled out.
+    Sampled,
+    /// A low priority event was dropped while the data plane is throttling.
+    Throttled,
+    /// Too many deliveries are pending, see [Backpressure](crate::Backpressure),
+    /// or the queued event was evicted from the [memory budget](crate::RudderStackBuilder::memory_budget).
+    Backpressured,
+    /// A message with the same ID was delivered recently.
+    Duplicate,
+    /// Dry run mode is enabled, the payload is only logged.
+    DryRun,
+    /// Analytics are compiled out with the `noop` feature.
+    Disabled,
+}
+
+/// The delivery status of a message, see [SendResult].
+#[derive(Debug)]
+pub enum SendStatus {
+    /// The message is waiting to be delivered.
+    Queued,
+    /// The message was not delivered and will not be.
+    Dropped(DropReason),
+    /// The data plane accepted the message, or it was recorded in mock mode.
+    Delivered,
+    /// The delivery failed.
+    Failed(SendError),
+}
+
+/// A message could not be delivered.
+#[derive(Debug, thiserror::Error)]
+pub enum SendError {
+    #[error(transparent)]
+    Delivery(#[from] DeliveryError),
+    #[error("the delivery task did not complete: {0}")]
+    Task(#[from] tauri::Error),
+    /// The serialized message exceeds the size limit of the RudderStack API,
+    /// see [RudderStackBuilder::truncate_properties](crate::RudderStackBuilder::truncate_properties).
+    #[error("the message is {size} bytes, the data plane accepts at most {max} bytes")]
+    PayloadTooLarge { size: usize, max: usize },
+    /// Returned by [AnalyticsExt::send_analytic_blocking](crate::AnalyticsExt::send_analytic_blocking),
+    /// and by queued deliveries dropped while waiting, awaited as [SendStatus::Dropped].
+    #[error("the message was dropped: {0:?}")]
+    Dropped(DropReason),
+    /// Returned by [AnalyticsExt::send_analytic_blocking_timeout](crate::AnalyticsExt::send_analytic_blocking_timeout)
+    /// if the message was not delivered in time, e.g. while offline.
+    #[error("the message was not delivered within {0:?}")]
+    TimedOut(Duration),
+}
+
+impl SendStatus {
+    /// The status of an awaited message as a result.
+    pub(crate) fn into_result(self) -> Result<(), SendError> {
+        match self {
+            SendStatus::Delivered => Ok(()),
+            SendStatus::Dropped(reason) => Err(SendError::Dropped(reason)),
+            SendStatus::Failed(err) => Err(err),
+            SendStatus::Queued => unreachable!("an awaited message is not queued"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SendState {
+    Queued(JoinHandle<Result<(), SendError>>),
+    Dropped(DropReason),
+    Delivered,
+    TooLarge { size: usize, max: usize },
+}
+
+/// A message handed to the plugin for delivery.
+///
+/// Await it for the final [SendStatus]:
+///
+/// ```ignore
+/// match app.track(event).await {
+///     SendStatus::Delivered => {}
+///     SendStatus::Dropped(reason) => tracing::debug!(?reason, "event dropped"),
+///     SendStatus::Failed(err) => tracing::error!("event failed: {err}"),
+///     SendStatus::Queued => unreachable!(),
+/// }
+/// ```
+///
+/// Dropping it does not cancel the delivery.
+#[derive(Debug)]
+pub struct SendResult {
+    message_id: String,
+    state: SendState,
+}
+
+impl SendResult {
+    pub(crate) fn queued(message_id: String, handle: JoinHandle<Result<(), SendError>>) -> Self {
+        Self {
+            message_id,
+            state: SendState::Queued(handle),
+        }
+    }
+
+    pub(crate) fn dropped(message_id: String, reason: DropReason) -> Self {
+        Self {
+            message_id,
+            state: SendState::Dropped(reason),
+        }
+    }
+
+    pub(crate) fn delivered(message_id: String) -> Self {
+        Self {
+            message_id,
+            state: SendState::Delivered,
+        }
+    }
+
+    pub(crate) fn too_large(message_id: String, size: usize, max: usize) -> Self {
+        Self {
+            message_id,
+            state: SendState::TooLarge { size, max },
+        }
+    }
+
+    /// The ID generated for the message, used to deduplicate deliveries. \
+    /// The default transport sends it as the `messageId` of the payload.
+    pub fn message_id(&self) -> &str {
+        &self.message_id
+    }
+
+    /// The status without waiting for the delivery, [SendStatus::Queued] until the result is awaited.
+    pub fn status(&self) -> SendStatus {
+        match &self.state {
+            SendState::Queued(_) => SendStatus::Queued,
+            SendState::Dropped(reason) => SendStatus::Dropped(*reason),
+            SendState::Delivered => SendStatus::Delivered,
+            SendState::TooLarge { size, max } => {
+                SendStatus::Failed(SendError::PayloadTooLarge { size, max })
+            }
+        }
+    }
+}
+
+impl IntoFuture for SendResult {
+    type Output = SendStatus;
+    type IntoFuture = Pin<Box<dyn Future<Output = SendStatus> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            match self.state {
+                SendState::Queued(handle) => match handle.await {
+                    Ok(Ok(())) => SendStatus::Delivered,
+                    Ok(Err(SendError::Dropped(reason))) => SendStatus::Dropped(reason),
+                    Ok(Err(err)) => SendStatus::Failed(err),
+                    Err(err) => SendStatus::Failed(err.into()),
+                },
+                SendState::Dropped(reason) => SendStatus::Dropped(reason),
+                SendState::Delivered => SendStatus::Delivered,
+                SendState::TooLarge { size, max } => {
+                    SendStatus::Failed(SendError::PayloadTooLarge { size, max })
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,98 @@
+//! Where the config and the identity of the user are persisted.
+
+use serde_json::Value;
+
+/// The error of a [ConfigStore].
+pub type StoreError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Persists the config of the plugin: the identity, consent state, trait counters and audit log,
+/// e.g. in the app's own database or a remote profile service. \
+/// By default the config is stored in `tauri-rudderstack.json` in the app config dir.
+///
+/// The config is passed as JSON, its layout is not a stable format and should be stored as is.
+pub trait ConfigStore: Send + Sync + 'static {
+    /// Load the stored config, `None` if nothing was stored yet.
+    fn load(&self) -> Result<Option<Value>, StoreError>;
+
+    /// Replace the stored config.
+    fn save(&self, config: &Value) -> Result<(), StoreError>;
+
+    /// Delete the stored config, used by [AnalyticsExt::wipe_analytics_data](crate::AnalyticsExt::wipe_analytics_data).
+    fn delete(&self) -> Result<(), StoreError>;
+}
+
+/// Where the anonymous ID and user ID are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Storage {
+    /// In the config file in the app config dir.
+    #[default]
+    File,
+    /// In the OS keychain or credential manager, the rest of the config stays in the config file.
+    /// The app identifier is used as the service name.
+    #[cfg(feature = "keyring")]
+    Keyring,
+    /// Only in memory, nothing is written to disk and every run gets a fresh anonymous ID,
+    /// e.g. for kiosks and shared machines.
+    Memory,
+}
+
+#[cfg(feature = "keyring")]
+pub(crate) mod keyring {
+    use keyring::Entry;
+
+    const ANONYMOUS_ID: &str = "anonymous_id";
+    const USER_ID: &str = "user_id";
+
+    /// `anonymous_id` for the default instance, `anonymous_id-<name>` for named ones.
+    fn entry(service: &str, instance: Option<&str>, name: &str) -> keyring::Result<Entry> {
+        match instance {
+            Some(instance) => Entry::new(service, &format!("{name}-{instance}")),
+            None => Entry::new(service, name),
+        }
+    }
+
+    /// The stored anonymous ID and user ID, the anonymous ID is `None` if it was never stored.
+    pub fn load(
+        service: &str,
+        instance: Option<&str>,
+    ) -> keyring::Result<(Option<String>, Option<String>)> {
+        Ok((
+            get(&entry(service, instance, ANONYMOUS_ID)?)?,
+            get(&entry(service, instance, USER_ID)?)?,
+        ))
+    }
+
+    pub fn save(
+        service: &str,
+        instance: Option<&str>,
+        anonymous_id: &str,
+        user_id: Option<&str>,
+    ) -> keyring::Result<()> {
+        entry(service, instance, ANONYMOUS_ID)?.set_password(anonymous_id)?;
+        let user_entry = entry(service, instance, USER_ID)?;
+        match user_id {
+            Some(user_id) => user_entry.set_password(user_id),
+            None => delete(&user_entry),
+        }
+    }
+
+    pub fn clear(service: &str, instance: Option<&str>) -> keyring::Result<()> {
+        delete(&entry(service, instance, ANONYMOUS_ID)?)?;
+        delete(&entry(service, instance, USER_ID)?)
+    }
+
+    fn get(entry: &Entry) -> keyring::Result<Option<String>> {
+        match entry.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn delete(entry: &Entry) -> keyring::Result<()> {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
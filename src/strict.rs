@@ -0,0 +1,107 @@
+//! Strict mode, which turns instrumentation mistakes into panics in debug builds.
+
+use std::{collections::HashSet, fmt::Debug};
+
+use serde_json::Value;
+
+use crate::types::Message;
+
+/// The instrumentation problems of a message that are not covered by the tracking plan. \
+/// `consent_destinations` are the destinations mapped to a consent category by a
+/// [DestinationConsent](crate::DestinationConsent) transformer, if one is added
+/// the destinations the message enables must be mapped.
+/// The size is checked when the message is sent, see [too_large].
+pub(crate) fn problems(message: &Message, consent_destinations: &HashSet<String>) -> Vec<String> {
+    let mut problems = Vec::new();
+    let name = match message {
+        Message::Track(track) => Some(&track.event),
+        Message::Page(page) => Some(&page.name),
+        Message::Screen(screen) => Some(&screen.name),
+        _ => None,
+    };
+    if name.is_some_and(|name| name.trim().is_empty()) {
+        problems.push("the event name is empty".to_string());
+    }
+    if !consent_destinations.is_empty() {
+        let mut unmapped: Vec<_> = message
+            .all_integrations()
+            .into_iter()
+            .filter_map(|integrations| integrations.as_ref()?.as_object())
+            .flatten()
+            .filter(|(destination, enabled)| {
+                destination.as_str() != "All"
+                    && !matches!(enabled, Value::Bool(false) | Value::Null)
+                    && !consent_destinations.contains(*destination)
+            })
+            .map(|(destination, _)| destination.as_str())
+            .collect();
+        unmapped.sort_unstable();
+        unmapped.dedup();
+        for destination in unmapped {
+            problems.push(format!(
+                "the destination `{destination}` is not mapped to a consent category"
+            ));
+        }
+    }
+    problems
+}
+
+/// The problem of a message that is still too large after truncating its properties,
+/// `max` is the limit of the message type, see [max_bytes](crate::payload_size::max_bytes).
+pub(crate) fn too_large(size: usize, max: usize) -> String {
+    format!("the payload is {size} bytes, more than the {max} bytes accepted by the data plane")
+}
+
+/// Panic with the problems of the message, at the call site that sent it.
+#[track_caller]
+pub(crate) fn fail(message: &impl Debug, problems: &[String]) -> ! {
+    panic!(
+        "invalid analytics event (strict mode):\n  - {}\nevent: {:?}",
+        problems.join("\n  - "),
+        message
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::types::{Batch, BatchMessage, Track};
+
+    fn track(event: &str, integrations: Option<Value>) -> Track {
+        Track {
+            event: event.to_string(),
+            integrations,
+            ..Track::default()
+        }
+    }
+
+    #[test]
+    fn flags_empty_event_names() {
+        let problems = problems(&Message::Track(track(" ", None)), &HashSet::new());
+        assert_eq!(problems, ["the event name is empty"]);
+    }
+
+    #[test]
+    fn flags_destinations_without_a_consent_category() {
+        let mapped = HashSet::from(["Braze".to_string()]);
+        let integrations = json!({ "All": true, "Braze": true, "Amplitude": false });
+        let message = Message::Track(track("Signed In", Some(integrations)));
+        assert!(problems(&message, &mapped).is_empty());
+
+        let message = Message::Batch(Batch {
+            batch: vec![
+                BatchMessage::Track(track("Signed In", None)),
+                BatchMessage::Track(track("Signed Out", Some(json!({ "Mixpanel": true })))),
+            ],
+            ..Batch::default()
+        });
+        assert_eq!(
+            problems(&message, &mapped),
+            ["the destination `Mixpanel` is not mapped to a consent category"]
+        );
+        // without a DestinationConsent transformer every destination may be enabled
+        assert!(problems(&message, &HashSet::new()).is_empty());
+    }
+}
@@ -0,0 +1,227 @@
+//! Helpers to test analytics in an app without a live write key.
+//!
+//! ```ignore
+//! use tauri_plugin_rudderstack::testing::TestHarness;
+//!
+//! let harness = TestHarness::new();
+//! sign_in(harness.handle());
+//! harness
+//!     .expect_track("Signed In")
+//!     .with_property("method", "oauth");
+//! ```
+
+use rudderanalytics::message::{BatchMessage, Message, Track};
+use serde_json::Value;
+use tauri::{
+    test::{mock_builder, mock_context, noop_assets, MockRuntime},
+    App, AppHandle,
+};
+
+use crate::{rudderanalytics, AnalyticsExt, RudderStackBuilder, Storage};
+
+/// A mock app with the plugin installed in [mock mode](RudderStackBuilder::mock).
+///
+/// Outgoing messages are recorded instead of sent,
+/// use [TestHarness::expect_track] to assert on them.
+pub struct TestHarness {
+    app: App<MockRuntime>,
+}
+
+impl TestHarness {
+    /// Builds the plugin with a placeholder data plane and write key and [Storage::Memory].
+    pub fn new() -> Self {
+        Self::with_builder(
+            RudderStackBuilder::new("http://localhost", "test-write-key").storage(Storage::Memory),
+        )
+    }
+
+    /// Builds the plugin from your own builder, mock mode is always enabled.
+    pub fn with_builder(builder: RudderStackBuilder) -> Self {
+        let app = mock_builder()
+            .plugin(builder.mock(true).build())
+            .build(mock_context(noop_assets()))
+            .expect("failed to build the mock app");
+        Self { app }
+    }
+
+    /// The mock app, e.g. to manage your own state.
+    pub fn app(&self) -> &App<MockRuntime> {
+        &self.app
+    }
+
+    /// Pass this to the code under test.
+    pub fn handle(&self) -> &AppHandle<MockRuntime> {
+        self.app.handle()
+    }
+
+    /// The messages recorded so far.
+    pub fn messages(&self) -> Vec<Message> {
+        self.handle().recorded_events()
+    }
+
+    /// Forget the messages recorded so far.
+    pub fn clear(&self) {
+        self.handle().clear_recorded_events();
+    }
+
+    /// The recorded [Track] messages, including those sent in batches.
+    pub fn tracks(&self) -> Vec<Track> {
+        self.messages()
+            .into_iter()
+            .flat_map(|message| match message {
+                Message::Track(track) => vec![track],
+                Message::Batch(batch) => batch
+                    .batch
+                    .into_iter()
+                    .filter_map(|message| match message {
+                        BatchMessage::Track(track) => Some(track),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Panics if no track event with this name was recorded.
+    pub fn expect_track(&self, event: &str) -> TrackExpectation {
+        let tracks: Vec<Track> = self
+            .tracks()
+            .into_iter()
+            .filter(|track| track.event == event)
+            .collect();
+        assert!(
+            !tracks.is_empty(),
+            "expected track event {:?} to be recorded, recorded events: {:#?}",
+            event,
+            self.messages()
+        );
+        TrackExpectation {
+            event: event.to_string(),
+            tracks,
+        }
+    }
+
+    /// Panics if a track event with this name was recorded.
+    pub fn expect_no_track(&self, event: &str) {
+        assert!(
+            !self.tracks().iter().any(|track| track.event == event),
+            "expected track event {:?} not to be recorded",
+            event
+        );
+    }
+}
+
+impl Default for TestHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The recorded track events matching an expectation, narrowed by each assertion.
+#[derive(Debug)]
+pub struct TrackExpectation {
+    event: String,
+    tracks: Vec<Track>,
+}
+
+impl TrackExpectation {
+    /// Panics if none of the matching events has this property value.
+    pub fn with_property(self, key: &str, value: impl Into<Value>) -> Self {
+        let value = value.into();
+        self.narrow(
+            |track| property(track, key) == Some(&value),
+            format!("property {:?} = {}", key, value),
+        )
+    }
+
+    /// Panics if none of the matching events has this property.
+    pub fn with_property_key(self, key: &str) -> Self {
+        self.narrow(
+            |track| property(track, key).is_some(),
+            format!("property {:?}", key),
+        )
+    }
+
+    /// Panics if none of the matching events was sent for this user ID.
+    pub fn with_user_id(self, user_id: &str) -> Self {
+        self.narrow(
+            |track| track.user_id.as_deref() == Some(user_id),
+            format!("user ID {:?}", user_id),
+        )
+    }
+
+    /// Panics unless exactly `count` events match.
+    pub fn times(self, count: usize) -> Self {
+        assert_eq!(
+            self.tracks.len(),
+            count,
+            "expected track event {:?} to be recorded {} times, recorded: {:#?}",
+            self.event,
+            count,
+            self.tracks
+        );
+        self
+    }
+
+    /// The matching events.
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn narrow(self, matches: impl Fn(&Track) -> bool, expected: String) -> Self {
+        let tracks: Vec<Track> = self
+            .tracks
+            .iter()
+            .filter(|track| matches(track))
+            .cloned()
+            .collect();
+        assert!(
+            !tracks.is_empty(),
+            "expected track event {:?} with {}, recorded: {:#?}",
+            self.event,
+            expected,
+            self.tracks
+        );
+        Self {
+            event: self.event,
+            tracks,
+        }
+    }
+}
+
+fn property<'a>(track: &'a Track, key: &str) -> Option<&'a Value> {
+    track
+        .properties
+        .as_ref()
+        .and_then(|properties| properties.get(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::types;
+
+    #[test]
+    fn asserts_on_recorded_tracks() {
+        let harness = TestHarness::new();
+        harness
+            .handle()
+            .send_analytic(types::Message::Track(types::Track {
+                event: "Signed In".to_string(),
+                properties: Some(json!({ "method": "oauth" })),
+                ..Default::default()
+            }));
+
+        harness
+            .expect_track("Signed In")
+            .with_property("method", "oauth")
+            .times(1);
+        harness.expect_no_track("Signed Out");
+
+        harness.clear();
+        assert!(harness.messages().is_empty());
+    }
+}
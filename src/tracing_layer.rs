@@ -0,0 +1,157 @@
+//! Forwarding of log records as track events, see [AnalyticsLayer].
+
+use std::{
+    cell::Cell,
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+use serde_json::{json, Map, Value};
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+use crate::{rudderanalytics, types::Track};
+
+/// The name of the track events.
+const EVENT: &str = "Log Recorded";
+
+/// Records of these targets are never sent, sending them could log again.
+const IGNORED_TARGETS: &[&str] = &[
+    "tauri_plugin_rudderstack",
+    "rudderanalytics",
+    "reqwest",
+    "hyper",
+];
+
+type Sink = Box<dyn Fn(Track) + Send + Sync>;
+
+thread_local! {
+    static SENDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A [tracing_subscriber::Layer] that sends matching log records as `Log Recorded` track events,
+/// with the `level`, `target`, `message`, `file`, `line` and other fields as properties.
+///
+/// The subscriber is usually set up before the app, so the layer only sends records once it is
+/// passed to [RudderStackBuilder::tracing_layer](crate::RudderStackBuilder::tracing_layer)
+/// and the plugin is set up.
+///
+/// ```ignore
+/// let layer = AnalyticsLayer::new().level(Level::ERROR).target("my_app");
+/// tracing_subscriber::registry().with(layer.clone()).init();
+///
+/// let rudderstack = RudderStackBuilder::new(data_plane, key).tracing_layer(layer).build();
+/// ```
+#[derive(Clone)]
+pub struct AnalyticsLayer {
+    sink: Arc<OnceLock<Sink>>,
+    level: Level,
+    targets: Vec<String>,
+}
+
+impl Default for AnalyticsLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnalyticsLayer {
+    pub fn new() -> Self {
+        Self {
+            sink: Arc::default(),
+            level: Level::WARN,
+            targets: Vec::new(),
+        }
+    }
+
+    /// Send records of this level and more severe levels, `WARN` by default.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Only send records of targets starting with this prefix, e.g. the crate name of the app.
+    /// Records of all targets are sent by default.
+    pub fn target(mut self, prefix: impl Into<String>) -> Self {
+        self.targets.push(prefix.into());
+        self
+    }
+
+    /// Send the records with this function from now on.
+    pub(crate) fn connect(&self, sink: impl Fn(Track) + Send + Sync + 'static) {
+        if self.sink.set(Box::new(sink)).is_err() {
+            tracing::warn!("The tracing layer is already used by another instance");
+        }
+    }
+
+    fn is_enabled(&self, metadata: &Metadata) -> bool {
+        let target = metadata.target();
+        *metadata.level() <= self.level
+            && !IGNORED_TARGETS
+                .iter()
+                .any(|ignored| target.starts_with(ignored))
+            && (self.targets.is_empty()
+                || self
+                    .targets
+                    .iter()
+                    .any(|prefix| target.starts_with(prefix.as_str())))
+    }
+}
+
+impl<S: Subscriber> Layer<S> for AnalyticsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let Some(sink) = self.sink.get() else {
+            return;
+        };
+        if !self.is_enabled(metadata) || SENDING.with(|sending| sending.replace(true)) {
+            return;
+        }
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        let mut properties = fields.0;
+        properties.insert("level".to_string(), json!(metadata.level().as_str()));
+        properties.insert("target".to_string(), json!(metadata.target()));
+        properties.insert("file".to_string(), json!(metadata.file()));
+        properties.insert("line".to_string(), json!(metadata.line()));
+        sink(Track {
+            event: EVENT.to_string(),
+            properties: Some(Value::Object(properties)),
+            ..Track::default()
+        });
+        SENDING.with(|sending| sending.set(false));
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor(Map<String, Value>);
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), json!(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+}
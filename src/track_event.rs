@@ -0,0 +1,61 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::types::Track;
+
+/// A strongly typed [Track] event.
+///
+/// Implement this on your own event structs instead of hand building the `properties` json.
+/// The struct fields are serialized as the event properties.
+/// Since the trait requires [specta::Type], the same structs can be added to your
+/// `tauri_specta::Builder` so they flow into your TypeScript bindings.
+/// With the `build-types` feature, an `EventCatalog` also exports a typed `track` helper per event.
+///
+/// ```ignore
+/// #[derive(serde::Serialize, specta::Type)]
+/// struct Purchase {
+///     order_id: String,
+///     total: f64,
+/// }
+///
+/// impl TrackEvent for Purchase {
+///     const NAME: &'static str = "Purchase";
+/// }
+///
+/// app.track(Purchase { order_id: "1".into(), total: 9.99 });
+/// ```
+pub trait TrackEvent: Serialize + specta::Type {
+    /// The name of the event, also used for its helper in an `EventCatalog`.
+    const NAME: &'static str;
+
+    /// The name of the event being tracked. \
+    /// Defaults to [TrackEvent::NAME].
+    fn event_name(&self) -> &str {
+        Self::NAME
+    }
+
+    /// The properties associated with the event. \
+    /// Defaults to the serialized struct.
+    fn properties(&self) -> Option<Value> {
+        match serde_json::to_value(self) {
+            Ok(Value::Null) => None,
+            Ok(value) => Some(value),
+            Err(err) => {
+                tracing::error!("Failed to serialize track event properties: {:?}", err);
+                None
+            }
+        }
+    }
+
+    /// Convert the typed event into a [Track] event.
+    fn into_track(self) -> Track
+    where
+        Self: Sized,
+    {
+        Track {
+            event: self.event_name().to_string(),
+            properties: self.properties(),
+            ..Track::default()
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::{collections::HashMap, path::Path};
+
+use serde_json::Value;
+use tracing::warn;
+
+use crate::types::{BatchMessage, Message, Track};
+
+/// What to do with events that violate the tracking plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ViolationBehavior {
+    /// Drop the event.
+    Drop,
+    /// Log a warning and send the event unchanged.
+    #[default]
+    Warn,
+    /// Send the event with the violations added to the `violations` context field.
+    Forward,
+}
+
+/// A tracking plan holds a JSON schema per event name.
+/// The properties of [Track](crate::types::Track) events are validated against the schema before they are sent.
+///
+/// Events without a schema are not validated.
+pub struct TrackingPlan {
+    schemas: HashMap<String, jsonschema::Validator>,
+    behavior: ViolationBehavior,
+}
+
+impl TrackingPlan {
+    /// Create an empty tracking plan.
+    pub fn new(behavior: ViolationBehavior) -> Self {
+        Self {
+            schemas: HashMap::new(),
+            behavior,
+        }
+    }
+
+    /// Create a tracking plan from a json object that maps event names to schemas. \
+    /// Use this with `include_str!` to load the tracking plan at build time.
+    pub fn from_json(json: &str, behavior: ViolationBehavior) -> Result<Self, TrackingPlanError> {
+        let plan: HashMap<String, Value> = serde_json::from_str(json)?;
+        plan.into_iter()
+            .try_fold(Self::new(behavior), |plan, (event, schema)| {
+                plan.event_schema(event, &schema)
+            })
+    }
+
+    /// Load a tracking plan from a json file at runtime. See [TrackingPlan::from_json].
+    pub fn load(
+        path: impl AsRef<Path>,
+        behavior: ViolationBehavior,
+    ) -> Result<Self, TrackingPlanError> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json, behavior)
+    }
+
+    /// Add the schema for the properties of an event.
+    pub fn event_schema(
+        mut self,
+        event: impl Into<String>,
+        schema: &Value,
+    ) -> Result<Self, TrackingPlanError> {
+        let event = event.into();
+        let validator =
+            jsonschema::validator_for(schema).map_err(|err| TrackingPlanError::Schema {
+                event: event.clone(),
+                message: err.to_string(),
+            })?;
+        self.schemas.insert(event, validator);
+        Ok(self)
+    }
+
+    /// Validate the track events of the message against the tracking plan, also in a batch. \
+    /// Returns false if the message should be dropped. Invalid events are removed from a batch
+    /// with [ViolationBehavior::Drop], the batch is dropped if none is left.
+    pub(crate) fn validate(&self, message: &mut Message) -> bool {
+        match message {
+            Message::Track(track) => self.validate_track(track),
+            Message::Batch(batch) => {
+                batch.batch.retain_mut(|message| match message {
+                    BatchMessage::Track(track) => self.validate_track(track),
+                    _ => true,
+                });
+                !batch.batch.is_empty()
+            }
+            _ => true,
+        }
+    }
+
+    /// Apply the [ViolationBehavior] to the track event if it is invalid. \
+    /// Returns false if the event should be dropped.
+    fn validate_track(&self, track: &mut Track) -> bool {
+        let violations = self.track_violations(track);
+        if violations.is_empty() {
+            return true;
+        }
+
+        warn!(event = %track.event, ?violations, "event violates the tracking plan");
+        match self.behavior {
+            ViolationBehavior::Drop => false,
+            ViolationBehavior::Warn => true,
+            ViolationBehavior::Forward => {
+                let context = track
+                    .context
+                    .get_or_insert_with(|| Value::Object(Default::default()));
+                if let Value::Object(context) = context {
+                    context.insert("violations".to_string(), violations.into());
+                }
+                true
+            }
+        }
+    }
+
+    /// The violations of the tracking plan by the track events of the message,
+    /// also in a batch, empty if they are valid.
+    pub(crate) fn violations(&self, message: &Message) -> Vec<String> {
+        match message {
+            Message::Track(track) => self.track_violations(track),
+            Message::Batch(batch) => batch
+                .batch
+                .iter()
+                .filter_map(|message| match message {
+                    BatchMessage::Track(track) => Some(track),
+                    _ => None,
+                })
+                .flat_map(|track| {
+                    self.track_violations(track)
+                        .into_iter()
+                        .map(|violation| format!("{}: {}", track.event, violation))
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn track_violations(&self, track: &Track) -> Vec<String> {
+        let Some(validator) = self.schemas.get(&track.event) else {
+            return Vec::new();
+        };
+
+        let properties = track
+            .properties
+            .clone()
+            .unwrap_or(Value::Object(Default::default()));
+        validator
+            .iter_errors(&properties)
+            .map(|err| format!("{}: {}", err.instance_path, err))
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrackingPlanError {
+    #[error("failed to read tracking plan {0}")]
+    Read(#[from] std::io::Error),
+    #[error("failed to parse tracking plan {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("invalid schema for event {event}: {message}")]
+    Schema { event: String, message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::types::{Batch, Page};
+
+    fn plan(behavior: ViolationBehavior) -> TrackingPlan {
+        TrackingPlan::new(behavior)
+            .event_schema(
+                "Signed In",
+                &json!({
+                    "type": "object",
+                    "properties": { "method": { "type": "string" } },
+                    "required": ["method"],
+                }),
+            )
+            .unwrap()
+    }
+
+    fn signed_in(properties: Value) -> Track {
+        Track {
+            event: "Signed In".to_string(),
+            properties: Some(properties),
+            ..Track::default()
+        }
+    }
+
+    #[test]
+    fn flags_missing_required_properties() {
+        let plan = plan(ViolationBehavior::Drop);
+        let message = Message::Track(signed_in(json!({})));
+        assert_eq!(plan.violations(&message).len(), 1);
+        assert!(!plan.validate(&mut message.clone()));
+
+        let message = Message::Track(signed_in(json!({ "method": "oauth" })));
+        assert!(plan.violations(&message).is_empty());
+    }
+
+    #[test]
+    fn flags_wrong_types() {
+        let plan = plan(ViolationBehavior::Forward);
+        let mut message = Message::Track(signed_in(json!({ "method": 1 })));
+        assert!(plan.validate(&mut message));
+        let Message::Track(track) = message else {
+            panic!("expected a track event");
+        };
+        let violations = &track.context.unwrap()["violations"];
+        assert_eq!(violations.as_array().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn does_not_validate_unknown_events() {
+        let plan = plan(ViolationBehavior::Drop);
+        let mut message = Message::Track(Track {
+            event: "Unknown".to_string(),
+            properties: Some(json!({ "method": 1 })),
+            ..Track::default()
+        });
+        assert!(plan.violations(&message).is_empty());
+        assert!(plan.validate(&mut message));
+    }
+
+    #[test]
+    fn validates_batched_track_events() {
+        let plan = plan(ViolationBehavior::Drop);
+        let valid = signed_in(json!({ "method": "oauth" }));
+        let mut message = Message::Batch(Batch {
+            batch: vec![
+                BatchMessage::Track(signed_in(json!({}))),
+                BatchMessage::Track(valid.clone()),
+                BatchMessage::Page(Page::default()),
+            ],
+            ..Batch::default()
+        });
+        assert_eq!(plan.violations(&message).len(), 1);
+        assert!(plan.validate(&mut message));
+        let Message::Batch(batch) = message else {
+            panic!("expected a batch");
+        };
+        assert_eq!(
+            batch.batch,
+            vec![
+                BatchMessage::Track(valid),
+                BatchMessage::Page(Page::default()),
+            ]
+        );
+
+        let mut message = Message::Batch(Batch {
+            batch: vec![BatchMessage::Track(signed_in(json!({ "method": 1 })))],
+            ..Batch::default()
+        });
+        assert!(!plan.validate(&mut message));
+    }
+}
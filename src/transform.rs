@@ -0,0 +1,340 @@
+use std::{cell::Cell, collections::HashMap};
+
+use serde_json::Value;
+
+use crate::{
+    consent::ConsentState,
+    priority::Priority,
+    types::{Batch, BatchMessage, Message, Screen},
+};
+
+/// The state available to a [Transformer].
+#[derive(Debug)]
+pub struct TransformContext<'a> {
+    /// The persisted consent state of the user.
+    pub consent: &'a ConsentState,
+    /// The sampling and rate limiting decision for the message, shared by all transformers.
+    pub decision: &'a SendDecision,
+}
+
+/// How a message is treated by sampling and rate limiting,
+/// set by [Transformer]s based on the content of the message.
+///
+/// ```ignore
+/// .transformer(|message: &mut Message, context: &TransformContext| {
+///     if is_error_from_paying_customer(message) {
+///         context.decision.exempt_from_sampling();
+///     }
+///     true
+/// })
+/// ```
+#[derive(Debug, Default)]
+pub struct SendDecision {
+    exempt_from_sampling: Cell<bool>,
+    priority: Cell<Priority>,
+}
+
+impl SendDecision {
+    pub(crate) fn new(priority: Priority) -> Self {
+        Self {
+            exempt_from_sampling: Cell::new(false),
+            priority: Cell::new(priority),
+        }
+    }
+
+    /// Always send the message, like a [Track](crate::types::Track) event with `force` set.
+    pub fn exempt_from_sampling(&self) {
+        self.exempt_from_sampling.set(true);
+    }
+
+    /// Drop the message instead of delaying it while the data plane is throttling,
+    /// like [Priority::Debug].
+    pub fn downgrade_priority(&self) {
+        self.priority.set(Priority::Debug);
+    }
+
+    /// Set the [Priority] of the message, e.g. to deliver the events of paying customers first.
+    pub fn set_priority(&self, priority: Priority) {
+        self.priority.set(priority);
+    }
+
+    /// Returns true if a transformer exempted the message from sampling.
+    pub fn is_exempt_from_sampling(&self) -> bool {
+        self.exempt_from_sampling.get()
+    }
+
+    /// The priority of the message, initially the one of the event.
+    pub fn priority(&self) -> Priority {
+        self.priority.get()
+    }
+
+    /// Returns true if the message has [Priority::Debug], e.g. after a transformer downgraded it.
+    pub fn is_low_priority(&self) -> bool {
+        self.priority.get() == Priority::Debug
+    }
+}
+
+/// A transformer modifies events before they are sent.
+///
+/// Transformers run in the order they are added, after the event registry and tracking plan.
+/// Any `Fn(&mut Message, &TransformContext) -> bool` can be used as a transformer.
+pub trait Transformer: Send + Sync + 'static {
+    /// Modify the message. Return false to drop it.
+    fn transform(&self, message: &mut Message, context: &TransformContext) -> bool;
+}
+
+impl<F> Transformer for F
+where
+    F: Fn(&mut Message, &TransformContext) -> bool + Send + Sync + 'static,
+{
+    fn transform(&self, message: &mut Message, context: &TransformContext) -> bool {
+        self(message, context)
+    }
+}
+
+/// A built-in [Transformer] that maps consent categories to destinations.
+///
+/// For every category the user has not granted, the mapped destinations are disabled
+/// in the `integrations` object of the event, and of every event in a batch.
+///
+/// ```ignore
+/// let transformer = DestinationConsent::new()
+///     .category("marketing", ["Braze", "Google Ads"])
+///     .category("analytics", ["Amplitude"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DestinationConsent {
+    categories: HashMap<String, Vec<String>>,
+}
+
+impl DestinationConsent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map a consent category to the destinations that require it.
+    pub fn category<I, S>(mut self, category: impl Into<String>, destinations: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.categories
+            .entry(category.into())
+            .or_default()
+            .extend(destinations.into_iter().map(Into::into));
+        self
+    }
+}
+
+impl DestinationConsent {
+    /// The destinations mapped to a consent category.
+    pub(crate) fn destinations(&self) -> impl Iterator<Item = &String> {
+        self.categories.values().flatten()
+    }
+}
+
+impl Transformer for DestinationConsent {
+    fn transform(&self, message: &mut Message, context: &TransformContext) -> bool {
+        let denied = self
+            .categories
+            .iter()
+            .filter(|(category, _)| !context.consent.is_category_granted(category))
+            .flat_map(|(_, destinations)| destinations);
+
+        let mut all_integrations = message.all_integrations_mut();
+        for destination in denied {
+            for integrations in all_integrations.iter_mut() {
+                let integrations =
+                    integrations.get_or_insert_with(|| Value::Object(Default::default()));
+                if let Value::Object(integrations) = integrations {
+                    integrations.insert(destination.clone(), Value::Bool(false));
+                }
+            }
+        }
+        true
+    }
+}
+
+/// How a [PageToScreen] transformer sends Page events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageMode {
+    /// Send Page events unchanged.
+    Page,
+    /// Convert Page events to Screen events.
+    Screen,
+    /// Send both a Page and a Screen event, in one batch.
+    Both,
+}
+
+impl PageMode {
+    fn page(self) -> bool {
+        matches!(self, PageMode::Page | PageMode::Both)
+    }
+
+    fn screen(self) -> bool {
+        matches!(self, PageMode::Screen | PageMode::Both)
+    }
+}
+
+/// A built-in [Transformer] that converts Page events to Screen events,
+/// for destinations that treat desktop apps like mobile apps and expect screen calls.
+///
+/// The mode can be overridden per platform and per destination,
+/// the destinations are routed with the `integrations` object of the events.
+///
+/// ```ignore
+/// let transformer = PageToScreen::new(PageMode::Screen)
+///     .platform("linux", PageMode::Both)
+///     .destination("Google Analytics 4", PageMode::Page);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PageToScreen {
+    mode: PageMode,
+    platforms: HashMap<String, PageMode>,
+    destinations: HashMap<String, PageMode>,
+}
+
+impl PageToScreen {
+    /// Use this mode for Page events on all platforms.
+    pub fn new(mode: PageMode) -> Self {
+        Self {
+            mode,
+            platforms: HashMap::new(),
+            destinations: HashMap::new(),
+        }
+    }
+
+    /// Use a different mode on this platform, named like [std::env::consts::OS], e.g. `"macos"`.
+    pub fn platform(mut self, os: impl Into<String>, mode: PageMode) -> Self {
+        self.platforms.insert(os.into(), mode);
+        self
+    }
+
+    /// Use a different mode for this destination.
+    pub fn destination(mut self, destination: impl Into<String>, mode: PageMode) -> Self {
+        self.destinations.insert(destination.into(), mode);
+        self
+    }
+
+    /// The mode on the current platform.
+    fn mode(&self) -> PageMode {
+        self.platforms
+            .get(std::env::consts::OS)
+            .copied()
+            .unwrap_or(self.mode)
+    }
+
+    /// Route the event to the destinations for which `wants` is true.
+    /// Explicitly disabled destinations stay disabled. \
+    /// Returns false if no destination wants the event.
+    fn route(
+        &self,
+        integrations: &mut Option<Value>,
+        mode: PageMode,
+        wants: fn(PageMode) -> bool,
+    ) -> bool {
+        let mut routes = serde_json::Map::new();
+        if !wants(mode) {
+            routes.insert("All".to_string(), Value::Bool(false));
+        }
+        for (destination, destination_mode) in &self.destinations {
+            if wants(*destination_mode) != wants(mode) {
+                routes.insert(destination.clone(), Value::Bool(wants(*destination_mode)));
+            }
+        }
+        let wanted = wants(mode) || routes.values().any(|route| route == &Value::Bool(true));
+        if routes.is_empty() {
+            return wanted;
+        }
+
+        let integrations = integrations.get_or_insert_with(|| Value::Object(Default::default()));
+        if let Value::Object(integrations) = integrations {
+            for (destination, route) in routes {
+                if integrations.get(&destination) != Some(&Value::Bool(false)) {
+                    integrations.insert(destination, route);
+                }
+            }
+        }
+        wanted
+    }
+}
+
+impl Transformer for PageToScreen {
+    fn transform(&self, message: &mut Message, _context: &TransformContext) -> bool {
+        let Message::Page(page) = message else {
+            return true;
+        };
+        let mode = self.mode();
+        let mut page = page.clone();
+        let mut screen = Screen {
+            name: page.name.clone(),
+            properties: page.properties.clone(),
+            anonymous_id: page.anonymous_id.clone(),
+            original_timestamp: page.original_timestamp,
+            context: page.context.clone(),
+            integrations: page.integrations.clone(),
+        };
+        let send_page = self.route(&mut page.integrations, mode, PageMode::page);
+        let send_screen = self.route(&mut screen.integrations, mode, PageMode::screen);
+
+        *message = match (send_page, send_screen) {
+            (true, true) => Message::Batch(Batch {
+                original_timestamp: page.original_timestamp,
+                batch: vec![BatchMessage::Page(page), BatchMessage::Screen(screen)],
+                ..Batch::default()
+            }),
+            (false, true) => Message::Screen(screen),
+            _ => Message::Page(page),
+        };
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::types::{Page, Track};
+
+    #[test]
+    fn destination_consent_disables_every_batch_item() {
+        let mut consent = ConsentState::default();
+        consent.set_category("marketing", false);
+        let decision = SendDecision::default();
+        let context = TransformContext {
+            consent: &consent,
+            decision: &decision,
+        };
+        let mut message = Message::Batch(Batch {
+            batch: vec![
+                BatchMessage::Track(Track {
+                    event: "Clicked".to_string(),
+                    ..Track::default()
+                }),
+                BatchMessage::Page(Page {
+                    name: "Home".to_string(),
+                    integrations: Some(json!({ "Amplitude": true })),
+                    ..Page::default()
+                }),
+            ],
+            ..Batch::default()
+        });
+
+        let transformer = DestinationConsent::new().category("marketing", ["Braze"]);
+        assert!(transformer.transform(&mut message, &context));
+
+        let Message::Batch(batch) = &mut message else {
+            panic!("expected a batch");
+        };
+        assert_eq!(batch.integrations, Some(json!({ "Braze": false })));
+        assert_eq!(
+            batch.batch[0].integrations_mut(),
+            &Some(json!({ "Braze": false }))
+        );
+        assert_eq!(
+            batch.batch[1].integrations_mut(),
+            &Some(json!({ "Amplitude": true, "Braze": false }))
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[cfg(not(feature = "noop"))]
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+#[cfg(not(feature = "noop"))]
+use rudderanalytics::client::RudderAnalytics;
+use rudderanalytics::{errors::Error, message::Message};
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(feature = "noop"))]
+use crate::http_transport::HttpTransport;
+use crate::rudderanalytics;
+
+/// A delivery in progress, see [Transport::deliver_async].
+pub type DeliveryFuture = Pin<Box<dyn Future<Output = Result<(), DeliveryError>> + Send>>;
+
+/// A failed delivery of a [Transport].
+///
+/// The deliveries slow down while the data plane is throttling and the
+/// [circuit breaker](crate::RudderStackBuilder::circuit_breaker) opens while it is down,
+/// based on the HTTP status and the `Retry-After` delay of the response.
+/// Custom transports report them with [DeliveryError::with_status].
+#[derive(Debug, thiserror::Error)]
+#[error("{error}")]
+pub struct DeliveryError {
+    /// The HTTP status of the response, `None` if no response was received.
+    pub status: Option<u16>,
+    /// The delay of the `Retry-After` header of the response.
+    pub retry_after: Option<Duration>,
+    /// The underlying error.
+    #[source]
+    pub error: Error,
+}
+
+impl DeliveryError {
+    /// The sink responded with the HTTP `status` and the delay of its `Retry-After` header, if any.
+    pub fn with_status(status: u16, retry_after: Option<Duration>) -> Self {
+        Self {
+            status: Some(status),
+            retry_after,
+            error: Error::InvalidRequest(format!("status code: {status}")),
+        }
+    }
+
+    /// Returns true if the data plane responded with 429 Too Many Requests
+    /// or 503 Service Unavailable.
+    pub(crate) fn is_throttled(&self) -> bool {
+        matches!(self.status, Some(429 | 503))
+    }
+
+    /// Returns true if the delivery failed because the data plane is unavailable: no response
+    /// was received, a server error or 429 Too Many Requests. Rejected messages, e.g. with
+    /// 400 Bad Request or an invalid payload, would fail the same way on a healthy data plane.
+    pub(crate) fn is_outage(&self) -> bool {
+        match self.status {
+            Some(status) => status >= 500 || status == 429,
+            None => matches!(self.error, Error::SendRequestError(_)),
+        }
+    }
+}
+
+impl From<Error> for DeliveryError {
+    fn from(error: Error) -> Self {
+        let status = match &error {
+            Error::SendRequestError(err) => err.status().map(|status| status.as_u16()),
+            _ => None,
+        };
+        Self {
+            status,
+            retry_after: None,
+            error,
+        }
+    }
+}
+
+/// Delivers fully assembled messages.
+///
+/// The default transport sends the messages to the RudderStack data plane.
+/// Implement this to deliver messages to an alternative sink, e.g. in tests or with a custom auth scheme.
+/// `deliver` is called from a blocking thread, implement [Transport::deliver_async] as well
+/// if the sink can be reached without blocking.
+pub trait Transport: Send + Sync + 'static {
+    /// Deliver the message.
+    fn deliver(&self, message: &Message) -> Result<(), DeliveryError>;
+
+    /// Deliver the message without holding a thread while waiting for the sink.
+    /// Events are delivered with this method, see [Transport::deliver_with_id]. \
+    /// The default implementation calls [Transport::deliver] on a blocking thread.
+    fn deliver_async(self: Arc<Self>, message: Message) -> DeliveryFuture {
+        Box::pin(async move {
+            tauri::async_runtime::spawn_blocking(move || self.deliver(&message))
+                .await
+                .unwrap_or_else(|err| {
+                    Err(Error::InvalidRequest(format!("the delivery task failed: {err}")).into())
+                })
+        })
+    }
+
+    /// Deliver the message with the ID generated for it, so the sink can deduplicate
+    /// retries and resends. Events are delivered with this method, except crash reports. \
+    /// The default implementation ignores the ID and calls [Transport::deliver_async].
+    fn deliver_with_id(self: Arc<Self>, message: Message, _message_id: String) -> DeliveryFuture {
+        self.deliver_async(message)
+    }
+
+    /// Check that the sink is reachable, without delivering an event.
+    /// Transports that can't be checked return an error.
+    fn health_check(&self) -> Result<(), Error> {
+        Err(Error::InvalidRequest(
+            "health check is not supported by this transport".to_string(),
+        ))
+    }
+}
+
+#[cfg(not(feature = "noop"))]
+impl Transport for RudderAnalytics {
+    fn deliver(&self, message: &Message) -> Result<(), DeliveryError> {
+        Ok(self.send(message)?)
+    }
+
+    fn health_check(&self) -> Result<(), Error> {
+        let res = self
+            .client
+            .get(format!("{}/health", self.data_plane_url))
+            .send()?;
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(Error::InvalidRequest(format!(
+                "status code: {}",
+                res.status()
+            )))
+        }
+    }
+}
+
+/// Discards every message, the transport of the `noop` feature.
+#[cfg(feature = "noop")]
+pub(crate) struct NoopTransport;
+
+#[cfg(feature = "noop")]
+impl Transport for NoopTransport {
+    fn deliver(&self, _message: &Message) -> Result<(), DeliveryError> {
+        Ok(())
+    }
+
+    fn deliver_async(self: Arc<Self>, _message: Message) -> DeliveryFuture {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn health_check(&self) -> Result<(), Error> {
+        Err(Error::InvalidRequest(
+            "analytics are disabled by the noop feature".to_string(),
+        ))
+    }
+}
+
+/// The result of a connectivity check of the data plane.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Connectivity {
+    /// True if the data plane responded successfully.
+    pub reachable: bool,
+    /// The time the check took in milliseconds.
+    pub latency_ms: u32,
+    /// Why the data plane is not reachable.
+    pub error: Option<String>,
+}
+
+impl Connectivity {
+    /// Run the health check of the transport and measure its latency. Blocks the thread.
+    pub(crate) fn check(transport: &dyn Transport) -> Self {
+        let start = Instant::now();
+        let result = transport.health_check();
+        let latency_ms = start.elapsed().as_millis().try_into().unwrap_or(u32::MAX);
+        match result {
+            Ok(()) => Self {
+                reachable: true,
+                latency_ms,
+                error: None,
+            },
+            Err(err) => Self {
+                reachable: false,
+                latency_ms,
+                error: Some(format!("{:?}", err)),
+            },
+        }
+    }
+}
+
+/// The data plane could not be changed.
+#[derive(Debug, thiserror::Error)]
+pub enum DataPlaneError {
+    #[error("the data plane can't be changed when a custom transport is used")]
+    CustomTransport,
+}
+
+/// The HTTP settings of the default transport, unused with the `noop` feature.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "noop", allow(dead_code))]
+pub(crate) struct HttpOptions {
+    pub connect_timeout: Duration,
+    pub request_timeout: Option<Duration>,
+    pub keep_alive: Option<Duration>,
+    pub headers: HashMap<String, String>,
+    /// PEM encoded root certificates trusted in addition to the system ones.
+    pub root_certificates: Vec<Vec<u8>>,
+    /// Compress the bodies of batch requests with gzip.
+    #[cfg(feature = "gzip")]
+    pub gzip_batches: bool,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Some(Duration::from_secs(30)),
+            keep_alive: Some(Duration::from_secs(90)),
+            headers: HashMap::new(),
+            root_certificates: Vec::new(),
+            #[cfg(feature = "gzip")]
+            gzip_batches: false,
+        }
+    }
+}
+
+impl HttpOptions {
+    /// Create the default transport, sending messages to the data plane.
+    #[cfg(not(feature = "noop"))]
+    pub fn transport(&self, data_plane: String, key: String) -> impl Transport {
+        #[cfg(not(any(feature = "native-tls", feature = "rustls-tls")))]
+        if !self.root_certificates.is_empty() {
+            tracing::error!("Root certificates require the native-tls or rustls-tls feature");
+        }
+        let transport = HttpTransport::new(
+            self.async_client(),
+            self.blocking_transport(data_plane, key),
+        );
+        #[cfg(feature = "gzip")]
+        let transport = transport.with_gzip_batches(self.gzip_batches);
+        transport
+    }
+
+    /// With the `noop` feature the default transport discards every message.
+    #[cfg(feature = "noop")]
+    pub fn transport(&self, _data_plane: String, _key: String) -> impl Transport {
+        NoopTransport
+    }
+
+    /// The client of async deliveries.
+    #[cfg(not(feature = "noop"))]
+    fn async_client(&self) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .pool_idle_timeout(self.keep_alive)
+            .tcp_keepalive(self.keep_alive)
+            .default_headers(self.header_map());
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+        let builder = self
+            .root_certificates()
+            .into_iter()
+            .fold(builder, |builder, certificate| {
+                builder.add_root_certificate(certificate)
+            });
+        builder.build().unwrap_or_else(|err| {
+            tracing::error!(
+                "Failed to build the HTTP client, using the defaults: {:?}",
+                err
+            );
+            reqwest::Client::new()
+        })
+    }
+
+    /// The rudderanalytics client, for blocking deliveries and health checks.
+    #[cfg(not(feature = "noop"))]
+    fn blocking_transport(&self, data_plane: String, key: String) -> RudderAnalytics {
+        let builder = reqwest::blocking::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.request_timeout)
+            .pool_idle_timeout(self.keep_alive)
+            .tcp_keepalive(self.keep_alive)
+            .default_headers(self.header_map());
+        #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+        let builder = self
+            .root_certificates()
+            .into_iter()
+            .fold(builder, |builder, certificate| {
+                builder.add_root_certificate(certificate)
+            });
+        match builder.build() {
+            Ok(client) => RudderAnalytics {
+                write_key: key,
+                data_plane_url: data_plane,
+                client,
+            },
+            Err(err) => {
+                tracing::error!(
+                    "Failed to build the HTTP client, using the defaults: {:?}",
+                    err
+                );
+                RudderAnalytics::load(key, data_plane)
+            }
+        }
+    }
+
+    /// The extra headers, skipping invalid header names or values.
+    #[cfg(not(feature = "noop"))]
+    fn header_map(&self) -> HeaderMap {
+        self.headers
+            .iter()
+            .filter_map(|(name, value)| {
+                match (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+                    (Ok(name), Ok(value)) => Some((name, value)),
+                    _ => {
+                        tracing::error!("Invalid HTTP header {:?}, skipping it", name);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// The parsed root certificates, skipping invalid ones.
+    #[cfg(all(
+        any(feature = "native-tls", feature = "rustls-tls"),
+        not(feature = "noop")
+    ))]
+    fn root_certificates(&self) -> Vec<reqwest::Certificate> {
+        self.root_certificates
+            .iter()
+            .filter_map(|pem| match reqwest::Certificate::from_pem(pem) {
+                Ok(certificate) => Some(certificate),
+                Err(err) => {
+                    tracing::error!("Invalid root certificate, skipping it: {:?}", err);
+                    None
+                }
+            })
+            .collect()
+    }
+}
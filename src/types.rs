@@ -5,6 +5,10 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::{rudderanalytics, Priority};
+
+pub mod ecommerce;
+
 pub(crate) type Context = serde_json::Map<String, serde_json::Value>;
 
 /// An enum containing all values which may be sent to RudderStack's API.
@@ -19,6 +23,80 @@ pub enum Message {
     Batch(Batch),
 }
 
+impl Message {
+    /// The `integrations` field of the message.
+    pub fn integrations_mut(&mut self) -> &mut Option<Value> {
+        match self {
+            Message::Identify(identify) => &mut identify.integrations,
+            Message::Track(track) => &mut track.integrations,
+            Message::Page(page) => &mut page.integrations,
+            Message::Screen(screen) => &mut screen.integrations,
+            Message::Group(group) => &mut group.integrations,
+            Message::Alias(alias) => &mut alias.integrations,
+            Message::Batch(batch) => &mut batch.integrations,
+        }
+    }
+
+    /// The `integrations` fields of the message and of every message in a batch.
+    pub(crate) fn all_integrations(&self) -> Vec<&Option<Value>> {
+        match self {
+            Message::Identify(identify) => vec![&identify.integrations],
+            Message::Track(track) => vec![&track.integrations],
+            Message::Page(page) => vec![&page.integrations],
+            Message::Screen(screen) => vec![&screen.integrations],
+            Message::Group(group) => vec![&group.integrations],
+            Message::Alias(alias) => vec![&alias.integrations],
+            Message::Batch(batch) => std::iter::once(&batch.integrations)
+                .chain(batch.batch.iter().map(|message| match message {
+                    BatchMessage::Identify(identify) => &identify.integrations,
+                    BatchMessage::Track(track) => &track.integrations,
+                    BatchMessage::Page(page) => &page.integrations,
+                    BatchMessage::Screen(screen) => &screen.integrations,
+                    BatchMessage::Group(group) => &group.integrations,
+                    BatchMessage::Alias(alias) => &alias.integrations,
+                }))
+                .collect(),
+        }
+    }
+
+    /// The `integrations` fields of the message and of every message in a batch.
+    pub(crate) fn all_integrations_mut(&mut self) -> Vec<&mut Option<Value>> {
+        match self {
+            Message::Batch(Batch {
+                batch,
+                integrations,
+                ..
+            }) => std::iter::once(integrations)
+                .chain(batch.iter_mut().map(BatchMessage::integrations_mut))
+                .collect(),
+            message => vec![message.integrations_mut()],
+        }
+    }
+
+    /// The priority of the message, see [Track::priority].
+    /// Identify and alias calls have [Priority::Identity].
+    pub fn priority(&self) -> Priority {
+        match self {
+            Message::Track(track) => track.priority,
+            Message::Identify(_) | Message::Alias(_) => Priority::Identity,
+            _ => Priority::Normal,
+        }
+    }
+
+    /// The `context` field of the message.
+    pub fn context_mut(&mut self) -> &mut Option<Value> {
+        match self {
+            Message::Identify(identify) => &mut identify.context,
+            Message::Track(track) => &mut track.context,
+            Message::Page(page) => &mut page.context,
+            Message::Screen(screen) => &mut screen.context,
+            Message::Group(group) => &mut group.context,
+            Message::Alias(alias) => &mut alias.context,
+            Message::Batch(batch) => &mut batch.context,
+        }
+    }
+}
+
 /// An identify event.
 /// The identify call lets you identify a visiting user and associate them to their actions. It also lets you record the traits about them like their name, email address, etc.
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
@@ -27,6 +105,11 @@ pub struct Identify {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub traits: Option<Value>,
 
+    /// The anonymous ID to send this event with instead of the stored one,
+    /// e.g. to attribute events to another local profile.
+    #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+    pub anonymous_id: Option<String>,
+
     /// The timestamp associated with this message.
     #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
     pub original_timestamp: Option<DateTime<Utc>>,
@@ -51,6 +134,28 @@ pub struct Track {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub properties: Option<Value>,
 
+    /// Always send this event, bypassing sampling, throttling and backpressure. \
+    /// Use this for business-critical events like purchases.
+    /// Only set from Rust, events sent from the webview can't bypass the limits.
+    #[serde(skip)]
+    pub force: bool,
+
+    /// Drop the event instead of sending it after this deadline, e.g. for impressions
+    /// that are worthless once stale.
+    #[serde(rename = "sendBy", default, skip_serializing_if = "Option::is_none")]
+    pub send_by: Option<DateTime<Utc>>,
+
+    /// The priority class of the event, higher priorities are delivered first
+    /// and `debug` events are dropped first.
+    /// Only set from Rust, events sent from the webview can't jump the queue.
+    #[serde(skip)]
+    pub priority: Priority,
+
+    /// The anonymous ID to send this event with instead of the stored one,
+    /// e.g. to attribute events to another local profile.
+    #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+    pub anonymous_id: Option<String>,
+
     /// The timestamp associated with this message.
     #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
     pub original_timestamp: Option<DateTime<Utc>>,
@@ -77,6 +182,11 @@ pub struct Page {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub properties: Option<Value>,
 
+    /// The anonymous ID to send this event with instead of the stored one,
+    /// e.g. to attribute events to another local profile.
+    #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+    pub anonymous_id: Option<String>,
+
     /// The timestamp associated with this message.
     #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
     pub original_timestamp: Option<DateTime<Utc>>,
@@ -105,6 +215,11 @@ pub struct Screen {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub properties: Option<Value>,
 
+    /// The anonymous ID to send this event with instead of the stored one,
+    /// e.g. to attribute events to another local profile.
+    #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+    pub anonymous_id: Option<String>,
+
     /// The timestamp associated with this message.
     #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
     pub original_timestamp: Option<DateTime<Utc>>,
@@ -131,6 +246,11 @@ pub struct Group {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub traits: Option<Value>,
 
+    /// The anonymous ID to send this event with instead of the stored one,
+    /// e.g. to attribute events to another local profile.
+    #[serde(rename = "anonymousId", skip_serializing_if = "Option::is_none")]
+    pub anonymous_id: Option<String>,
+
     /// The timestamp associated with this message.
     #[serde(rename = "originalTimestamp", skip_serializing_if = "Option::is_none")]
     pub original_timestamp: Option<DateTime<Utc>>,
@@ -215,6 +335,20 @@ pub enum BatchMessage {
     Alias(Alias),
 }
 
+impl BatchMessage {
+    /// The `integrations` field of the message.
+    pub fn integrations_mut(&mut self) -> &mut Option<Value> {
+        match self {
+            BatchMessage::Identify(identify) => &mut identify.integrations,
+            BatchMessage::Track(track) => &mut track.integrations,
+            BatchMessage::Page(page) => &mut page.integrations,
+            BatchMessage::Screen(screen) => &mut screen.integrations,
+            BatchMessage::Group(group) => &mut group.integrations,
+            BatchMessage::Alias(alias) => &mut alias.integrations,
+        }
+    }
+}
+
 /// Converts a [Message] to a [rudderanalytics::message::Message].
 pub(crate) fn convert_message(message: Message) -> rudderanalytics::message::Message {
     match message {
@@ -239,7 +373,7 @@ pub(crate) fn convert_message(message: Message) -> rudderanalytics::message::Mes
         Message::Group(group) => {
             rudderanalytics::message::Message::Group(rudderanalytics::message::Group {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: group.anonymous_id,
                 group_id: group.group_id,
                 traits: group.traits,
                 original_timestamp: group.original_timestamp,
@@ -250,7 +384,7 @@ pub(crate) fn convert_message(message: Message) -> rudderanalytics::message::Mes
         Message::Identify(identify) => {
             rudderanalytics::message::Message::Identify(rudderanalytics::message::Identify {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: identify.anonymous_id,
                 traits: identify.traits,
                 original_timestamp: identify.original_timestamp,
                 context: identify.context,
@@ -260,7 +394,7 @@ pub(crate) fn convert_message(message: Message) -> rudderanalytics::message::Mes
         Message::Page(page) => {
             rudderanalytics::message::Message::Page(rudderanalytics::message::Page {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: page.anonymous_id,
                 name: page.name,
                 properties: page.properties,
                 original_timestamp: page.original_timestamp,
@@ -271,7 +405,7 @@ pub(crate) fn convert_message(message: Message) -> rudderanalytics::message::Mes
         Message::Screen(screen) => {
             rudderanalytics::message::Message::Screen(rudderanalytics::message::Screen {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: screen.anonymous_id,
                 name: screen.name,
                 properties: screen.properties,
                 original_timestamp: screen.original_timestamp,
@@ -282,7 +416,7 @@ pub(crate) fn convert_message(message: Message) -> rudderanalytics::message::Mes
         Message::Track(track) => {
             rudderanalytics::message::Message::Track(rudderanalytics::message::Track {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: track.anonymous_id,
                 event: track.event,
                 properties: track.properties,
                 original_timestamp: track.original_timestamp,
@@ -309,7 +443,7 @@ fn convert_batch_message(batch_message: BatchMessage) -> rudderanalytics::messag
         BatchMessage::Group(group) => {
             rudderanalytics::message::BatchMessage::Group(rudderanalytics::message::Group {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: group.anonymous_id,
                 group_id: group.group_id,
                 traits: group.traits,
                 original_timestamp: group.original_timestamp,
@@ -320,7 +454,7 @@ fn convert_batch_message(batch_message: BatchMessage) -> rudderanalytics::messag
         BatchMessage::Identify(identify) => {
             rudderanalytics::message::BatchMessage::Identify(rudderanalytics::message::Identify {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: identify.anonymous_id,
                 traits: identify.traits,
                 original_timestamp: identify.original_timestamp,
                 context: identify.context,
@@ -330,7 +464,7 @@ fn convert_batch_message(batch_message: BatchMessage) -> rudderanalytics::messag
         BatchMessage::Page(page) => {
             rudderanalytics::message::BatchMessage::Page(rudderanalytics::message::Page {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: page.anonymous_id,
                 name: page.name,
                 properties: page.properties,
                 original_timestamp: page.original_timestamp,
@@ -341,7 +475,7 @@ fn convert_batch_message(batch_message: BatchMessage) -> rudderanalytics::messag
         BatchMessage::Screen(screen) => {
             rudderanalytics::message::BatchMessage::Screen(rudderanalytics::message::Screen {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: screen.anonymous_id,
                 name: screen.name,
                 properties: screen.properties,
                 original_timestamp: screen.original_timestamp,
@@ -352,7 +486,7 @@ fn convert_batch_message(batch_message: BatchMessage) -> rudderanalytics::messag
         BatchMessage::Track(track) => {
             rudderanalytics::message::BatchMessage::Track(rudderanalytics::message::Track {
                 user_id: None,
-                anonymous_id: None,
+                anonymous_id: track.anonymous_id,
                 event: track.event,
                 properties: track.properties,
                 original_timestamp: track.original_timestamp,
@@ -362,3 +496,62 @@ fn convert_batch_message(batch_message: BatchMessage) -> rudderanalytics::messag
         }
     }
 }
+
+/// A typed builder for the `integrations` field of a message.
+///
+/// ```ignore
+/// let integrations = Integrations::all(false)
+///     .enable("Amplitude")
+///     .enable_with_options("Braze", json!({ "appId": "..." }));
+///
+/// let track = Track {
+///     event: "Purchase".to_string(),
+///     integrations: Some(integrations.into()),
+///     ..Track::default()
+/// };
+/// ```
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Integrations(serde_json::Map<String, Value>);
+
+impl Integrations {
+    /// Route the message to all destinations by default, or to none if `enabled` is false.
+    pub fn all(enabled: bool) -> Self {
+        Self::default().set("All", Value::Bool(enabled))
+    }
+
+    /// Route the message to this destination.
+    pub fn enable(self, destination: impl Into<String>) -> Self {
+        self.set(destination, Value::Bool(true))
+    }
+
+    /// Do not route the message to this destination.
+    pub fn disable(self, destination: impl Into<String>) -> Self {
+        self.set(destination, Value::Bool(false))
+    }
+
+    /// Route the message to this destination, with destination specific options.
+    pub fn enable_with_options(self, destination: impl Into<String>, options: Value) -> Self {
+        self.set(destination, options)
+    }
+
+    /// Returns true if the message is routed to this destination.
+    pub fn is_enabled(&self, destination: &str) -> bool {
+        match self.0.get(destination) {
+            Some(Value::Bool(enabled)) => *enabled,
+            Some(_) => true,
+            None => !matches!(self.0.get("All"), Some(Value::Bool(false))),
+        }
+    }
+
+    fn set(mut self, destination: impl Into<String>, value: Value) -> Self {
+        self.0.insert(destination.into(), value);
+        self
+    }
+}
+
+impl From<Integrations> for Value {
+    fn from(integrations: Integrations) -> Self {
+        Value::Object(integrations.0)
+    }
+}
@@ -0,0 +1,159 @@
+//! Typed events of the [RudderStack ecommerce spec](https://www.rudderstack.com/docs/event-spec/ecommerce-events-spec/).
+//!
+//! Send them with [AnalyticsExt::track](crate::AnalyticsExt::track). The types are exported to the
+//! TypeScript bindings, use them as the `properties` of track events sent from the webview.
+
+use serde::{Deserialize, Serialize};
+
+use crate::TrackEvent;
+
+/// A product, used by the product events and in the `products` of cart, checkout and order events.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct Product {
+    /// The ID of the product in the database.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_id: Option<String>,
+    /// The stock keeping unit of the product.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brand: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// The price of a single unit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coupon: Option<String>,
+    /// The position of the product in a list.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_url: Option<String>,
+}
+
+/// `Products Searched`: the user searched for products.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct ProductsSearched {
+    pub query: String,
+}
+
+/// `Product Viewed`: the user viewed the details of a product.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct ProductViewed {
+    #[serde(flatten)]
+    pub product: Product,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+/// `Product Added`: the user added a product to the cart.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct ProductAdded {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cart_id: Option<String>,
+    #[serde(flatten)]
+    pub product: Product,
+}
+
+/// `Product Removed`: the user removed a product from the cart.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct ProductRemoved {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cart_id: Option<String>,
+    #[serde(flatten)]
+    pub product: Product,
+}
+
+/// `Cart Viewed`: the user viewed the cart.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct CartViewed {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cart_id: Option<String>,
+    pub products: Vec<Product>,
+}
+
+/// `Checkout Started`: the user started the checkout.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct CheckoutStarted {
+    #[serde(flatten)]
+    pub order: Order,
+}
+
+/// `Order Completed`: the user completed the order.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct OrderCompleted {
+    #[serde(flatten)]
+    pub order: Order,
+}
+
+/// `Order Refunded`: the order was refunded, fully or only the listed `products`.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct OrderRefunded {
+    #[serde(flatten)]
+    pub order: Order,
+}
+
+/// `Order Cancelled`: the order was cancelled.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct OrderCancelled {
+    #[serde(flatten)]
+    pub order: Order,
+}
+
+/// The properties shared by the checkout and order events.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Default, specta::Type)]
+pub struct Order {
+    pub order_id: String,
+    /// The store or affiliation the order is attributed to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub affiliation: Option<String>,
+    /// The total value of the order, after discounts, including shipping and taxes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    /// The revenue of the order, after discounts, excluding shipping and taxes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revenue: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipping: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discount: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coupon: Option<String>,
+    /// The currency of the order in ISO 4217 format, e.g. `USD`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    pub products: Vec<Product>,
+}
+
+macro_rules! track_event {
+    ($($type:ty => $name:literal),* $(,)?) => {
+        $(
+            impl TrackEvent for $type {
+                const NAME: &'static str = $name;
+            }
+        )*
+    };
+}
+
+track_event! {
+    ProductsSearched => "Products Searched",
+    ProductViewed => "Product Viewed",
+    ProductAdded => "Product Added",
+    ProductRemoved => "Product Removed",
+    CartViewed => "Cart Viewed",
+    CheckoutStarted => "Checkout Started",
+    OrderCompleted => "Order Completed",
+    OrderRefunded => "Order Refunded",
+    OrderCancelled => "Order Cancelled",
+}
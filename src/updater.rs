@@ -0,0 +1,63 @@
+//! Analytics for [tauri-plugin-updater](tauri_plugin_updater).
+//!
+//! Use these functions instead of the ones of the updater to send `Update Available`
+//! and `Update Downloaded` events. `Update Installed` is sent on the first launch
+//! after the version of the app changed.
+
+use serde_json::json;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_updater::{Update, UpdaterExt as _};
+
+use crate::{types::Track, AnalyticsExt as _};
+
+/// Check for an update, sending an `Update Available` event if there is one.
+pub async fn check<R: Runtime>(app: &AppHandle<R>) -> tauri_plugin_updater::Result<Option<Update>> {
+    let update = app.updater()?.check().await?;
+    if let Some(update) = &update {
+        send(app, "Update Available", update);
+    }
+    Ok(update)
+}
+
+/// Download and install the update, sending an `Update Downloaded` event before it is installed.
+/// The callbacks are passed to [Update::download].
+pub async fn download_and_install<R: Runtime, C: FnMut(usize, Option<u64>), D: FnOnce()>(
+    app: &AppHandle<R>,
+    update: &Update,
+    on_chunk: C,
+    on_download_finish: D,
+) -> tauri_plugin_updater::Result<()> {
+    let bytes = update.download(on_chunk, on_download_finish).await?;
+    send(app, "Update Downloaded", update);
+    update.install(bytes)
+}
+
+fn send<R: Runtime>(app: &AppHandle<R>, event: &str, update: &Update) {
+    app.send_analytic_track(Track {
+        event: event.to_string(),
+        properties: Some(json!({
+            "current_version": update.current_version,
+            "version": update.version,
+        })),
+        ..Track::default()
+    });
+}
+
+/// Send an `Update Installed` event if the version of the app changed since the last launch.
+pub(crate) fn installed<R: Runtime>(
+    instance: &crate::Instance<R>,
+    previous_version: Option<String>,
+) {
+    let version = instance.handle.package_info().version.to_string();
+    let Some(previous_version) = previous_version.filter(|previous| *previous != version) else {
+        return;
+    };
+    instance.send_analytic_track(Track {
+        event: "Update Installed".to_string(),
+        properties: Some(json!({
+            "previous_version": previous_version,
+            "version": version,
+        })),
+        ..Track::default()
+    });
+}
@@ -0,0 +1,61 @@
+//! Restricts which webviews may invoke the commands,
+//! see [RudderStackBuilder::allow_webview](crate::RudderStackBuilder::allow_webview).
+
+/// The label patterns of the webviews allowed to invoke the commands, empty allows all webviews.
+#[derive(Clone, Default)]
+pub(crate) struct WebviewScope {
+    patterns: Vec<String>,
+}
+
+impl WebviewScope {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+
+    pub fn is_allowed(&self, label: &str) -> bool {
+        self.patterns.is_empty() || self.patterns.iter().any(|pattern| matches(pattern, label))
+    }
+}
+
+/// Glob match, `*` matches any sequence of characters.
+fn matches(pattern: &str, label: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // split always yields at least one part
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = label.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // no `*` in the pattern
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_labels_by_glob() {
+        let scope = WebviewScope::new(vec!["main".to_string(), "settings-*".to_string()]);
+        assert!(scope.is_allowed("main"));
+        assert!(scope.is_allowed("settings-"));
+        assert!(scope.is_allowed("settings-general"));
+        assert!(!scope.is_allowed("main-embed"));
+        assert!(!scope.is_allowed("third-party"));
+
+        assert!(matches("*-panel-*", "left-panel-1"));
+        assert!(!matches("*-panel-*", "left-panel"));
+        assert!(matches("a*b*b", "abb"));
+        assert!(!matches("a*b*b", "ab"));
+        assert!(WebviewScope::default().is_allowed("anything"));
+    }
+}
@@ -0,0 +1,44 @@
+use serde_json::{json, Map, Value};
+use tauri::{Runtime, Window};
+
+use crate::types::Message;
+
+/// Add the label, logical size, scale factor and monitor of the window to the properties
+/// of a Screen event, keeping the properties set by the caller.
+pub(crate) fn enrich_screen<R: Runtime>(window: &Window<R>, message: &mut Message) {
+    let Message::Screen(screen) = message else {
+        return;
+    };
+    let properties = screen
+        .properties
+        .get_or_insert_with(|| Value::Object(Map::new()));
+    let Value::Object(properties) = properties else {
+        return;
+    };
+    for (key, value) in metadata(window) {
+        properties.entry(key).or_insert(value);
+    }
+}
+
+fn metadata<R: Runtime>(window: &Window<R>) -> Map<String, Value> {
+    let mut metadata = Map::new();
+    metadata.insert("window_label".to_string(), json!(window.label()));
+    let scale_factor = window.scale_factor().ok();
+    if let Some(scale_factor) = scale_factor {
+        metadata.insert("scale_factor".to_string(), json!(scale_factor));
+    }
+    if let Ok(size) = window.inner_size() {
+        let size = size.to_logical::<f64>(scale_factor.unwrap_or(1.0));
+        metadata.insert("window_width".to_string(), json!(size.width));
+        metadata.insert("window_height".to_string(), json!(size.height));
+    }
+    if let Some(name) = window
+        .current_monitor()
+        .ok()
+        .flatten()
+        .and_then(|monitor| monitor.name().cloned())
+    {
+        metadata.insert("monitor".to_string(), json!(name));
+    }
+    metadata
+}